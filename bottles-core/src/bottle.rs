@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::component::Kind;
+use crate::snapshot::Retention;
+
+/// Icons a bottle can be decorated with.
+pub const ICONS: [&str; 4] = [
+    "xbox-controller-symbolic",
+    "build-alt-symbolic",
+    "magic-wand-symbolic",
+    "issue-symbolic",
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bottle {
+    pub id: String,
+    pub name: String,
+    pub environment: Environment,
+    /// Id of the runner used to start programs in this bottle.
+    #[serde(default)]
+    pub runner: String,
+    /// One of [`ICONS`], defaults to the icon of the environment.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// How many snapshots of the prefix are kept.
+    #[serde(default)]
+    pub retention: Retention,
+    #[serde(default)]
+    pub settings: Settings,
+    /// Versions of the components installed in the prefix.
+    #[serde(default)]
+    pub components: BTreeMap<Kind, String>,
+    pub created: DateTime<Utc>,
+    /// Folder holding the manifest and the prefix, filled in when loaded.
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Bottle {
+    pub fn new(
+        name: impl Into<String>,
+        environment: Environment,
+        runner: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.into(),
+            environment,
+            runner: runner.into(),
+            icon: None,
+            retention: Retention::default(),
+            settings: Settings::for_environment(environment),
+            components: BTreeMap::new(),
+            created: Utc::now(),
+            path: PathBuf::new(),
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        self.icon
+            .as_deref()
+            .and_then(|icon| ICONS.into_iter().find(|i| *i == icon))
+            .unwrap_or(self.environment.icon())
+    }
+
+    /// The Wine prefix of this bottle.
+    pub fn prefix(&self) -> PathBuf {
+        self.path.join("prefix")
+    }
+}
+
+/// How programs are started in a bottle.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub windows: WindowsVersion,
+    /// Translate Direct3D 9 to 11 to Vulkan, when DXVK is installed in the prefix.
+    pub dxvk: bool,
+    /// Translate Direct3D 12 to Vulkan, when VKD3D-Proton is installed in the prefix.
+    pub vkd3d: bool,
+    pub esync: bool,
+    pub fsync: bool,
+    /// Start programs through `gamemoderun`.
+    pub gamemode: bool,
+    /// Start programs through `mangohud`.
+    pub mangohud: bool,
+    /// Variables added to the environment of every program, in order.
+    pub env: Vec<(String, String)>,
+}
+
+impl Settings {
+    pub fn for_environment(environment: Environment) -> Self {
+        match environment {
+            Environment::Gaming => Self {
+                dxvk: true,
+                vkd3d: true,
+                esync: true,
+                fsync: true,
+                gamemode: true,
+                ..Default::default()
+            },
+            Environment::Software | Environment::Custom => Self::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowsVersion {
+    Windows11,
+    #[default]
+    Windows10,
+    Windows81,
+    Windows7,
+    WindowsXp,
+}
+
+impl WindowsVersion {
+    pub const ALL: [Self; 5] = [
+        Self::Windows11,
+        Self::Windows10,
+        Self::Windows81,
+        Self::Windows7,
+        Self::WindowsXp,
+    ];
+
+    /// Titles of [`Self::ALL`], in the same order.
+    pub const TITLES: [&'static str; 5] = [
+        "Windows 11",
+        "Windows 10",
+        "Windows 8.1",
+        "Windows 7",
+        "Windows XP",
+    ];
+
+    pub fn title(&self) -> &'static str {
+        Self::TITLES[self.index()]
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|version| version == self)
+            .expect("every version is listed")
+    }
+
+    /// The name `winecfg` knows the version by.
+    pub fn winecfg(&self) -> &'static str {
+        match self {
+            Self::Windows11 => "win11",
+            Self::Windows10 => "win10",
+            Self::Windows81 => "win81",
+            Self::Windows7 => "win7",
+            Self::WindowsXp => "winxp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Environment {
+    #[default]
+    Gaming,
+    Software,
+    Custom,
+}
+
+impl Environment {
+    pub const ALL: [Self; 3] = [Self::Gaming, Self::Software, Self::Custom];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Gaming => "Gaming",
+            Self::Software => "Software",
+            Self::Custom => "Custom",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Gaming => "Games, launchers and game engines.",
+            Self::Software => "Productivity and development tools.",
+            Self::Custom => "A clean slate for experiments.",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Gaming => "xbox-controller-symbolic",
+            Self::Software => "build-alt-symbolic",
+            Self::Custom => "issue-symbolic",
+        }
+    }
+}
@@ -0,0 +1,371 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! DXVK and VKD3D-Proton, which run Direct3D over Vulkan.
+//!
+//! Installing a [`Release`] in a bottle copies its DLLs over the builtin ones of
+//! Wine in `system32` and `syswow64` and overrides them to load as native. The
+//! version is recorded in the bottle manifest, and what changed in the prefix in
+//! a [`Ledger`] next to it, so uninstalling puts the builtin DLLs back and
+//! removes the overrides. Whether programs use an installed component is still
+//! up to the DXVK and VKD3D settings of the bottle.
+//!
+//! The releases shipped with the application can be extended by a
+//! [`CATALOG`] in the data directory.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::bottle::Bottle;
+use crate::dependency::{self, Installed};
+use crate::error::{Error, Result};
+use crate::fetch::{self, Fetcher};
+use crate::runner::{self, Runner};
+use crate::store::{self, read_ron, write_ron, BottleStore};
+
+const BUILTIN: &str = include_str!("../components.ron");
+
+/// Name of the catalog of extra releases in the data directory.
+pub const CATALOG: &str = "components-catalog.ron";
+
+/// What installing each component changed in a bottle.
+pub type Ledger = BTreeMap<Kind, Installed>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Kind {
+    /// Direct3D 8 to 11.
+    Dxvk,
+    /// Direct3D 12.
+    Vkd3d,
+}
+
+impl Kind {
+    pub const ALL: [Self; 2] = [Self::Dxvk, Self::Vkd3d];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Dxvk => "DXVK",
+            Self::Vkd3d => "VKD3D-Proton",
+        }
+    }
+
+    /// Names the folder the DLLs replaced by the component are kept in. The
+    /// prefix keeps it apart from the folders of dependency packages, which are
+    /// named after the package.
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Dxvk => "component-dxvk",
+            Self::Vkd3d => "component-vkd3d-proton",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    pub kind: Kind,
+    pub version: String,
+    /// A tarball with the 64-bit DLLs in an `x64` folder and the 32-bit ones in
+    /// an `x32` or `x86` folder.
+    pub url: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl Release {
+    pub fn title(&self) -> String {
+        format!("{} {}", self.kind.title(), self.version)
+    }
+}
+
+/// The releases that can be installed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Catalog {
+    releases: Vec<Release>,
+}
+
+impl Catalog {
+    pub fn new(releases: Vec<Release>) -> Self {
+        Self { releases }
+    }
+
+    /// The built-in catalog merged with the one in the data directory, sorted by
+    /// kind and from the newest version to the oldest.
+    pub async fn load() -> Self {
+        let mut releases: Vec<Release> = match ron::from_str(BUILTIN) {
+            Ok(releases) => releases,
+            Err(why) => {
+                eprintln!("invalid built-in components: {why}");
+                vec![]
+            }
+        };
+
+        let path = store::data_dir().join(CATALOG);
+        if fs::try_exists(&path).await.unwrap_or_default() {
+            match read_ron::<Vec<Release>>(&path).await {
+                Ok(extra) => {
+                    for release in extra {
+                        releases.retain(|builtin| {
+                            builtin.kind != release.kind || builtin.version != release.version
+                        });
+                        releases.push(release);
+                    }
+                }
+                Err(why) => eprintln!("skipping components {}: {why}", path.display()),
+            }
+        }
+
+        releases.sort_by(|a, b| {
+            a.kind
+                .cmp(&b.kind)
+                .then_with(|| version_key(&b.version).cmp(&version_key(&a.version)))
+        });
+        Self { releases }
+    }
+
+    pub fn releases(&self) -> &[Release] {
+        &self.releases
+    }
+
+    pub fn get(&self, kind: Kind, version: &str) -> Option<&Release> {
+        self.releases
+            .iter()
+            .find(|release| release.kind == kind && release.version == version)
+    }
+}
+
+/// Installs and uninstalls components in bottles.
+#[derive(Clone, Debug)]
+pub struct Components {
+    fetcher: Arc<dyn Fetcher>,
+    /// Downloads, kept to install releases in other bottles without fetching them
+    /// again.
+    download_dir: PathBuf,
+}
+
+impl Components {
+    pub fn new(fetcher: Arc<dyn Fetcher>) -> Self {
+        Self {
+            fetcher,
+            download_dir: store::cache_dir().join("components"),
+        }
+    }
+
+    /// Keeps downloads under `dir` instead of the cache directory.
+    pub fn with_dir(self, dir: &Path) -> Self {
+        Self {
+            download_dir: dir.join("components"),
+            ..self
+        }
+    }
+
+    /// Installs `release` in `bottle`, replacing the version of the same kind
+    /// already installed, and returns the updated bottle. `progress` is called
+    /// with the fraction of the work done and what is being done.
+    pub async fn install(
+        &self,
+        store: &BottleStore,
+        runner: &dyn Runner,
+        bottle: &Bottle,
+        release: &Release,
+        progress: impl Fn(f32, String) + Send + Sync,
+    ) -> Result<Bottle> {
+        let kind = release.kind;
+        if bottle.components.get(&kind) == Some(&release.version) {
+            progress(1.0, "Done".to_string());
+            return Ok(bottle.clone());
+        }
+
+        let name = fetch::file_name(&release.url)
+            .ok_or_else(|| Error::Component(format!("invalid url {}", release.url)))?;
+        let archive = self.download_dir.join(name);
+        let status = format!("Downloading {}...", release.title());
+        fetch::download(
+            &*self.fetcher,
+            &release.url,
+            release.sha256.as_deref(),
+            &archive,
+            &|done| progress(done * 0.6, status.clone()),
+        )
+        .await?;
+
+        progress(0.6, format!("Unpacking {}...", release.title()));
+        let unpacked = self
+            .download_dir
+            .join(format!(".{}-{}.part", kind.id(), release.version));
+        if fs::try_exists(&unpacked).await? {
+            fs::remove_dir_all(&unpacked).await?;
+        }
+        fs::create_dir_all(&unpacked).await?;
+        let result = {
+            let (archive, unpacked) = (archive.clone(), unpacked.clone());
+            tokio::task::spawn_blocking(move || fetch::unpack(&archive, &unpacked))
+                .await
+                .map_err(|why| Error::Component(why.to_string()))
+                .and_then(|result| result)
+        };
+        let result = match result {
+            Ok(()) => {
+                self.replace(store, runner, bottle, release, &unpacked, &progress)
+                    .await
+            }
+            Err(why) => Err(why),
+        };
+        fs::remove_dir_all(&unpacked).await?;
+        let bottle = result?;
+        progress(1.0, "Done".to_string());
+        Ok(bottle)
+    }
+
+    /// Puts the builtin DLLs replaced by the component `kind` back in `bottle`,
+    /// and returns the updated bottle.
+    pub async fn uninstall(
+        &self,
+        store: &BottleStore,
+        runner: &dyn Runner,
+        bottle: &Bottle,
+        kind: Kind,
+    ) -> Result<Bottle> {
+        let mut ledger = ledger(bottle).await?;
+        if let Some(installed) = ledger.remove(&kind) {
+            dependency::revert(runner, bottle, kind.id(), &installed).await?;
+            write_ron(&bottle.path.join(store::COMPONENTS), &ledger).await?;
+        }
+
+        let mut bottle = store.get(&bottle.id).await?;
+        if bottle.components.remove(&kind).is_some() {
+            store.save(&bottle).await?;
+        }
+        Ok(bottle)
+    }
+
+    /// Uninstalls the version of the same kind as `release`, then copies the
+    /// DLLs unpacked at `unpacked` into the prefix of `bottle` and overrides them.
+    /// The new DLLs are removed again when this fails.
+    async fn replace(
+        &self,
+        store: &BottleStore,
+        runner: &dyn Runner,
+        bottle: &Bottle,
+        release: &Release,
+        unpacked: &Path,
+        progress: &(dyn Fn(f32, String) + Send + Sync),
+    ) -> Result<Bottle> {
+        let kind = release.kind;
+        let root = fetch::single_folder(unpacked)
+            .await?
+            .unwrap_or(unpacked.to_path_buf());
+        if ledger(bottle).await?.contains_key(&kind) {
+            progress(0.7, format!("Removing {}...", kind.title()));
+            self.uninstall(store, runner, bottle, kind).await?;
+        }
+
+        progress(0.8, format!("Installing {}...", release.title()));
+        let mut installed = Installed::new();
+        let result = place_dlls(runner, bottle, kind, &root, &mut installed).await;
+        if let Err(why) = result {
+            if let Err(why) = dependency::revert(runner, bottle, kind.id(), &installed).await {
+                eprintln!(
+                    "failed to undo the installation of {}: {why}",
+                    release.title()
+                );
+            }
+            return Err(why);
+        }
+
+        let mut ledger = ledger(bottle).await?;
+        ledger.insert(kind, installed);
+        write_ron(&bottle.path.join(store::COMPONENTS), &ledger).await?;
+
+        // Read again so changes saved meanwhile aren't lost.
+        let mut bottle = store.get(&bottle.id).await?;
+        bottle.components.insert(kind, release.version.clone());
+        store.save(&bottle).await?;
+        Ok(bottle)
+    }
+}
+
+/// The components installed in `bottle`, with what they changed.
+pub async fn ledger(bottle: &Bottle) -> Result<Ledger> {
+    let path = bottle.path.join(store::COMPONENTS);
+    if !fs::try_exists(&path).await? {
+        return Ok(Ledger::new());
+    }
+    read_ron(&path).await
+}
+
+/// Copies the DLLs of each architecture to the system folder Windows loads them
+/// from, then overrides them to load as native.
+async fn place_dlls(
+    runner: &dyn Runner,
+    bottle: &Bottle,
+    kind: Kind,
+    root: &Path,
+    installed: &mut Installed,
+) -> Result<()> {
+    let prefix = bottle.prefix();
+    let windows = prefix.join("drive_c").join("windows");
+    // 32-bit prefixes have no syswow64, and keep the 32-bit DLLs in system32.
+    let targets: &[(&[&str], &str)] = if fs::try_exists(windows.join("syswow64")).await? {
+        &[(&["x64"], "system32"), (&["x32", "x86"], "syswow64")]
+    } else {
+        &[(&["x32", "x86"], "system32")]
+    };
+
+    let mut dlls = BTreeSet::new();
+    for (folders, target) in targets {
+        let Some(folder) = folders
+            .iter()
+            .map(|folder| root.join(folder))
+            .find(|folder| folder.is_dir())
+        else {
+            continue;
+        };
+        let mut entries = fs::read_dir(&folder).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_dll = path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("dll"));
+            let (Some(name), Some(stem)) = (path.file_name(), path.file_stem()) else {
+                continue;
+            };
+            if !is_dll || !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let destination = windows.join(target).join(name);
+            dependency::place(bottle, kind.id(), &path, &destination, installed).await?;
+            dlls.insert(stem.to_string_lossy().to_lowercase());
+        }
+    }
+    if dlls.is_empty() {
+        return Err(Error::Component(format!(
+            "no DLLs for this bottle in {}",
+            kind.title()
+        )));
+    }
+
+    for dll in dlls {
+        runner::set_registry(
+            runner,
+            &prefix,
+            runner::DLL_OVERRIDES,
+            &dll,
+            "native,builtin",
+        )
+        .await?;
+        installed.overrides.push(dll);
+    }
+    Ok(())
+}
+
+/// Orders versions such as `2.13` after `2.5.3`, number by number.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
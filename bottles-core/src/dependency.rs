@@ -0,0 +1,540 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Redistributables installed inside a bottle, such as the Visual C++ runtimes,
+//! .NET or the core fonts.
+//!
+//! A [`Manifest`] describes every [`Package`]: the files it is made of, how they
+//! are installed and the registry values and DLL overrides it needs. Each bottle
+//! keeps a [`Ledger`] of the packages installed in it along with what they
+//! changed in the prefix, so uninstalling a package puts back the files it
+//! replaced and removes its registry values and overrides. Changes made by
+//! installers run for a package stay behind, as there is no telling what they did.
+//!
+//! The manifest shipped with the application can be extended, or its packages
+//! replaced, by a `dependencies.ron` manifest in the data directory.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::process::Command;
+
+use crate::bottle::Bottle;
+use crate::error::{Error, Result};
+use crate::fetch::{self, Fetcher};
+use crate::lnk;
+use crate::runner::{self, Launch, Runner};
+use crate::store::{self, read_ron, write_ron};
+
+const BUILTIN: &str = include_str!("../dependencies.ron");
+
+/// Packages installed in a bottle, by id.
+pub type Ledger = BTreeMap<String, Installed>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Ids of the packages installed before this one.
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Files downloaded before the actions run.
+    #[serde(default)]
+    pub sources: Vec<Source>,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Source {
+    pub url: String,
+    /// Name the download is saved as, which actions refer to it by.
+    pub file: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    /// Runs a downloaded installer in the bottle and waits for it to exit
+    /// successfully.
+    Run {
+        file: String,
+        #[serde(default)]
+        arguments: Vec<String>,
+    },
+    /// Extracts the files matching `filter` from a cabinet archive with
+    /// cabextract, into `destination` when it is given as a Windows path, or
+    /// next to the downloads for the following actions otherwise.
+    Extract {
+        file: String,
+        #[serde(default)]
+        filter: Option<String>,
+        #[serde(default)]
+        destination: Option<String>,
+    },
+    /// Copies a downloaded or extracted file to `destination`, a Windows folder.
+    Copy { file: String, destination: String },
+    /// Sets a string value in the registry of the bottle.
+    Registry {
+        key: String,
+        name: String,
+        value: String,
+    },
+    /// Sets how Wine loads `dll`, such as `native,builtin`.
+    Override { dll: String, mode: String },
+}
+
+/// What installing a package changed in a bottle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Installed {
+    pub installed: DateTime<Utc>,
+    /// Files added to the prefix, relative to it.
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// Files of the prefix that were replaced, relative to it. The originals
+    /// are kept aside until the package is uninstalled.
+    #[serde(default)]
+    pub replaced: Vec<PathBuf>,
+    /// Registry values set, as key and name.
+    #[serde(default)]
+    pub registry: Vec<(String, String)>,
+    /// DLLs given an override.
+    #[serde(default)]
+    pub overrides: Vec<String>,
+}
+
+impl Installed {
+    pub(crate) fn new() -> Self {
+        Self {
+            installed: Utc::now(),
+            files: vec![],
+            replaced: vec![],
+            registry: vec![],
+            overrides: vec![],
+        }
+    }
+}
+
+/// The packages that can be installed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    packages: Vec<Package>,
+}
+
+impl Manifest {
+    pub fn new(packages: Vec<Package>) -> Self {
+        Self { packages }
+    }
+
+    /// The built-in manifest merged with the one in the data directory, sorted
+    /// by name.
+    pub async fn load() -> Self {
+        let mut packages: Vec<Package> = match ron::from_str(BUILTIN) {
+            Ok(packages) => packages,
+            Err(why) => {
+                eprintln!("invalid built-in dependencies: {why}");
+                vec![]
+            }
+        };
+
+        let path = store::data_dir().join("dependencies.ron");
+        if fs::try_exists(&path).await.unwrap_or_default() {
+            match read_ron::<Vec<Package>>(&path).await {
+                Ok(extra) => {
+                    for package in extra {
+                        packages.retain(|builtin| builtin.id != package.id);
+                        packages.push(package);
+                    }
+                }
+                Err(why) => eprintln!("skipping dependencies {}: {why}", path.display()),
+            }
+        }
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { packages }
+    }
+
+    pub fn packages(&self) -> &[Package] {
+        &self.packages
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Package> {
+        self.packages.iter().find(|package| package.id == id)
+    }
+
+    /// The package `id` preceded by the packages it requires, each listed once
+    /// and after its own requirements.
+    pub fn resolve(&self, id: &str) -> Result<Vec<&Package>> {
+        let mut resolved = vec![];
+        self.visit(id, &mut vec![], &mut resolved)?;
+        Ok(resolved)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        id: &str,
+        path: &mut Vec<&'a str>,
+        resolved: &mut Vec<&'a Package>,
+    ) -> Result<()> {
+        if resolved.iter().any(|package| package.id == id) {
+            return Ok(());
+        }
+        let package = self
+            .get(id)
+            .ok_or_else(|| Error::Dependency(format!("unknown package {id}")))?;
+        if path.contains(&package.id.as_str()) {
+            return Err(Error::Dependency(format!("{id} requires itself")));
+        }
+
+        path.push(&package.id);
+        for requirement in &package.requires {
+            self.visit(requirement, path, resolved)?;
+        }
+        path.pop();
+        resolved.push(package);
+        Ok(())
+    }
+}
+
+/// Installs and uninstalls the packages of a [`Manifest`].
+#[derive(Clone, Debug)]
+pub struct Dependencies {
+    manifest: Manifest,
+    fetcher: Arc<dyn Fetcher>,
+    /// Downloads, kept to install packages in other bottles without fetching them
+    /// again.
+    download_dir: PathBuf,
+}
+
+impl Dependencies {
+    pub fn new(manifest: Manifest, fetcher: Arc<dyn Fetcher>) -> Self {
+        Self {
+            manifest,
+            fetcher,
+            download_dir: store::cache_dir().join("dependencies"),
+        }
+    }
+
+    /// Keeps downloads under `dir` instead of the cache directory.
+    pub fn with_dir(self, dir: &Path) -> Self {
+        Self {
+            download_dir: dir.join("dependencies"),
+            ..self
+        }
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Installs the package `id` in `bottle`, after the packages it requires that
+    /// aren't installed yet. `progress` is called with the fraction of the work
+    /// done and what is being done.
+    pub async fn install(
+        &self,
+        runner: &dyn Runner,
+        bottle: &Bottle,
+        id: &str,
+        progress: impl Fn(f32, String) + Send + Sync,
+    ) -> Result<()> {
+        let mut ledger = ledger(bottle).await?;
+        let pending: Vec<&Package> = self
+            .manifest
+            .resolve(id)?
+            .into_iter()
+            .filter(|package| !ledger.contains_key(&package.id))
+            .collect();
+
+        for (index, package) in pending.iter().enumerate() {
+            let report = |done: f32, status: String| {
+                progress((index as f32 + done) / pending.len() as f32, status)
+            };
+            let installed = self
+                .install_package(runner, bottle, package, &report)
+                .await?;
+            ledger.insert(package.id.clone(), installed);
+            write_ron(&bottle.path.join(store::DEPENDENCIES), &ledger).await?;
+        }
+        progress(1.0, "Done".to_string());
+        Ok(())
+    }
+
+    /// Uninstalls the package `id` from `bottle`, unless another installed
+    /// package requires it.
+    pub async fn uninstall(&self, runner: &dyn Runner, bottle: &Bottle, id: &str) -> Result<()> {
+        let mut ledger = ledger(bottle).await?;
+        let Some(installed) = ledger.get(id) else {
+            return Ok(());
+        };
+
+        let dependents: Vec<&str> = ledger
+            .keys()
+            .filter_map(|other| self.manifest.get(other))
+            .filter(|package| package.requires.iter().any(|required| required == id))
+            .map(|package| package.name.as_str())
+            .collect();
+        if !dependents.is_empty() {
+            return Err(Error::Dependency(format!(
+                "{id} is needed by {}",
+                dependents.join(", ")
+            )));
+        }
+
+        revert(runner, bottle, id, installed).await?;
+        ledger.remove(id);
+        write_ron(&bottle.path.join(store::DEPENDENCIES), &ledger).await
+    }
+
+    /// Downloads and installs `package`, undoing what was done when an action
+    /// fails.
+    async fn install_package(
+        &self,
+        runner: &dyn Runner,
+        bottle: &Bottle,
+        package: &Package,
+        report: &(dyn Fn(f32, String) + Send + Sync),
+    ) -> Result<Installed> {
+        let downloads = self.download_dir.join(&package.id);
+        let steps = (package.sources.len() + package.actions.len()) as f32;
+
+        for (index, source) in package.sources.iter().enumerate() {
+            let status = format!("Downloading {}...", source.file);
+            let progress = |done: f32| report((index as f32 + done) / steps, status.clone());
+            progress(0.0);
+            fetch::download(
+                &*self.fetcher,
+                &source.url,
+                source.sha256.as_deref(),
+                &downloads.join(&source.file),
+                &progress,
+            )
+            .await?;
+        }
+
+        let mut installed = Installed::new();
+        for (index, action) in package.actions.iter().enumerate() {
+            report(
+                (package.sources.len() + index) as f32 / steps,
+                format!("Installing {}...", package.name),
+            );
+            let result = apply(runner, bottle, package, &downloads, action, &mut installed).await;
+            if let Err(why) = result {
+                if let Err(why) = revert(runner, bottle, &package.id, &installed).await {
+                    eprintln!("failed to undo the installation of {}: {why}", package.id);
+                }
+                return Err(why);
+            }
+        }
+        Ok(installed)
+    }
+}
+
+/// The packages installed in `bottle`.
+pub async fn ledger(bottle: &Bottle) -> Result<Ledger> {
+    let path = bottle.path.join(store::DEPENDENCIES);
+    if !fs::try_exists(&path).await? {
+        return Ok(Ledger::new());
+    }
+    read_ron(&path).await
+}
+
+async fn apply(
+    runner: &dyn Runner,
+    bottle: &Bottle,
+    package: &Package,
+    downloads: &Path,
+    action: &Action,
+    installed: &mut Installed,
+) -> Result<()> {
+    let prefix = bottle.prefix();
+    match action {
+        Action::Run { file, arguments } => {
+            let launch = Launch {
+                executable: existing(downloads, file).await?,
+                arguments: arguments.clone(),
+                working_dir: Some(downloads.to_path_buf()),
+                ..Default::default()
+            };
+            let status = runner.spawn(&prefix, &launch)?.wait().await?;
+            if !status.success() {
+                return Err(Error::Dependency(format!("{file} exited with {status}")));
+            }
+        }
+        Action::Extract {
+            file,
+            filter,
+            destination,
+        } => {
+            let archive = existing(downloads, file).await?;
+            let cabextract = runner::which("cabextract").ok_or_else(|| {
+                Error::Dependency(format!("cabextract is needed to install {}", package.name))
+            })?;
+
+            let extracted = downloads.join(format!("{file}.d"));
+            if fs::try_exists(&extracted).await? {
+                fs::remove_dir_all(&extracted).await?;
+            }
+            let mut command = Command::new(cabextract);
+            command.arg("-q").arg("-d").arg(&extracted);
+            if let Some(filter) = filter {
+                command.arg("-F").arg(filter);
+            }
+            let status = command.arg(&archive).status().await?;
+            if !status.success() {
+                return Err(Error::Dependency(format!(
+                    "cabextract exited with {status} for {file}"
+                )));
+            }
+
+            if let Some(destination) = destination {
+                let destination = host_path(&prefix, destination)?;
+                for source in files(&extracted).await? {
+                    let relative = source.strip_prefix(&extracted).unwrap_or(&source);
+                    place(
+                        bottle,
+                        &package.id,
+                        &source,
+                        &destination.join(relative),
+                        installed,
+                    )
+                    .await?;
+                }
+            } else {
+                // Later actions refer to the extracted files by name.
+                for source in files(&extracted).await? {
+                    if let Some(name) = source.file_name() {
+                        fs::rename(&source, downloads.join(name)).await?;
+                    }
+                }
+            }
+            fs::remove_dir_all(&extracted).await?;
+        }
+        Action::Copy { file, destination } => {
+            let source = existing(downloads, file).await?;
+            let destination = host_path(&prefix, destination)?.join(file);
+            place(bottle, &package.id, &source, &destination, installed).await?;
+        }
+        Action::Registry { key, name, value } => {
+            runner::set_registry(runner, &prefix, key, name, value).await?;
+            installed.registry.push((key.clone(), name.clone()));
+        }
+        Action::Override { dll, mode } => {
+            runner::set_registry(runner, &prefix, runner::DLL_OVERRIDES, dll, mode).await?;
+            installed.overrides.push(dll.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Undoes the changes `installed` made to `bottle` for the package `id`.
+///
+/// Registry values that are already gone don't stop the rest from being undone.
+pub(crate) async fn revert(
+    runner: &dyn Runner,
+    bottle: &Bottle,
+    id: &str,
+    installed: &Installed,
+) -> Result<()> {
+    let prefix = bottle.prefix();
+    for dll in &installed.overrides {
+        if let Err(why) = runner::delete_registry(runner, &prefix, runner::DLL_OVERRIDES, dll).await
+        {
+            eprintln!("failed to remove the override of {dll}: {why}");
+        }
+    }
+    for (key, name) in &installed.registry {
+        if let Err(why) = runner::delete_registry(runner, &prefix, key, name).await {
+            eprintln!("failed to remove {key}\\{name}: {why}");
+        }
+    }
+
+    for file in &installed.files {
+        let path = prefix.join(file);
+        if fs::try_exists(&path).await? {
+            fs::remove_file(&path).await?;
+        }
+    }
+    let backups = backup_dir(bottle, id);
+    for file in &installed.replaced {
+        fs::rename(backups.join(file), prefix.join(file)).await?;
+    }
+    if fs::try_exists(&backups).await? {
+        fs::remove_dir_all(&backups).await?;
+    }
+    Ok(())
+}
+
+/// Copies `source` to `destination` in the prefix of `bottle`, keeping aside the
+/// file it replaces.
+pub(crate) async fn place(
+    bottle: &Bottle,
+    id: &str,
+    source: &Path,
+    destination: &Path,
+    installed: &mut Installed,
+) -> Result<()> {
+    let prefix = bottle.prefix();
+    let relative = destination
+        .strip_prefix(&prefix)
+        .map_err(|_| Error::Dependency(format!("{} is outside the bottle", destination.display())))?
+        .to_path_buf();
+
+    // Files placed by an earlier action of the same package have nothing to keep.
+    if !installed.files.contains(&relative) {
+        if fs::try_exists(destination).await? {
+            let backup = backup_dir(bottle, id).join(&relative);
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::rename(destination, &backup).await?;
+            installed.replaced.push(relative.clone());
+        }
+        installed.files.push(relative);
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::copy(source, destination).await?;
+    Ok(())
+}
+
+/// Where the files replaced by the package `id` are kept.
+fn backup_dir(bottle: &Bottle, id: &str) -> PathBuf {
+    bottle.path.join("backups").join(id)
+}
+
+fn host_path(prefix: &Path, windows: &str) -> Result<PathBuf> {
+    lnk::unix_path(&prefix.join("drive_c"), windows)
+        .ok_or_else(|| Error::Dependency(format!("{windows} isn't on drive C:")))
+}
+
+async fn existing(downloads: &Path, file: &str) -> Result<PathBuf> {
+    let path = downloads.join(file);
+    if !fs::try_exists(&path).await? {
+        return Err(Error::Dependency(format!("{file} is missing")));
+    }
+    Ok(path)
+}
+
+/// Every file under `root`.
+async fn files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
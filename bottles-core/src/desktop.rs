@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Launchers in the applications menu of the desktop.
+//!
+//! Each entry is a freedesktop `.desktop` file, in `$XDG_DATA_HOME/applications`
+//! unless told otherwise, that starts its program through a [`Uri::Run`] link,
+//! so the running window follows the program when there is one. Entries and
+//! their icons are named after the bottle and the program, which is how they're
+//! found again to be removed along with either of them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+
+use crate::bottle::{Bottle, Environment};
+use crate::error::Result;
+use crate::pe;
+use crate::program::Program;
+use crate::store;
+use crate::uri::Uri;
+
+/// Icon of the application, used by programs without an icon of their own.
+const APP_ICON: &str = "dev.edfloreshz.Bottles.Next";
+
+/// The entries of the applications menu, and the icons they show.
+#[derive(Clone, Debug)]
+pub struct Desktop {
+    applications: PathBuf,
+    icons: PathBuf,
+}
+
+impl Default for Desktop {
+    fn default() -> Self {
+        Self::new(applications_dir(), store::data_dir().join("icons"))
+    }
+}
+
+impl Desktop {
+    /// Keeps entries in `applications` and their icons in `icons`.
+    pub fn new(applications: impl Into<PathBuf>, icons: impl Into<PathBuf>) -> Self {
+        Self {
+            applications: applications.into(),
+            icons: icons.into(),
+        }
+    }
+
+    /// Adds `program` to the applications menu, or updates its entry.
+    pub async fn install(&self, bottle: &Bottle, program: &Program) -> Result<()> {
+        let name = entry_name(&bottle.id, &program.id);
+        let icon = match self.export_icon(bottle, program, &name).await {
+            Ok(icon) => icon.display().to_string(),
+            Err(why) => {
+                eprintln!("no icon for the desktop entry of {}: {why}", program.title);
+                APP_ICON.to_string()
+            }
+        };
+
+        let uri = Uri::Run {
+            bottle: bottle.id.clone(),
+            program: program.id.clone(),
+        };
+        let exec = [
+            std::env::current_exe()?.display().to_string(),
+            uri.to_string(),
+        ]
+        .iter()
+        .map(|argument| exec_argument(argument))
+        .collect::<Vec<_>>()
+        .join(" ");
+        let mut entry = format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Exec={}\n\
+             Icon={}\n\
+             Terminal=false\n",
+            escape(&program.title),
+            escape(&exec),
+            escape(&icon),
+        );
+        if bottle.environment == Environment::Gaming {
+            entry.push_str("Categories=Game;\n");
+        }
+
+        // Desktops watch this directory, so the entry is written elsewhere first
+        // to never show up half written.
+        fs::create_dir_all(&self.applications).await?;
+        let path = self.applications.join(format!("{name}.desktop"));
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, entry).await?;
+        fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    /// Removes the entry of a program from the applications menu, if it has one.
+    pub async fn remove(&self, bottle: &str, program: &str) -> Result<()> {
+        let name = entry_name(bottle, program);
+        remove_file(&self.applications.join(format!("{name}.desktop"))).await?;
+        remove_file(&self.icons.join(format!("{name}.png"))).await?;
+        Ok(())
+    }
+
+    /// Removes the entries of every program of a bottle from the applications
+    /// menu.
+    pub async fn remove_bottle(&self, bottle: &str) -> Result<()> {
+        let prefix = entry_name(bottle, "");
+        for dir in [&self.applications, &self.icons] {
+            let mut entries = match fs::read_dir(dir).await {
+                Ok(entries) => entries,
+                Err(why) if why.kind() == io::ErrorKind::NotFound => continue,
+                Err(why) => return Err(why.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    remove_file(&entry.path()).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the icon of the executable of `program` next to the other entry
+    /// icons, since the icon cache may be cleared at any time.
+    async fn export_icon(&self, bottle: &Bottle, program: &Program, name: &str) -> Result<PathBuf> {
+        let executable = program
+            .host_executable(bottle)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        let icon = pe::icon(&executable).await?;
+        fs::create_dir_all(&self.icons).await?;
+        let path = self.icons.join(format!("{name}.png"));
+        fs::copy(&icon, &path).await?;
+        Ok(path)
+    }
+}
+
+/// Where desktop entries of the user are found.
+pub fn applications_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("applications")
+}
+
+fn entry_name(bottle: &str, program: &str) -> String {
+    format!("bottles-next-{bottle}-{program}")
+}
+
+async fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(why) if why.kind() != io::ErrorKind::NotFound => Err(why),
+        _ => Ok(()),
+    }
+}
+
+/// Quotes an argument of the `Exec` key when it has characters the desktop
+/// would otherwise interpret.
+fn exec_argument(argument: &str) -> String {
+    let argument = argument.replace('%', "%%");
+    let reserved = |c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c);
+    if !argument.contains(reserved) {
+        return argument;
+    }
+    let mut quoted = String::from("\"");
+    for c in argument.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes a string value of a desktop entry.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_arguments_are_left_alone() {
+        assert_eq!(
+            exec_argument("/usr/bin/bottles-next"),
+            "/usr/bin/bottles-next"
+        );
+        assert_eq!(
+            exec_argument("bottles-next://run/a/b"),
+            "bottles-next://run/a/b"
+        );
+    }
+
+    #[test]
+    fn reserved_characters_are_quoted() {
+        assert_eq!(
+            exec_argument("/opt/My Apps/bottles-next"),
+            r#""/opt/My Apps/bottles-next""#
+        );
+        assert_eq!(
+            exec_argument(r#"/opt/"a" $HOME `b` \c"#),
+            r#""/opt/\"a\" \$HOME \`b\` \\c""#
+        );
+        assert_eq!(exec_argument("it's;here"), r#""it's;here""#);
+        // Field codes are escaped whether the argument is quoted or not.
+        assert_eq!(exec_argument("100%"), "100%%");
+        assert_eq!(exec_argument("100% done"), r#""100%% done""#);
+    }
+
+    #[test]
+    fn values_stay_on_one_line() {
+        assert_eq!(escape("a\nb\tc\rd"), r"a\nb\tc\rd");
+        // Escaping happens after quoting, so the backslashes of the quoting are
+        // doubled, as the specification wants.
+        assert_eq!(escape(&exec_argument(r#"a "b""#)), r#""a \\"b\\"""#);
+    }
+}
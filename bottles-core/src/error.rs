@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{io, sync::Arc};
+
+/// Errors produced while managing bottles on disk.
+///
+/// The error is cheap to clone so it can travel inside application messages.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Io(Arc<io::Error>),
+    #[error("invalid manifest {path}: {reason}")]
+    Manifest { path: String, reason: String },
+    #[error("bottle not found: {0}")]
+    BottleNotFound(String),
+    #[error("program not found: {0}")]
+    ProgramNotFound(String),
+    #[error("runner not found: {0}")]
+    RunnerNotFound(String),
+    #[error("{name} could be any of {}", .ids.join(", "))]
+    Ambiguous { name: String, ids: Vec<String> },
+    #[error("{0} is already running")]
+    AlreadyRunning(String),
+    #[error("failed to initialize the prefix: {0}")]
+    Prefix(String),
+    #[error("snapshot failed: {0}")]
+    Snapshot(String),
+    #[error("{0}")]
+    Script(String),
+    #[error("no terminal emulator found")]
+    TerminalNotFound,
+    #[error("failed to load the image: {0}")]
+    Image(String),
+    #[error("failed to extract the icon: {0}")]
+    Icon(String),
+    #[error("invalid shortcut: {0}")]
+    Shortcut(String),
+    #[error("invalid link: {0}")]
+    Uri(String),
+    #[error("download failed: {0}")]
+    Download(String),
+    #[error("checksum mismatch for {path}: expected {expected}, got {actual}")]
+    Checksum {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("installation failed: {0}")]
+    Installer(String),
+    #[error("dependency failed: {0}")]
+    Dependency(String),
+    #[error("{0}")]
+    Runner(String),
+    #[error("failed to unpack {0}")]
+    Unpack(String),
+    #[error("component failed: {0}")]
+    Component(String),
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(Arc::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
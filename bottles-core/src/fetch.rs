@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Downloads of installers, redistributables, runners and components.
+//!
+//! Files are fetched through a [`Fetcher`], which is [`Http`] normally and a
+//! [`Mirror`] of local files where the network isn't wanted, such as in tests.
+//! [`download`] adds checksum verification on top of either and never leaves a
+//! partial file at the destination. Runners and components are published as
+//! tarballs, which [`unpack`] extracts.
+
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use xz2::read::XzDecoder;
+
+use crate::error::{Error, Result};
+
+/// Reports how much of a download is done, from 0 to 1.
+pub type Progress<'a> = &'a (dyn Fn(f32) + Send + Sync);
+
+pub trait Fetcher: Debug + Send + Sync {
+    /// Writes the file at `url` to `destination`.
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        destination: &'a Path,
+        progress: Progress<'a>,
+    ) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Fetches files over HTTP, and copies the ones given as `file://` URLs.
+#[derive(Clone, Debug, Default)]
+pub struct Http {
+    client: reqwest::Client,
+}
+
+impl Fetcher for Http {
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        destination: &'a Path,
+        progress: Progress<'a>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Some(path) = url.strip_prefix("file://") {
+                fs::copy(path, destination).await?;
+                progress(1.0);
+                return Ok(());
+            }
+
+            let response = self
+                .client
+                .get(url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|why| Error::Download(format!("{url}: {why}")))?;
+            let total = response.content_length().filter(|total| *total > 0);
+            let mut file = fs::File::create(destination).await?;
+            let mut chunks = response.bytes_stream();
+            let mut done = 0;
+            while let Some(chunk) = chunks.next().await {
+                let chunk = chunk.map_err(|why| Error::Download(format!("{url}: {why}")))?;
+                file.write_all(&chunk).await?;
+                done += chunk.len() as u64;
+                if let Some(total) = total {
+                    progress(done as f32 / total as f32);
+                }
+            }
+            file.flush().await?;
+            Ok(())
+        })
+    }
+}
+
+/// Serves files from a directory laid out like the URLs they stand in for, so
+/// `https://example.com/files/setup.exe` is read from `<root>/example.com/files/setup.exe`.
+#[derive(Clone, Debug)]
+pub struct Mirror {
+    root: PathBuf,
+}
+
+impl Mirror {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Where the file of `url` is expected in the mirror.
+    pub fn path(&self, url: &str) -> PathBuf {
+        let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let url = url.split(['?', '#']).next().unwrap_or_default();
+        url.split('/')
+            .filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+            .fold(self.root.clone(), |path, segment| path.join(segment))
+    }
+}
+
+impl Fetcher for Mirror {
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        destination: &'a Path,
+        progress: Progress<'a>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = self.path(url);
+            if !fs::try_exists(&path).await? {
+                return Err(Error::Download(format!(
+                    "{url}: not in {}",
+                    self.root.display()
+                )));
+            }
+            fs::copy(&path, destination).await?;
+            progress(1.0);
+            Ok(())
+        })
+    }
+}
+
+/// The name of the file `url` points to, which downloads are saved under.
+///
+/// Returns `None` when the last segment of the path can't name a file, such as
+/// `.` or `..`, which would put the download somewhere else than intended.
+pub fn file_name(url: &str) -> Option<&str> {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|url| url.rsplit('/').next())
+        .filter(|name| !matches!(*name, "" | "." | ".."))
+}
+
+/// Fetches `url` to `destination` unless a file matching `sha256` is already
+/// there, and checks the fetched file against `sha256` when it is given.
+pub async fn download(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    sha256: Option<&str>,
+    destination: &Path,
+    progress: Progress<'_>,
+) -> Result<()> {
+    if fs::try_exists(destination).await? && verify(destination, sha256).await.is_ok() {
+        progress(1.0);
+        return Ok(());
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let part = destination.with_extension("part");
+    let result = match fetcher.fetch(url, &part, progress).await {
+        Ok(()) => verify(&part, sha256).await,
+        Err(why) => Err(why),
+    };
+    if let Err(why) = result {
+        let _ = fs::remove_file(&part).await;
+        return Err(why);
+    }
+    fs::rename(&part, destination).await?;
+    Ok(())
+}
+
+/// Checks that the SHA-256 of the file at `path` is `sha256`, given in hex.
+pub async fn verify(path: &Path, sha256: Option<&str>) -> Result<()> {
+    let Some(expected) = sha256 else {
+        return Ok(());
+    };
+
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    let actual = format!("{:x}", hasher.finalize());
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(Error::Checksum {
+            path: path.display().to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Unpacks the tarball at `archive` into `destination`, picking the decompressor
+/// from its extension. This blocks, so it is meant for `spawn_blocking`.
+pub fn unpack(archive: &Path, destination: &Path) -> Result<()> {
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let file = File::open(archive)?;
+    let reader: Box<dyn Read> = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Box::new(GzDecoder::new(file))
+    } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+        Box::new(XzDecoder::new(file))
+    } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        Box::new(zstd::Decoder::new(file).map_err(|why| Error::Unpack(format!("{name}: {why}")))?)
+    } else if name.ends_with(".tar") {
+        Box::new(file)
+    } else {
+        return Err(Error::Unpack(format!("{name}: unsupported archive")));
+    };
+
+    let mut tar = tar::Archive::new(reader);
+    tar.set_preserve_permissions(true);
+    // Entries reaching outside `destination` are skipped by `unpack`.
+    tar.unpack(destination)
+        .map_err(|why| Error::Unpack(format!("{name}: {why}")))
+}
+
+/// The only entry of `dir`, if it is a folder. Archives usually hold a single
+/// folder named after the release.
+pub async fn single_folder(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut entries = fs::read_dir(dir).await?;
+    let Some(first) = entries.next_entry().await? else {
+        return Ok(None);
+    };
+    if entries.next_entry().await?.is_some() || !first.file_type().await?.is_dir() {
+        return Ok(None);
+    }
+    Ok(Some(first.path()))
+}
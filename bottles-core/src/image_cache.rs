@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Thumbnails of cover art.
+//!
+//! Cover art is often much larger than the cards showing it, so covers are
+//! decoded once, scaled down to the size of a card and kept on disk under the
+//! hash of their contents. The least recently used thumbnails are removed once
+//! the cache grows past its size budget.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::store;
+
+/// Height of the thumbnails, twice the height of a card so they stay sharp on
+/// scaled displays.
+pub const THUMBNAIL_HEIGHT: u32 = 300;
+
+/// Size of the cache on disk before old thumbnails are removed, in bytes.
+const BUDGET: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct ImageCache {
+    root: PathBuf,
+    budget: u64,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new(store::cache_dir().join("thumbnails"), BUDGET)
+    }
+}
+
+impl ImageCache {
+    pub fn new(root: impl Into<PathBuf>, budget: u64) -> Self {
+        Self {
+            root: root.into(),
+            budget,
+        }
+    }
+
+    /// Returns a thumbnail of the image at `source`, creating it on first use.
+    pub async fn thumbnail(&self, source: &Path) -> Result<PathBuf> {
+        let cache = self.clone();
+        let source = source.to_path_buf();
+        tokio::task::spawn_blocking(move || cache.thumbnail_blocking(&source))
+            .await
+            .map_err(|why| Error::Image(why.to_string()))?
+    }
+
+    fn thumbnail_blocking(&self, source: &Path) -> Result<PathBuf> {
+        let data = fs::read(source)?;
+        let path = self.root.join(format!("{:x}.jpg", Sha256::digest(&data)));
+        if path.exists() {
+            // The modification time tells which thumbnails were used last.
+            fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(SystemTime::now())?;
+            return Ok(path);
+        }
+
+        let image = image::load_from_memory(&data).map_err(|why| Error::Image(why.to_string()))?;
+        let image = if image.height() > THUMBNAIL_HEIGHT {
+            let width = (image.width() as u64 * THUMBNAIL_HEIGHT as u64 / image.height() as u64)
+                .max(1) as u32;
+            image.resize_exact(width, THUMBNAIL_HEIGHT, FilterType::Triangle)
+        } else {
+            image
+        };
+
+        // Written next to its final name first so a crash never leaves half an
+        // image in the cache.
+        fs::create_dir_all(&self.root)?;
+        let tmp = path.with_extension("tmp");
+        DynamicImage::ImageRgb8(image.to_rgb8())
+            .save_with_format(&tmp, ImageFormat::Jpeg)
+            .map_err(|why| Error::Image(why.to_string()))?;
+        fs::rename(&tmp, &path)?;
+
+        if let Err(why) = self.evict() {
+            eprintln!("failed to clean up the image cache: {why}");
+        }
+        Ok(path)
+    }
+
+    /// Removes the least recently used thumbnails until the cache fits its budget.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = vec![];
+        let mut size = 0;
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                size += metadata.len();
+                entries.push((metadata.modified()?, metadata.len(), entry.path()));
+            }
+        }
+        if size <= self.budget {
+            return Ok(());
+        }
+
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in entries {
+            if size <= self.budget {
+                break;
+            }
+            fs::remove_file(path)?;
+            size -= len;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Recipes that set up a game or a launcher in one go.
+//!
+//! A [`Recipe`] lists the [`Step`]s needed to install a program in a bottle:
+//! redistributables, installers to download and run, and the program to add to
+//! the bottle once everything is in place. The [`Installer`] runs them in order
+//! and remembers the last step that went through, so an installation that failed
+//! picks up from there the next time it is started.
+//!
+//! Recipes are RON files. The built-in ones ship with the application and more
+//! can be added to the `recipes` folder of the data directory.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::bottle::{Bottle, Environment};
+use crate::dependency::{Dependencies, Manifest};
+use crate::error::{Error, Result};
+use crate::fetch::{self, Fetcher};
+use crate::program::Program;
+use crate::runner::{self, Launch, Runner, Runners};
+use crate::store::{self, read_ron, write_ron, BottleStore};
+
+const BUILTIN: [&str; 2] = [
+    include_str!("../recipes/battlenet.ron"),
+    include_str!("../recipes/steam.ron"),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recipe {
+    /// Stable identifier, also naming the progress of unfinished installations.
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Environment of the bottles created for the recipe.
+    #[serde(default)]
+    pub environment: Environment,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Step {
+    /// Installs packages of the dependency [`Manifest`] in the bottle.
+    Dependencies(Vec<String>),
+    /// Downloads `url` as `file`, unless a local copy of `file` was given.
+    Download {
+        url: String,
+        file: String,
+        #[serde(default)]
+        sha256: Option<String>,
+    },
+    /// Runs `file` in the bottle and waits for it to exit successfully.
+    Run {
+        file: String,
+        #[serde(default)]
+        arguments: Vec<String>,
+    },
+    /// Adds a program to the bottle once its executable, given as a Windows
+    /// path, was installed. The cover art is downloaded along with it.
+    Register {
+        title: String,
+        executable: String,
+        #[serde(default)]
+        arguments: Vec<String>,
+        #[serde(default)]
+        cover: Option<String>,
+    },
+}
+
+impl Step {
+    /// Describes the step while it runs.
+    pub fn title(&self) -> String {
+        match self {
+            Self::Dependencies(packages) => format!("Installing {}...", packages.join(", ")),
+            Self::Download { file, .. } => format!("Downloading {file}..."),
+            Self::Run { file, .. } => format!("Running {file}..."),
+            Self::Register { title, .. } => format!("Adding {title}..."),
+        }
+    }
+}
+
+/// The bottle a recipe is installed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// A bottle created for the recipe, named `name` and using `runner`.
+    New { name: String, runner: String },
+    /// The bottle with this id.
+    Existing(String),
+}
+
+/// Progress of an installation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    /// Index of the running step, the bottle being step 0.
+    pub step: usize,
+    pub steps: usize,
+    pub title: String,
+    /// Progress of the running step, from 0 to 1.
+    pub progress: f32,
+}
+
+impl Update {
+    /// Progress of the whole installation, from 0 to 1.
+    pub fn overall(&self) -> f32 {
+        (self.step as f32 + self.progress) / self.steps as f32
+    }
+}
+
+/// What an unfinished installation already went through.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct State {
+    bottle: Option<String>,
+    /// Number of steps of the recipe that are done.
+    completed: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Installer {
+    store: BottleStore,
+    runners: Runners,
+    fetcher: Arc<dyn Fetcher>,
+    /// Progress of unfinished installations, one file per recipe.
+    state_dir: PathBuf,
+    /// Holds the downloads of unfinished installations, one folder per recipe,
+    /// and the downloads of dependencies.
+    cache_dir: PathBuf,
+}
+
+impl Installer {
+    pub fn new(store: BottleStore, runners: Runners, fetcher: Arc<dyn Fetcher>) -> Self {
+        Self {
+            store,
+            runners,
+            fetcher,
+            state_dir: store::data_dir().join("installations"),
+            cache_dir: store::cache_dir(),
+        }
+    }
+
+    /// Keeps progress and downloads under `dir` instead of the data and cache
+    /// directories.
+    pub fn with_dir(self, dir: &Path) -> Self {
+        Self {
+            state_dir: dir.join("installations"),
+            cache_dir: dir.to_path_buf(),
+            ..self
+        }
+    }
+
+    /// The bottle an unfinished installation of `recipe` was going into, which
+    /// it resumes in whatever target is given.
+    pub async fn unfinished(&self, recipe: &Recipe) -> Option<String> {
+        read_ron::<State>(&self.state_path(recipe))
+            .await
+            .ok()
+            .and_then(|state| state.bottle)
+    }
+
+    /// Installs `recipe` in `target`, or resumes its unfinished installation.
+    ///
+    /// Files named in `files` are used instead of downloading them, for
+    /// installers the user already has.
+    pub async fn install(
+        &self,
+        recipe: &Recipe,
+        target: Target,
+        files: &HashMap<String, PathBuf>,
+        report: impl Fn(Update) + Send + Sync,
+    ) -> Result<Bottle> {
+        let steps = recipe.steps.len() + 1;
+        let update = |step: usize, title: String, progress: f32| {
+            report(Update {
+                step,
+                steps,
+                title,
+                progress,
+            })
+        };
+
+        let state_path = self.state_path(recipe);
+        let mut state: State = if fs::try_exists(&state_path).await? {
+            read_ron(&state_path).await?
+        } else {
+            State::default()
+        };
+
+        update(0, "Preparing the bottle...".to_string(), 0.);
+        let resumed = match &state.bottle {
+            Some(id) => match self.store.get(id).await {
+                Ok(bottle) => Some(bottle),
+                // The bottle was deleted since, so start over.
+                Err(Error::BottleNotFound(_)) => None,
+                Err(why) => return Err(why),
+            },
+            None => None,
+        };
+        let bottle = match resumed {
+            Some(bottle) => bottle,
+            None => {
+                let bottle = self.bottle(recipe, target).await?;
+                state = State {
+                    bottle: Some(bottle.id.clone()),
+                    completed: 0,
+                };
+                fs::create_dir_all(&self.state_dir).await?;
+                write_ron(&state_path, &state).await?;
+                bottle
+            }
+        };
+        let runner = self
+            .runners
+            .get(&bottle.runner)
+            .ok_or_else(|| Error::RunnerNotFound(bottle.runner.clone()))?;
+
+        let downloads = self.cache_dir.join("downloads").join(&recipe.id);
+        for (index, step) in recipe.steps.iter().enumerate().skip(state.completed) {
+            let step_update = |progress: f32| update(index + 1, step.title(), progress);
+            step_update(0.);
+            match step {
+                Step::Dependencies(packages) => {
+                    let dependencies =
+                        Dependencies::new(Manifest::load().await, self.fetcher.clone())
+                            .with_dir(&self.cache_dir);
+                    for (done, package) in packages.iter().enumerate() {
+                        let progress = |progress: f32, _| {
+                            step_update((done as f32 + progress) / packages.len() as f32)
+                        };
+                        dependencies
+                            .install(&*runner, &bottle, package, progress)
+                            .await?;
+                    }
+                }
+                Step::Download { url, file, sha256 } => {
+                    if !files.contains_key(file) {
+                        let destination = downloads.join(file);
+                        fetch::download(
+                            &*self.fetcher,
+                            url,
+                            sha256.as_deref(),
+                            &destination,
+                            &step_update,
+                        )
+                        .await?;
+                    }
+                }
+                Step::Run { file, arguments } => {
+                    let path = match files.get(file) {
+                        Some(path) => path.clone(),
+                        None => downloads.join(file),
+                    };
+                    run(&*runner, &bottle, &path, arguments).await?;
+                }
+                Step::Register {
+                    title,
+                    executable,
+                    arguments,
+                    cover,
+                } => {
+                    self.register(&bottle, title, executable, arguments, cover.as_deref())
+                        .await?
+                }
+            }
+            step_update(1.);
+
+            state.completed = index + 1;
+            write_ron(&state_path, &state).await?;
+        }
+
+        fs::remove_file(&state_path).await?;
+        if fs::try_exists(&downloads).await? {
+            fs::remove_dir_all(&downloads).await?;
+        }
+        Ok(bottle)
+    }
+
+    /// Creates the bottle of a new installation, or finds the existing one.
+    async fn bottle(&self, recipe: &Recipe, target: Target) -> Result<Bottle> {
+        let (name, runner) = match target {
+            Target::Existing(id) => return self.store.get(&id).await,
+            Target::New { name, runner } => (name, runner),
+        };
+        let runner = self
+            .runners
+            .get(&runner)
+            .ok_or_else(|| Error::RunnerNotFound(runner.clone()))?;
+
+        let bottle = self
+            .store
+            .create(Bottle::new(name, recipe.environment, runner.id()))
+            .await?;
+        if let Err(why) = runner::init_prefix(&*runner, &bottle.prefix()).await {
+            // Don't leave a bottle without a working prefix behind.
+            self.store.delete(&bottle.id).await?;
+            return Err(why);
+        }
+        Ok(bottle)
+    }
+
+    async fn register(
+        &self,
+        bottle: &Bottle,
+        title: &str,
+        executable: &str,
+        arguments: &[String],
+        cover: Option<&str>,
+    ) -> Result<()> {
+        // Installing again updates the program added the first time.
+        let mut program = self
+            .store
+            .programs(&bottle.id)
+            .await?
+            .into_iter()
+            .find(|program| program.executable == Path::new(executable))
+            .unwrap_or_else(|| Program::new(&bottle.id, title, executable.into()));
+        program.arguments = arguments.to_vec();
+
+        let installed = program
+            .host_executable(bottle)
+            .is_some_and(|path| path.is_file());
+        if !installed {
+            return Err(Error::Installer(format!("{executable} wasn't installed")));
+        }
+
+        if let Some(url) = cover {
+            let name = url
+                .split(['?', '#'])
+                .next()
+                .and_then(|url| url.rsplit('/').next())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("cover");
+            let path = bottle.path.join("covers").join(name);
+            fetch::download(&*self.fetcher, url, None, &path, &|_| {}).await?;
+            program.cover = Some(path);
+        }
+
+        self.store.save_program(program).await?;
+        Ok(())
+    }
+
+    fn state_path(&self, recipe: &Recipe) -> PathBuf {
+        self.state_dir.join(format!("{}.ron", recipe.id))
+    }
+}
+
+/// The built-in recipes followed by the ones in the data directory, sorted by name.
+pub async fn recipes() -> Vec<Recipe> {
+    let mut recipes: Vec<Recipe> = BUILTIN
+        .iter()
+        .filter_map(|recipe| match ron::from_str(recipe) {
+            Ok(recipe) => Some(recipe),
+            Err(why) => {
+                eprintln!("invalid built-in recipe: {why}");
+                None
+            }
+        })
+        .collect();
+
+    let dir = store::data_dir().join("recipes");
+    if let Ok(mut entries) = fs::read_dir(&dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("ron") {
+                continue;
+            }
+            match read_ron::<Recipe>(&path).await {
+                // Recipes of the user replace the built-in ones with the same id.
+                Ok(recipe) => {
+                    recipes.retain(|builtin| builtin.id != recipe.id);
+                    recipes.push(recipe);
+                }
+                Err(why) => eprintln!("skipping recipe {}: {why}", path.display()),
+            }
+        }
+    }
+
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+    recipes
+}
+
+async fn run(
+    runner: &dyn Runner,
+    bottle: &Bottle,
+    path: &Path,
+    arguments: &[String],
+) -> Result<()> {
+    if !fs::try_exists(path).await? {
+        return Err(Error::Installer(format!("{} is missing", path.display())));
+    }
+    let launch = Launch {
+        executable: path.to_path_buf(),
+        arguments: arguments.to_vec(),
+        working_dir: path.parent().map(Path::to_path_buf),
+        ..Default::default()
+    };
+    let status = runner.spawn(&bottle.prefix(), &launch)?.wait().await?;
+    if !status.success() {
+        return Err(Error::Installer(format!(
+            "{} exited with {status}",
+            path.display()
+        )));
+    }
+    Ok(())
+}
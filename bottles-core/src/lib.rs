@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Bottles, the programs installed in them and everything needed to run them.
+//!
+//! Nothing in this crate depends on the interface, so the application window and
+//! the command line share it.
+
+pub mod bottle;
+pub mod component;
+pub mod dependency;
+pub mod desktop;
+pub mod error;
+pub mod fetch;
+pub mod image_cache;
+pub mod installer;
+pub mod lnk;
+pub mod pe;
+pub mod program;
+pub mod runner;
+pub mod scanner;
+pub mod snapshot;
+pub mod store;
+pub mod supervisor;
+pub mod uri;
+
+pub use error::{Error, Result};
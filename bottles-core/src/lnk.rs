@@ -0,0 +1,298 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Reader for Windows shortcuts, the Shell Link (`.lnk`) binary format.
+//!
+//! Only what is needed to start the program a shortcut points to is read: the
+//! target path, its arguments, the working directory and the icon. The layout is
+//! documented in [MS-SHLLINK].
+//!
+//! [MS-SHLLINK]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-shllink
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+const HEADER_SIZE: u32 = 0x4c;
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+// Link flags, telling which optional structures follow the header.
+const HAS_TARGET_ID_LIST: u32 = 1 << 0;
+const HAS_LINK_INFO: u32 = 1 << 1;
+const HAS_NAME: u32 = 1 << 2;
+const HAS_RELATIVE_PATH: u32 = 1 << 3;
+const HAS_WORKING_DIR: u32 = 1 << 4;
+const HAS_ARGUMENTS: u32 = 1 << 5;
+const HAS_ICON_LOCATION: u32 = 1 << 6;
+const IS_UNICODE: u32 = 1 << 7;
+
+/// Link info flag set when the target is on a local volume.
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 1 << 0;
+
+/// Signature of the extra data block holding the target with environment variables.
+const ENVIRONMENT_VARIABLE_BLOCK: u32 = 0xa000_0001;
+
+/// The contents of a shortcut, with paths as Windows programs see them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Shortcut {
+    /// What the shortcut opens, e.g. `C:\Program Files\Game\game.exe`.
+    pub target: String,
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+    /// The file holding the icon of the shortcut and the index of the icon in it.
+    pub icon: Option<(String, i32)>,
+    pub description: Option<String>,
+}
+
+impl Shortcut {
+    /// Reads the shortcut at `path`. Targets given relative to the shortcut are
+    /// resolved against its folder, which must be inside `drive`.
+    pub fn read(path: &Path, drive: &Path) -> Result<Self> {
+        let mut shortcut = Self::parse(&fs::read(path)?)?;
+        if !is_absolute(&shortcut.target) {
+            let dir = path.parent().unwrap_or(drive);
+            let dir = match dir.strip_prefix(drive) {
+                Ok(relative) => format!("C:\\{}", relative.to_string_lossy().replace('/', "\\")),
+                Err(_) => return Err(invalid("the target is outside of the prefix")),
+            };
+            shortcut.target = format!("{}\\{}", dir.trim_end_matches('\\'), shortcut.target);
+        }
+        Ok(shortcut)
+    }
+
+    /// Parses the contents of a `.lnk` file.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        if reader.u32()? != HEADER_SIZE || reader.bytes(16)? != LINK_CLSID {
+            return Err(invalid("not a shell link"));
+        }
+        let flags = reader.u32()?;
+        // File attributes, creation, access and write times, then the file size.
+        reader.skip(4 + 8 * 3 + 4)?;
+        let icon_index = reader.u32()? as i32;
+        // Show command, hotkey and reserved fields.
+        reader.skip(4 + 2 + 2 + 4 + 4)?;
+
+        if flags & HAS_TARGET_ID_LIST != 0 {
+            let size = reader.u16()? as usize;
+            reader.skip(size)?;
+        }
+
+        let mut target = None;
+        if flags & HAS_LINK_INFO != 0 {
+            let start = reader.position;
+            let size = reader.u32()? as usize;
+            reader.skip(size.saturating_sub(4))?;
+            target = link_info_target(&data[start..reader.position])?;
+        }
+
+        let unicode = flags & IS_UNICODE != 0;
+        let mut string = |flag: u32| -> Result<Option<String>> {
+            if flags & flag == 0 {
+                return Ok(None);
+            }
+            let value = reader.string_data(unicode)?;
+            Ok((!value.is_empty()).then_some(value))
+        };
+        let description = string(HAS_NAME)?;
+        let relative_path = string(HAS_RELATIVE_PATH)?;
+        let working_dir = string(HAS_WORKING_DIR)?;
+        let arguments = string(HAS_ARGUMENTS)?;
+        let icon = string(HAS_ICON_LOCATION)?.map(|location| (location, icon_index));
+
+        if target.is_none() {
+            target = environment_target(&mut reader).or(relative_path);
+        }
+        let target = target.ok_or_else(|| invalid("the shortcut has no target"))?;
+
+        Ok(Self {
+            target,
+            arguments,
+            working_dir,
+            icon,
+            description,
+        })
+    }
+}
+
+/// Reads the local path of the target from a link info structure. Offsets in the
+/// structure are relative to its start.
+fn link_info_target(info: &[u8]) -> Result<Option<String>> {
+    let mut reader = Reader::new(info);
+    reader.skip(4)?;
+    let header_size = reader.u32()?;
+    let flags = reader.u32()?;
+    if flags & VOLUME_ID_AND_LOCAL_BASE_PATH == 0 {
+        return Ok(None);
+    }
+    let _volume_id = reader.u32()?;
+    let base_path = reader.u32()? as usize;
+    let _network_link = reader.u32()?;
+    let suffix = reader.u32()? as usize;
+
+    // Newer writers add offsets to UTF-16 copies of the same paths.
+    if header_size >= 0x24 {
+        let base_path = reader.u32()? as usize;
+        let suffix = reader.u32()? as usize;
+        if base_path != 0 {
+            let mut path = utf16_at(info, base_path)?;
+            if suffix != 0 {
+                path.push_str(&utf16_at(info, suffix)?);
+            }
+            return Ok(Some(path));
+        }
+    }
+
+    let mut path = ansi_at(info, base_path)?;
+    if suffix != 0 {
+        path.push_str(&ansi_at(info, suffix)?);
+    }
+    Ok(Some(path))
+}
+
+/// Looks for the target in the extra data blocks following the string data.
+fn environment_target(reader: &mut Reader) -> Option<String> {
+    loop {
+        let size = reader.u32().ok()? as usize;
+        // A block smaller than its own header terminates the list.
+        if size < 8 {
+            return None;
+        }
+        let mut block = Reader::new(reader.bytes(size - 4).ok()?);
+        if block.u32().ok()? == ENVIRONMENT_VARIABLE_BLOCK {
+            let ansi = block.bytes(260).ok()?;
+            let unicode = block.bytes(520).ok()?;
+            let target = utf16_at(unicode, 0)
+                .ok()
+                .filter(|target| !target.is_empty())
+                .or_else(|| ansi_at(ansi, 0).ok())?;
+            return Some(target).filter(|target| !target.is_empty());
+        }
+    }
+}
+
+fn ansi_at(data: &[u8], offset: usize) -> Result<String> {
+    let bytes = data.get(offset..).ok_or_else(truncated)?;
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(truncated)?;
+    // Shortcuts store these in the system code page; Latin-1 is a fair guess.
+    Ok(bytes[..end].iter().map(|&byte| byte as char).collect())
+}
+
+fn utf16_at(data: &[u8], offset: usize) -> Result<String> {
+    let bytes = data.get(offset..).ok_or_else(truncated)?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+/// Whether `path` starts with a drive letter or is a network path.
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with("\\\\")
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// Maps a Windows path to the file it designates in the prefix containing
+/// `drive`, the `drive_c` folder of the prefix.
+///
+/// Windows paths ignore case, so each component is matched against the files on
+/// disk regardless of case. Returns `None` for paths on other drives or network
+/// shares, and for paths climbing out of the drive with `..`.
+pub fn unix_path(drive: &Path, path: &str) -> Option<PathBuf> {
+    let path = path.trim().replace('/', "\\");
+    let relative = path
+        .strip_prefix("C:\\")
+        .or_else(|| path.strip_prefix("c:\\"))?;
+
+    let mut resolved = drive.to_path_buf();
+    for component in relative
+        .split('\\')
+        .filter(|component| !component.is_empty() && *component != ".")
+    {
+        if component == ".." {
+            return None;
+        }
+        let exact = resolved.join(component);
+        resolved = if exact.exists() {
+            exact
+        } else {
+            fs::read_dir(&resolved)
+                .ok()
+                .and_then(|entries| {
+                    entries.flatten().find(|entry| {
+                        entry
+                            .file_name()
+                            .to_string_lossy()
+                            .eq_ignore_ascii_case(component)
+                    })
+                })
+                .map(|entry| entry.path())
+                .unwrap_or(exact)
+        };
+    }
+    Some(resolved)
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Shortcut(reason.to_string())
+}
+
+fn truncated() -> Error {
+    invalid("unexpected end of file")
+}
+
+/// Reads little-endian values from a buffer, failing instead of going past its end.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(len).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.position..end).ok_or_else(truncated)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a string prefixed by its length in characters.
+    fn string_data(&mut self, unicode: bool) -> Result<String> {
+        let len = self.u16()? as usize;
+        if unicode {
+            let bytes = self.bytes(len * 2)?;
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            Ok(String::from_utf16_lossy(&units))
+        } else {
+            Ok(self.bytes(len)?.iter().map(|&byte| byte as char).collect())
+        }
+    }
+}
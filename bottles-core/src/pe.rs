@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Icons of Windows executables.
+//!
+//! Executables keep their icons as resources: an `RT_GROUP_ICON` entry lists the
+//! sizes available for an icon and each size is stored as its own `RT_ICON`
+//! entry. The largest image of the first group, the one Windows shows for the
+//! file, is rebuilt into an `.ico` file and converted to PNG.
+//!
+//! Executables come from anywhere, so every offset is checked before it is used
+//! and a malformed file only ever results in an error.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+use crate::error::{Error, Result};
+use crate::store;
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+
+/// Index of the resource table in the data directories of the optional header.
+const RESOURCE_DIRECTORY: usize = 2;
+
+/// Returns the PNG icon of `executable`, extracting it on first use.
+///
+/// Icons are cached under the cache directory of the application, keyed by the
+/// path and modification time of the executable so updates get a fresh icon.
+pub async fn icon(executable: &Path) -> Result<PathBuf> {
+    let metadata = fs::metadata(executable).await?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs())
+        .unwrap_or_default();
+    let key = Sha256::digest(format!("{}:{modified}", executable.display()));
+    let cached = store::cache_dir()
+        .join("icons")
+        .join(format!("{key:x}.png"));
+    if fs::try_exists(&cached).await? {
+        return Ok(cached);
+    }
+
+    let data = fs::read(executable).await?;
+    let path = cached.clone();
+    tokio::task::spawn_blocking(move || {
+        let ico = group_icon(&data)?;
+        let image = image::load_from_memory_with_format(&ico, image::ImageFormat::Ico)
+            .map_err(|why| Error::Icon(why.to_string()))?;
+        std::fs::create_dir_all(path.parent().expect("cached icons are in a folder"))?;
+        image
+            .save_with_format(&path, image::ImageFormat::Png)
+            .map_err(|why| Error::Icon(why.to_string()))
+    })
+    .await
+    .map_err(|why| Error::Icon(why.to_string()))??;
+    Ok(cached)
+}
+
+/// Builds an `.ico` file holding the largest image of the first icon group of a
+/// PE executable.
+pub fn group_icon(data: &[u8]) -> Result<Vec<u8>> {
+    let resources = Resources::new(data)?;
+
+    let groups = resources.entries(0, RT_GROUP_ICON)?;
+    let group = groups
+        .first()
+        .ok_or_else(|| invalid("the executable has no icon"))?;
+    let group = resources.data(*group)?;
+
+    // GRPICONDIR followed by 14 bytes long GRPICONDIRENTRY records.
+    let count = u16_at(group, 4)? as usize;
+    let mut best: Option<(u32, u16, &[u8])> = None;
+    for index in 0..count {
+        let entry = group
+            .get(6 + index * 14..6 + (index + 1) * 14)
+            .ok_or_else(truncated)?;
+        // Sizes are stored in a byte, 0 meaning 256.
+        let width = match entry[0] {
+            0 => 256,
+            width => width as u32,
+        };
+        let bit_count = u16_at(entry, 6)?;
+        if best
+            .is_some_and(|(best_width, best_bits, _)| (width, bit_count) <= (best_width, best_bits))
+        {
+            continue;
+        }
+        let id = u16_at(entry, 12)? as u32;
+        // Groups sometimes list sizes that were stripped from the file.
+        if let Some(image) = resources.find(RT_ICON, id)? {
+            best = Some((width, bit_count, image));
+        }
+    }
+    let (width, bit_count, image) = best.ok_or_else(|| invalid("the icon has no image"))?;
+
+    // ICONDIR followed by a single 16 bytes long ICONDIRENTRY.
+    let size = (width % 256) as u8;
+    let mut ico = Vec::with_capacity(22 + image.len());
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    ico.extend_from_slice(&[size, size, 0, 0]);
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&bit_count.to_le_bytes());
+    ico.extend_from_slice(&(image.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+    ico.extend_from_slice(image);
+    Ok(ico)
+}
+
+/// The resource section of a PE executable.
+struct Resources<'a> {
+    data: &'a [u8],
+    /// Where the resource section starts in the file.
+    start: usize,
+    sections: Vec<Section>,
+}
+
+/// Where a section is loaded in memory and where it is in the file.
+struct Section {
+    address: u32,
+    size: u32,
+    offset: u32,
+}
+
+impl<'a> Resources<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        if data.get(..2) != Some(b"MZ") {
+            return Err(invalid("not a Windows executable"));
+        }
+        let pe = u32_at(data, 0x3c)? as usize;
+        if data.get(pe..pe + 4) != Some(b"PE\0\0") {
+            return Err(invalid("not a PE executable"));
+        }
+
+        // COFF header, then the optional header.
+        let coff = pe + 4;
+        let section_count = u16_at(data, coff + 2)? as usize;
+        let optional_size = u16_at(data, coff + 16)? as usize;
+        let optional = coff + 20;
+        let directories = match u16_at(data, optional)? {
+            0x10b => optional + 96,
+            0x20b => optional + 112,
+            _ => return Err(invalid("unknown optional header")),
+        };
+        let directory_count = u32_at(data, directories - 4)? as usize;
+        if directory_count <= RESOURCE_DIRECTORY {
+            return Err(invalid("the executable has no resources"));
+        }
+        let address = u32_at(data, directories + RESOURCE_DIRECTORY * 8)?;
+        if address == 0 {
+            return Err(invalid("the executable has no resources"));
+        }
+
+        let table = optional + optional_size;
+        let sections = (0..section_count)
+            .map(|index| {
+                let header = table + index * 40;
+                Ok(Section {
+                    size: u32_at(data, header + 8)?.max(u32_at(data, header + 16)?),
+                    address: u32_at(data, header + 12)?,
+                    offset: u32_at(data, header + 20)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut resources = Self {
+            data,
+            start: 0,
+            sections,
+        };
+        resources.start = resources.offset(address)?;
+        Ok(resources)
+    }
+
+    /// Converts an address in memory into an offset in the file.
+    fn offset(&self, address: u32) -> Result<usize> {
+        self.sections
+            .iter()
+            .find(|section| address >= section.address && address - section.address < section.size)
+            .map(|section| (address - section.address) as usize + section.offset as usize)
+            .ok_or_else(|| invalid("address outside of the sections"))
+    }
+
+    /// The entries named `id` of the directory at `directory`, as offsets relative
+    /// to the resource section.
+    fn entries(&self, directory: u32, id: u32) -> Result<Vec<u32>> {
+        let directory = self.start + directory as usize;
+        let named = u16_at(self.data, directory + 12)? as usize;
+        let ids = u16_at(self.data, directory + 14)? as usize;
+        let mut entries = vec![];
+        for index in named..named + ids {
+            let entry = directory + 16 + index * 8;
+            if u32_at(self.data, entry)? == id {
+                entries.push(u32_at(self.data, entry + 4)?);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The data of the first resource below the entry at `entry`, going down
+    /// through the name and language levels.
+    fn data(&self, mut entry: u32) -> Result<&'a [u8]> {
+        for _ in 0..2 {
+            if entry & 0x8000_0000 == 0 {
+                break;
+            }
+            let directory = self.start + (entry & 0x7fff_ffff) as usize;
+            let named = u16_at(self.data, directory + 12)? as usize;
+            let ids = u16_at(self.data, directory + 14)? as usize;
+            if named + ids == 0 {
+                return Err(invalid("empty resource directory"));
+            }
+            entry = u32_at(self.data, directory + 16 + 4)?;
+        }
+        if entry & 0x8000_0000 != 0 {
+            return Err(invalid("resource directories are nested too deep"));
+        }
+
+        let leaf = self.start + entry as usize;
+        let address = u32_at(self.data, leaf)?;
+        let size = u32_at(self.data, leaf + 4)? as usize;
+        let offset = self.offset(address)?;
+        self.data
+            .get(offset..offset.saturating_add(size))
+            .ok_or_else(truncated)
+    }
+
+    /// The data of the resource of type `kind` named `id`, if there is one.
+    fn find(&self, kind: u32, id: u32) -> Result<Option<&'a [u8]>> {
+        let Some(&types) = self.entries(0, kind)?.first() else {
+            return Ok(None);
+        };
+        if types & 0x8000_0000 == 0 {
+            return Err(invalid("malformed resource directory"));
+        }
+        match self.entries(types & 0x7fff_ffff, id)?.first() {
+            Some(&entry) => self.data(entry).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data.get(offset..offset + 2).ok_or_else(truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Icon(reason.to_string())
+}
+
+fn truncated() -> Error {
+    invalid("unexpected end of file")
+}
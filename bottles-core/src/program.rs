@@ -0,0 +1,256 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::bottle::Bottle;
+use crate::lnk;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Program {
+    pub id: String,
+    pub title: String,
+    pub executable: PathBuf,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub cover: Option<PathBuf>,
+    /// Scaled down copy of the cover, loaded in the background.
+    #[serde(skip)]
+    pub thumbnail: Option<PathBuf>,
+    /// Icon extracted from the executable, shown when there is no cover.
+    #[serde(skip)]
+    pub icon: Option<PathBuf>,
+    #[serde(default)]
+    pub overrides: Overrides,
+    /// Whether the program was added to the applications menu.
+    #[serde(default)]
+    pub desktop_entry: bool,
+    /// The bottle this program is installed in, filled in when loaded.
+    #[serde(skip)]
+    pub bottle: String,
+    #[serde(skip)]
+    pub status: Status,
+    /// Play statistics, kept in a separate file next to the program manifest.
+    #[serde(skip)]
+    pub activity: Activity,
+}
+
+/// Launch options of a program, layered on top of the settings of its bottle.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Overrides {
+    /// Appended to the arguments of the program.
+    pub arguments: Vec<String>,
+    /// Replaces the working directory of the program.
+    pub working_dir: Option<PathBuf>,
+    /// Added after the variables of the bottle, so they win on conflicts.
+    pub env: Vec<(String, String)>,
+    /// Shell script run before the program starts, which isn't started if it fails.
+    pub pre_launch: Option<String>,
+    /// Shell script run once the program exits.
+    pub post_launch: Option<String>,
+    /// Id of the runner used instead of the one of the bottle.
+    pub runner: Option<String>,
+    /// Starts the program in a terminal to follow its output.
+    pub terminal: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activity {
+    pub last_started: Option<DateTime<Utc>>,
+    pub last_stopped: Option<DateTime<Utc>>,
+    /// Total time the program has been running, in seconds.
+    pub play_time: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Status {
+    #[default]
+    Idle,
+    Running {
+        pid: u32,
+    },
+    Exited {
+        code: i32,
+    },
+    Crashed {
+        signal: Option<i32>,
+    },
+}
+
+impl Status {
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Running { .. })
+    }
+
+    /// A short description of the status, if there is something worth showing.
+    pub fn caption(&self) -> Option<String> {
+        match self {
+            Self::Idle | Self::Exited { code: 0 } => None,
+            Self::Running { .. } => Some("Running...".to_string()),
+            Self::Exited { code } => Some(format!("Exited with code {code}")),
+            Self::Crashed {
+                signal: Some(signal),
+            } => Some(format!("Crashed (signal {signal})")),
+            Self::Crashed { signal: None } => Some("Crashed".to_string()),
+        }
+    }
+}
+
+impl Program {
+    pub fn new(bottle: impl Into<String>, title: impl Into<String>, executable: PathBuf) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.into(),
+            executable,
+            arguments: vec![],
+            working_dir: None,
+            cover: None,
+            thumbnail: None,
+            icon: None,
+            overrides: Overrides::default(),
+            desktop_entry: false,
+            bottle: bottle.into(),
+            status: Status::Idle,
+            activity: Activity::default(),
+        }
+    }
+
+    /// Where the executable is on the host, for executables given as Windows paths
+    /// such as `C:\Games\game.exe`.
+    pub fn host_executable(&self, bottle: &Bottle) -> Option<PathBuf> {
+        if self.executable.is_absolute() {
+            return Some(self.executable.clone());
+        }
+        lnk::unix_path(
+            &bottle.prefix().join("drive_c"),
+            &self.executable.to_string_lossy(),
+        )
+    }
+}
+
+/// Splits a command line into arguments, keeping double-quoted text together.
+///
+/// Quotes are escaped the way Windows programs expect: `\"` is a literal quote,
+/// and backslashes are only special right before a quote, so paths such as
+/// `C:\Games\` keep theirs.
+pub fn split_arguments(line: &str) -> Vec<String> {
+    let mut arguments = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut pending = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut backslashes = 1;
+                while chars.next_if_eq(&'\\').is_some() {
+                    backslashes += 1;
+                }
+                if chars.next_if_eq(&'"').is_some() {
+                    current.extend(std::iter::repeat_n('\\', backslashes / 2));
+                    if backslashes % 2 == 1 {
+                        current.push('"');
+                    } else {
+                        quoted = !quoted;
+                    }
+                } else {
+                    current.extend(std::iter::repeat_n('\\', backslashes));
+                }
+                pending = true;
+            }
+            '"' => {
+                quoted = !quoted;
+                pending = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if pending {
+                    arguments.push(std::mem::take(&mut current));
+                    pending = false;
+                }
+            }
+            c => {
+                current.push(c);
+                pending = true;
+            }
+        }
+    }
+
+    if pending {
+        arguments.push(current);
+    }
+    arguments
+}
+
+/// Joins arguments back into a command line understood by [`split_arguments`],
+/// quoting and escaping the ones that need it.
+pub fn join_arguments(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| {
+            let plain =
+                !argument.is_empty() && !argument.contains(|c: char| c.is_whitespace() || c == '"');
+            if plain {
+                return argument.clone();
+            }
+
+            let mut quoted = String::from("\"");
+            let mut backslashes = 0;
+            for c in argument.chars() {
+                match c {
+                    '\\' => backslashes += 1,
+                    '"' => {
+                        // Backslashes before a quote are escaped along with it.
+                        quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                        quoted.push('"');
+                        backslashes = 0;
+                    }
+                    c => {
+                        quoted.extend(std::iter::repeat_n('\\', backslashes));
+                        quoted.push(c);
+                        backslashes = 0;
+                    }
+                }
+            }
+            // The closing quote must not be escaped by trailing backslashes.
+            quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_keeps_quoted_text_together() {
+        assert_eq!(split_arguments(r#"-a "b c" "" d"#), ["-a", "b c", "", "d"]);
+        assert_eq!(
+            split_arguments(r#"--title "say \"hi\"" C:\Games\ x"#),
+            ["--title", r#"say "hi""#, r"C:\Games\", "x"]
+        );
+        assert_eq!(split_arguments(r#"a\\"b c"#), [r"a\b c"]);
+    }
+
+    #[test]
+    fn join_round_trips() {
+        let cases: &[&[&str]] = &[
+            &["-a", "b c", "", "d"],
+            &[r#"say "hi""#, r#"""#, r#"a"b"#],
+            &[r"C:\Program Files\", r"C:\Games\", r"\\server\share"],
+            &[r#"\""#, r#"x\"y"#, "tab\there", r"end\\"],
+        ];
+        for &case in cases {
+            let arguments: Vec<String> = case.iter().map(|argument| argument.to_string()).collect();
+            let line = join_arguments(&arguments);
+            assert_eq!(split_arguments(&line), arguments, "{line}");
+        }
+    }
+}
@@ -0,0 +1,364 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Runners execute Windows programs inside a Wine prefix.
+//!
+//! Bottles reference a runner by its id, which is resolved against the runners
+//! discovered on the system at startup.
+
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use tokio::process::{Child, Command};
+
+use crate::bottle::{Bottle, Settings, WindowsVersion};
+use crate::error::{Error, Result};
+use crate::program::Program;
+
+pub use fake::Fake;
+pub use proton::Proton;
+pub use wine::Wine;
+
+pub mod fake;
+pub mod proton;
+pub mod repository;
+pub mod wine;
+
+/// Environment variable holding the script run by the [`Fake`] runner. When it is
+/// set, the fake runner is registered next to the real ones.
+pub const FAKE_RUNNER_ENV: &str = "BOTTLES_NEXT_FAKE_RUNNER";
+
+/// File in a prefix naming the Windows version last set in it. Living in the
+/// prefix, it follows the registry through snapshots.
+const WINDOWS_VERSION: &str = ".bottles-windows-version";
+
+/// Registry key holding how Wine loads each DLL.
+pub const DLL_OVERRIDES: &str = "HKEY_CURRENT_USER\\Software\\Wine\\DllOverrides";
+
+/// Terminal emulators programs can be started in, with the arguments preceding
+/// the command they run. The first one installed is used.
+const TERMINALS: [(&str, &[&str]); 4] = [
+    ("xdg-terminal-exec", &[]),
+    ("konsole", &["--hold", "-e"]),
+    ("gnome-terminal", &["--wait", "--"]),
+    ("xterm", &["-hold", "-e"]),
+];
+
+pub trait Runner: Debug + Send + Sync {
+    /// Stable identifier stored in bottle manifests.
+    fn id(&self) -> &str;
+
+    fn name(&self) -> &str;
+
+    fn version(&self) -> Option<&str>;
+
+    /// Starts `launch` inside `prefix`.
+    ///
+    /// The child leads a process group of its own, so it can be stopped along with
+    /// everything it starts, such as the program behind a wrapper.
+    fn spawn(&self, prefix: &Path, launch: &Launch) -> io::Result<Child>;
+
+    /// Terminates every process running inside `prefix`.
+    fn kill<'a>(&'a self, prefix: &'a Path) -> BoxFuture<'a, io::Result<()>>;
+}
+
+/// Everything a runner needs to start a program.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Launch {
+    pub executable: PathBuf,
+    pub arguments: Vec<String>,
+    pub working_dir: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    /// Commands the program is started through, such as `gamemoderun`.
+    pub wrappers: Vec<String>,
+}
+
+impl Launch {
+    /// Builds the launch of `program` in `bottle`.
+    ///
+    /// The program itself comes first, then the settings of the bottle and finally
+    /// the overrides of the program, each layer winning over the previous ones.
+    pub fn new(bottle: &Bottle, program: &Program) -> Result<Self> {
+        let overrides = &program.overrides;
+        let mut launch = Self::from(program).with_settings(&bottle.settings);
+        launch.arguments.extend(overrides.arguments.iter().cloned());
+        if let Some(working_dir) = &overrides.working_dir {
+            launch.working_dir = Some(working_dir.clone());
+        }
+        launch.env.extend(overrides.env.iter().cloned());
+        if overrides.terminal {
+            // The terminal wraps everything else so it shows their output too.
+            launch
+                .wrappers
+                .splice(0..0, terminal().ok_or(Error::TerminalNotFound)?);
+        }
+        Ok(launch)
+    }
+
+    /// Applies the settings of the bottle the program is started in.
+    pub fn with_settings(mut self, settings: &Settings) -> Self {
+        // Builtin DLLs fall back to WineD3D when DXVK or VKD3D are turned off.
+        let dxvk = if settings.dxvk { "n,b" } else { "b" };
+        let vkd3d = if settings.vkd3d { "n,b" } else { "b" };
+        self.env.push((
+            "WINEDLLOVERRIDES".to_string(),
+            format!("d3d9,d3d10core,d3d11,dxgi={dxvk};d3d12,d3d12core={vkd3d}"),
+        ));
+        if settings.esync {
+            self.env.push(("WINEESYNC".to_string(), "1".to_string()));
+        }
+        if settings.fsync {
+            self.env.push(("WINEFSYNC".to_string(), "1".to_string()));
+        }
+        self.env.extend(settings.env.iter().cloned());
+
+        if settings.gamemode {
+            self.wrappers.push("gamemoderun".to_string());
+        }
+        if settings.mangohud {
+            self.wrappers.push("mangohud".to_string());
+        }
+        self
+    }
+}
+
+impl From<&Program> for Launch {
+    fn from(program: &Program) -> Self {
+        Self {
+            executable: program.executable.clone(),
+            arguments: program.arguments.clone(),
+            working_dir: program.working_dir.clone(),
+            env: vec![],
+            wrappers: vec![],
+        }
+    }
+}
+
+/// The runners available on this system.
+#[derive(Clone, Debug, Default)]
+pub struct Runners {
+    runners: Vec<Arc<dyn Runner>>,
+}
+
+impl Runners {
+    /// Looks for system Wine and Proton-style runners installed under `runners_dir`.
+    pub async fn discover(runners_dir: &Path) -> Self {
+        let mut runners: Vec<Arc<dyn Runner>> = vec![];
+
+        if let Some(wine) = Wine::discover().await {
+            runners.push(Arc::new(wine));
+        }
+
+        for proton in Proton::discover(runners_dir).await {
+            runners.push(Arc::new(proton));
+        }
+
+        if let Ok(script) = std::env::var(FAKE_RUNNER_ENV) {
+            runners.push(Arc::new(Fake::new(script)));
+        }
+
+        Self { runners }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn Runner>> {
+        self.runners
+            .iter()
+            .find(|runner| runner.id() == id)
+            .cloned()
+    }
+
+    /// The runner `program` is started with: the one it overrides, or else the
+    /// one of `bottle`.
+    pub fn for_program(&self, bottle: &Bottle, program: &Program) -> Result<Arc<dyn Runner>> {
+        let id = program.overrides.runner.as_ref().unwrap_or(&bottle.runner);
+        self.get(id)
+            .ok_or_else(|| Error::RunnerNotFound(id.clone()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Runner>> {
+        self.runners.iter()
+    }
+
+    /// The runner assigned to new bottles when none is chosen.
+    pub fn default_id(&self) -> Option<&str> {
+        self.runners.first().map(|runner| runner.id())
+    }
+}
+
+impl FromIterator<Arc<dyn Runner>> for Runners {
+    fn from_iter<T: IntoIterator<Item = Arc<dyn Runner>>>(iter: T) -> Self {
+        Self {
+            runners: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Creates the Wine prefix at `prefix`, or updates it to the version of `runner`.
+pub async fn init_prefix(runner: &dyn Runner, prefix: &Path) -> Result<()> {
+    run_tool(runner, prefix, "wineboot", &["--init"]).await
+}
+
+/// Sets the Windows version reported to the programs of `prefix`.
+pub async fn set_windows_version(
+    runner: &dyn Runner,
+    prefix: &Path,
+    version: WindowsVersion,
+) -> Result<()> {
+    run_tool(runner, prefix, "winecfg", &["-v", version.winecfg()]).await?;
+    tokio::fs::create_dir_all(prefix).await?;
+    tokio::fs::write(prefix.join(WINDOWS_VERSION), version.winecfg()).await?;
+    Ok(())
+}
+
+/// Sets the Windows version of `prefix` to `version` unless it is set already,
+/// such as before starting a program in it.
+pub async fn apply_windows_version(
+    runner: &dyn Runner,
+    prefix: &Path,
+    version: WindowsVersion,
+) -> Result<()> {
+    let current = tokio::fs::read_to_string(prefix.join(WINDOWS_VERSION))
+        .await
+        .unwrap_or_default();
+    if current.trim() == version.winecfg() {
+        return Ok(());
+    }
+    set_windows_version(runner, prefix, version).await
+}
+
+/// Sets the string value `name` of the registry key `key` in `prefix`.
+pub async fn set_registry(
+    runner: &dyn Runner,
+    prefix: &Path,
+    key: &str,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    let arguments = ["add", key, "/v", name, "/t", "REG_SZ", "/d", value, "/f"];
+    run_tool(runner, prefix, "reg", &arguments).await
+}
+
+/// Removes the value `name` of the registry key `key` from `prefix`.
+pub async fn delete_registry(
+    runner: &dyn Runner,
+    prefix: &Path,
+    key: &str,
+    name: &str,
+) -> Result<()> {
+    run_tool(runner, prefix, "reg", &["delete", key, "/v", name, "/f"]).await
+}
+
+/// Runs one of the tools shipped with Wine and waits for it to finish.
+async fn run_tool(
+    runner: &dyn Runner,
+    prefix: &Path,
+    tool: &str,
+    arguments: &[&str],
+) -> Result<()> {
+    let launch = Launch {
+        executable: tool.into(),
+        arguments: arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect(),
+        ..Default::default()
+    };
+
+    let status = runner.spawn(prefix, &launch)?.wait().await?;
+    if !status.success() {
+        return Err(Error::Prefix(format!("{tool} exited with {status}")));
+    }
+    Ok(())
+}
+
+/// Builds the command running `launch` through the `wine` binary at `wine`.
+pub(crate) fn wine_command(wine: &Path, prefix: &Path, launch: &Launch) -> Command {
+    let mut command = match launch.wrappers.split_first() {
+        Some((wrapper, arguments)) => {
+            let mut command = Command::new(wrapper);
+            command.args(arguments).arg(wine);
+            command
+        }
+        None => Command::new(wine),
+    };
+    command
+        .arg(&launch.executable)
+        .args(&launch.arguments)
+        .env("WINEPREFIX", prefix)
+        .envs(launch.env.iter().map(|(key, value)| (key, value)))
+        .process_group(0)
+        .kill_on_drop(false);
+    if let Some(working_dir) = &launch.working_dir {
+        command.current_dir(working_dir);
+    }
+    command
+}
+
+/// Runs `wineserver -k` against `prefix`, which terminates every process in it.
+pub(crate) async fn wineserver_kill(wineserver: &Path, prefix: &Path) -> io::Result<()> {
+    let status = Command::new(wineserver)
+        .arg("-k")
+        .env("WINEPREFIX", prefix)
+        .status()
+        .await?;
+
+    // wineserver exits with an error when no server was running for the prefix.
+    if !status.success() {
+        eprintln!("wineserver -k exited with {status}");
+    }
+    Ok(())
+}
+
+/// Sends `signal` to every process in the group led by `pid`. A group that is
+/// already gone isn't an error.
+pub(crate) fn signal_group(pid: u32, signal: i32) -> io::Result<()> {
+    let pid = pid_t(pid)?;
+    // SAFETY: killpg has no memory safety requirements.
+    if unsafe { libc::killpg(pid, signal) } == 0 {
+        return Ok(());
+    }
+    match io::Error::last_os_error() {
+        why if why.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+        why => Err(why),
+    }
+}
+
+/// Whether any process is left in the group led by `pid`.
+pub(crate) fn group_exists(pid: u32) -> bool {
+    let Ok(pid) = pid_t(pid) else {
+        return false;
+    };
+    // SAFETY: killpg has no memory safety requirements, and signal 0 only checks
+    // that the group exists.
+    let result = unsafe { libc::killpg(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Converts a process id for libc, refusing 0, which would signal the
+/// application itself.
+fn pid_t(pid: u32) -> io::Result<libc::pid_t> {
+    libc::pid_t::try_from(pid)
+        .ok()
+        .filter(|pid| *pid > 0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid process id"))
+}
+
+/// The command line starting a program in the first terminal emulator found.
+fn terminal() -> Option<Vec<String>> {
+    TERMINALS.iter().find_map(|(terminal, arguments)| {
+        let terminal = which(terminal)?;
+        let mut command = vec![terminal.display().to_string()];
+        command.extend(arguments.iter().map(|argument| argument.to_string()));
+        Some(command)
+    })
+}
+
+/// Finds an executable named `name` in `PATH`.
+pub(crate) fn which(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,109 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::BoxFuture;
+use tokio::process::{Child, Command};
+
+use super::{Launch, Runner};
+
+/// A runner that never touches Wine.
+///
+/// Every launch runs `script` with `sh -c` instead of the requested program, so the
+/// whole launch path can be exercised on machines without Wine. Invocations are
+/// recorded and can be inspected through [`Fake::invocations`].
+#[derive(Clone, Debug)]
+pub struct Fake {
+    script: String,
+    invocations: Arc<Mutex<Vec<Invocation>>>,
+    children: Arc<Mutex<Vec<(PathBuf, u32)>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Invocation {
+    Spawn { prefix: PathBuf, launch: Launch },
+    Kill { prefix: PathBuf },
+}
+
+impl Fake {
+    pub const ID: &'static str = "fake";
+
+    pub fn new(script: impl Into<String>) -> Self {
+        Self {
+            script: script.into(),
+            invocations: Arc::default(),
+            children: Arc::default(),
+        }
+    }
+
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.invocations.lock().unwrap().clone()
+    }
+
+    fn record(&self, invocation: Invocation) {
+        self.invocations.lock().unwrap().push(invocation);
+    }
+}
+
+impl Runner for Fake {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn name(&self) -> &str {
+        "Fake runner"
+    }
+
+    fn version(&self) -> Option<&str> {
+        None
+    }
+
+    fn spawn(&self, prefix: &Path, launch: &Launch) -> io::Result<Child> {
+        self.record(Invocation::Spawn {
+            prefix: prefix.to_path_buf(),
+            launch: launch.clone(),
+        });
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.script)
+            .env("WINEPREFIX", prefix)
+            .env("FAKE_EXECUTABLE", &launch.executable)
+            .envs(launch.env.iter().map(|(key, value)| (key, value)))
+            .process_group(0);
+        if let Some(working_dir) = &launch.working_dir {
+            command.current_dir(working_dir);
+        }
+        let child = command.spawn()?;
+
+        if let Some(pid) = child.id() {
+            self.children
+                .lock()
+                .unwrap()
+                .push((prefix.to_path_buf(), pid));
+        }
+        Ok(child)
+    }
+
+    fn kill<'a>(&'a self, prefix: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            self.record(Invocation::Kill {
+                prefix: prefix.to_path_buf(),
+            });
+
+            let pids: Vec<u32> = {
+                let mut children = self.children.lock().unwrap();
+                let (killed, kept): (Vec<_>, Vec<_>) =
+                    children.drain(..).partition(|(p, _)| p == prefix);
+                *children = kept;
+                killed.into_iter().map(|(_, pid)| pid).collect()
+            };
+
+            for pid in pids {
+                super::signal_group(pid, libc::SIGTERM)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use futures_util::future::BoxFuture;
+use tokio::{fs, process::Child};
+
+use super::{Launch, Runner};
+
+/// A Proton-style runner unpacked into its own directory.
+///
+/// Both the upstream `dist/` layout and the `files/` layout used by GE-Proton are
+/// recognized, as well as Wine builds with `bin/` at their root. Programs are started through the bundled `wine` binary rather than
+/// the `proton` script, which expects to be driven by Steam.
+#[derive(Clone, Debug)]
+pub struct Proton {
+    id: String,
+    bin: PathBuf,
+    version: Option<String>,
+}
+
+impl Proton {
+    /// Lists the Proton runners found directly under `dir`.
+    pub async fn discover(dir: &Path) -> Vec<Self> {
+        let mut runners = vec![];
+        let Ok(mut entries) = fs::read_dir(dir).await else {
+            return runners;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Some(runner) = Self::open(&entry.path()).await {
+                runners.push(runner);
+            }
+        }
+
+        runners.sort_by(|a, b| a.id.cmp(&b.id));
+        runners
+    }
+
+    /// Opens the runner at `path`, if it looks like a Proton build.
+    pub async fn open(path: &Path) -> Option<Self> {
+        let id = path.file_name()?.to_str()?.to_string();
+        let bin = ["files", "dist", ""]
+            .iter()
+            .map(|layout| path.join(layout).join("bin"))
+            .find(|bin| bin.join("wine").is_file())?;
+
+        // Proton ships a `version` file holding a build timestamp and a name.
+        let version = fs::read_to_string(path.join("version"))
+            .await
+            .ok()
+            .and_then(|version| version.split_whitespace().last().map(str::to_string));
+
+        Some(Self { id, bin, version })
+    }
+}
+
+impl Runner for Proton {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.id
+    }
+
+    fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    fn spawn(&self, prefix: &Path, launch: &Launch) -> io::Result<Child> {
+        super::wine_command(&self.bin.join("wine"), prefix, launch).spawn()
+    }
+
+    fn kill<'a>(&'a self, prefix: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let wineserver = self.bin.join("wineserver");
+            super::wineserver_kill(&wineserver, prefix).await
+        })
+    }
+}
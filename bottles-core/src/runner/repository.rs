@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Downloadable runners.
+//!
+//! A repository publishes an [`Index`] of runner releases as JSON. Releases are
+//! tarballs holding a Proton-style runner, which are verified against their
+//! checksum, unpacked into the runners directory and named after their id.
+//! Runners still used by a bottle or a program can't be removed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::{Proton, Runner};
+use crate::error::{Error, Result};
+use crate::fetch::{self, Fetcher};
+use crate::store::{self, BottleStore};
+
+/// Index used when none is configured.
+pub const INDEX: &str =
+    "https://github.com/edfloreshz/bottles-next-demo/raw/main/resources/runners.json";
+
+/// Environment variable holding the location of the index, which wins over the
+/// configured one. `file://` paths work too, for mirrors and offline use.
+pub const INDEX_ENV: &str = "BOTTLES_NEXT_RUNNER_INDEX";
+
+/// The runner releases published by a repository.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Index {
+    pub releases: Vec<Release>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    /// Id of the runner once installed, which names its folder.
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    /// A tarball holding the runner, compressed with gzip, xz or zstd.
+    pub url: String,
+    pub sha256: String,
+    /// Size of the archive in bytes, when known.
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// Installs the runners of an [`Index`] under the runners directory, where
+/// [`Runners::discover`](super::Runners::discover) finds them.
+#[derive(Clone, Debug)]
+pub struct Repository {
+    index: String,
+    fetcher: Arc<dyn Fetcher>,
+    runners_dir: PathBuf,
+    download_dir: PathBuf,
+}
+
+impl Repository {
+    pub fn new(index: impl Into<String>, fetcher: Arc<dyn Fetcher>) -> Self {
+        Self {
+            index: index.into(),
+            fetcher,
+            runners_dir: store::data_dir().join("runners"),
+            download_dir: store::cache_dir().join("runners"),
+        }
+    }
+
+    /// Keeps runners and downloads under `dir` instead of the data and cache
+    /// directories.
+    pub fn with_dir(self, dir: &Path) -> Self {
+        Self {
+            runners_dir: dir.join("runners"),
+            download_dir: dir.join("downloads"),
+            ..self
+        }
+    }
+
+    /// Fetches the index, every time so new releases show up.
+    pub async fn releases(&self) -> Result<Vec<Release>> {
+        let path = self.download_dir.join("index.json");
+        // A leftover copy would be taken for a fresh one.
+        if fs::try_exists(&path).await? {
+            fs::remove_file(&path).await?;
+        }
+        fetch::download(&*self.fetcher, &self.index, None, &path, &|_| {}).await?;
+        let contents = fs::read(&path).await;
+        fs::remove_file(&path).await?;
+        let index: Index = serde_json::from_slice(&contents?).map_err(|why| Error::Manifest {
+            path: self.index.clone(),
+            reason: why.to_string(),
+        })?;
+        Ok(index.releases)
+    }
+
+    /// The runners installed from repositories or unpacked by hand.
+    pub async fn installed(&self) -> Vec<Proton> {
+        Proton::discover(&self.runners_dir).await
+    }
+
+    /// Downloads, verifies and unpacks `release`. `progress` is called with the
+    /// fraction of the work done and what is being done.
+    pub async fn install(
+        &self,
+        release: &Release,
+        progress: impl Fn(f32, String) + Send + Sync,
+    ) -> Result<()> {
+        let destination = self.path(&release.id)?;
+        if fs::try_exists(&destination).await? {
+            return Err(Error::Runner(format!(
+                "{} is already installed",
+                release.id
+            )));
+        }
+
+        let name = fetch::file_name(&release.url)
+            .ok_or_else(|| Error::Download(format!("{} doesn't name a file", release.url)))?;
+        let archive = self.download_dir.join(name);
+        let status = format!("Downloading {}...", release.name);
+        fetch::download(
+            &*self.fetcher,
+            &release.url,
+            Some(&release.sha256),
+            &archive,
+            &|done| progress(done * 0.8, status.clone()),
+        )
+        .await?;
+
+        progress(0.8, format!("Unpacking {}...", release.name));
+        // Unpacked next to its final place first so a failure never leaves half a
+        // runner behind.
+        let unpacked = self.runners_dir.join(format!(".{}.part", release.id));
+        if fs::try_exists(&unpacked).await? {
+            fs::remove_dir_all(&unpacked).await?;
+        }
+        fs::create_dir_all(&unpacked).await?;
+        let result = {
+            let (archive, unpacked) = (archive.clone(), unpacked.clone());
+            tokio::task::spawn_blocking(move || fetch::unpack(&archive, &unpacked))
+                .await
+                .map_err(|why| Error::Runner(why.to_string()))
+                .and_then(|result| result)
+        };
+        if let Err(why) = result {
+            fs::remove_dir_all(&unpacked).await?;
+            return Err(why);
+        }
+
+        let root = fetch::single_folder(&unpacked)
+            .await?
+            .unwrap_or(unpacked.clone());
+        if Proton::open(&root).await.is_none() {
+            fs::remove_dir_all(&unpacked).await?;
+            return Err(Error::Runner(format!(
+                "{} doesn't hold a runner",
+                release.url
+            )));
+        }
+        fs::rename(&root, &destination).await?;
+        if fs::try_exists(&unpacked).await? {
+            fs::remove_dir_all(&unpacked).await?;
+        }
+        fs::remove_file(&archive).await?;
+
+        progress(1.0, "Done".to_string());
+        Ok(())
+    }
+
+    /// Removes the runner `id`, unless a bottle or a program uses it.
+    pub async fn remove(&self, store: &BottleStore, id: &str) -> Result<()> {
+        let users = users(store, id).await?;
+        if !users.is_empty() {
+            return Err(Error::Runner(format!(
+                "{id} is used by {}",
+                users.join(", ")
+            )));
+        }
+        fs::remove_dir_all(self.path(id)?).await?;
+        Ok(())
+    }
+
+    /// Removes the installed runners no bottle or program uses, returning their ids.
+    pub async fn prune(&self, store: &BottleStore) -> Result<Vec<String>> {
+        let used = used(store).await?;
+        let mut removed = vec![];
+        for runner in self.installed().await {
+            let id = runner.id().to_string();
+            if !used.contains(&id) {
+                fs::remove_dir_all(self.path(&id)?).await?;
+                removed.push(id);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// The folder of the runner `id`, which must not reach outside the runners
+    /// directory.
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+            return Err(Error::Runner(format!("invalid runner id {id}")));
+        }
+        Ok(self.runners_dir.join(id))
+    }
+}
+
+/// Where to read the index from: the environment first, then `configured`, then
+/// [`INDEX`].
+pub fn index_url(configured: Option<&str>) -> String {
+    std::env::var(INDEX_ENV)
+        .ok()
+        .or_else(|| configured.map(str::to_string))
+        .filter(|index| !index.trim().is_empty())
+        .unwrap_or_else(|| INDEX.to_string())
+}
+
+/// Ids of the runners used by bottles and by the overrides of their programs.
+pub async fn used(store: &BottleStore) -> Result<HashSet<String>> {
+    let bottles = store.load().await?;
+    let programs = store.library(&bottles).await?;
+    Ok(bottles
+        .into_iter()
+        .map(|bottle| bottle.runner)
+        .chain(
+            programs
+                .into_iter()
+                .filter_map(|program| program.overrides.runner),
+        )
+        .collect())
+}
+
+/// Names of the bottles using the runner `id`, by themselves or through one of
+/// their programs.
+async fn users(store: &BottleStore, id: &str) -> Result<Vec<String>> {
+    let bottles = store.load().await?;
+    let programs = store.library(&bottles).await?;
+    Ok(bottles
+        .into_iter()
+        .filter(|bottle| {
+            bottle.runner == id
+                || programs.iter().any(|program| {
+                    program.bottle == bottle.id && program.overrides.runner.as_deref() == Some(id)
+                })
+        })
+        .map(|bottle| bottle.name)
+        .collect())
+}
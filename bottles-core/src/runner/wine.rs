@@ -0,0 +1,61 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use futures_util::future::BoxFuture;
+use tokio::process::{Child, Command};
+
+use super::{Launch, Runner};
+
+/// The Wine installation found in `PATH`.
+#[derive(Clone, Debug)]
+pub struct Wine {
+    wine: PathBuf,
+    wineserver: PathBuf,
+    version: Option<String>,
+}
+
+impl Wine {
+    pub const ID: &'static str = "system-wine";
+
+    pub async fn discover() -> Option<Self> {
+        let wine = super::which("wine")?;
+        let wineserver =
+            super::which("wineserver").unwrap_or_else(|| wine.with_file_name("wineserver"));
+
+        let version = Command::new(&wine)
+            .arg("--version")
+            .output()
+            .await
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+        Some(Self {
+            wine,
+            wineserver,
+            version,
+        })
+    }
+}
+
+impl Runner for Wine {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn name(&self) -> &str {
+        "System Wine"
+    }
+
+    fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    fn spawn(&self, prefix: &Path, launch: &Launch) -> io::Result<Child> {
+        super::wine_command(&self.wine, prefix, launch).spawn()
+    }
+
+    fn kill<'a>(&'a self, prefix: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(super::wineserver_kill(&self.wineserver, prefix))
+    }
+}
@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Discovery of the programs installed in a prefix.
+//!
+//! Installers don't tell anyone where they put things, so the scanner looks in the
+//! places Windows programs usually end up: the shortcuts of the Start Menu and of
+//! the desktops, then the `Program Files` folders. Helpers such as uninstallers,
+//! redistributable installers and crash reporters are left out.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::error::{Error, Result};
+use crate::lnk::{self, Shortcut};
+use crate::program;
+
+/// How deep the scanner goes below `Program Files`. Games keep their executable
+/// a few folders down, e.g. `Publisher/Game/bin/x64/game.exe`.
+const MAX_DEPTH: usize = 5;
+
+/// How deep the scanner goes below the Start Menu, where shortcuts sit in a
+/// folder per publisher or program.
+const MAX_MENU_DEPTH: usize = 3;
+
+/// Start Menu folders, relative to `drive_c` for everyone and to the folder of
+/// each user otherwise. Older Wine versions used the shorter layout.
+const START_MENU: [&str; 2] = [
+    "ProgramData/Microsoft/Windows/Start Menu/Programs",
+    "AppData/Roaming/Microsoft/Windows/Start Menu/Programs",
+];
+const LEGACY_START_MENU: &str = "Start Menu/Programs";
+
+/// Folders that only hold components shipped with Windows or with other programs.
+const IGNORED_DIRS: [&str; 11] = [
+    "common files",
+    "internet explorer",
+    "windows media player",
+    "windows nt",
+    "windows photo viewer",
+    "windowspowershell",
+    "microsoft.net",
+    "_commonredist",
+    "redist",
+    "directx",
+    "vcredist",
+];
+
+/// Beginnings of the names of executables that aren't worth launching on their own.
+const IGNORED_NAMES: [&str; 12] = [
+    "unins",
+    "uninst",
+    "setup",
+    "install",
+    "update",
+    "crashreport",
+    "crashhandler",
+    "errorreport",
+    "vc_redist",
+    "vcredist",
+    "dxsetup",
+    "dotnet",
+];
+
+/// An executable found in a prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Discovered {
+    pub title: String,
+    pub executable: PathBuf,
+    pub arguments: Vec<String>,
+    pub working_dir: Option<PathBuf>,
+    /// Where the executable is as seen by Windows programs, e.g. `C:\Games\game.exe`.
+    pub location: String,
+}
+
+/// Stops a running scan when cancelled. Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Looks for the executables installed in `prefix`, sorted by title.
+///
+/// Fails with [`Error::Cancelled`] as soon as `cancel` is triggered.
+pub async fn scan(prefix: &Path, cancel: Cancel) -> Result<Vec<Discovered>> {
+    let drive = prefix.join("drive_c");
+    tokio::task::spawn_blocking(move || scan_drive(&drive, &cancel))
+        .await
+        .map_err(io::Error::other)?
+}
+
+fn scan_drive(drive: &Path, cancel: &Cancel) -> Result<Vec<Discovered>> {
+    let users = users(drive);
+
+    // Shortcuts come first, their names are the ones people know programs by.
+    let mut menus = vec![drive.join(START_MENU[0])];
+    for user in &users {
+        menus.push(user.join(START_MENU[1]));
+        menus.push(user.join(LEGACY_START_MENU));
+    }
+    let mut shortcuts = vec![];
+    for menu in menus {
+        walk(&menu, MAX_MENU_DEPTH, cancel, &mut |path| {
+            shortcuts.extend(shortcut(drive, path))
+        })?;
+    }
+    // Wine links the desktop of the prefix user to the one of the host, which
+    // has nothing to do with the prefix, so only real folders are looked at.
+    let desktops: Vec<PathBuf> = users
+        .iter()
+        .map(|user| user.join("Desktop"))
+        .filter(|desktop| fs::symlink_metadata(desktop).is_ok_and(|metadata| metadata.is_dir()))
+        .collect();
+    let mut executables = vec![];
+    for desktop in &desktops {
+        walk(desktop, 0, cancel, &mut |path| {
+            shortcuts.extend(shortcut(drive, path));
+            executables.extend(executable(drive, path));
+        })?;
+    }
+    for folder in ["Program Files", "Program Files (x86)"] {
+        walk(&drive.join(folder), MAX_DEPTH, cancel, &mut |path| {
+            executables.extend(executable(drive, path))
+        })?;
+    }
+
+    // The same file can be reachable from several shortcuts and folders.
+    let mut targets = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut found = vec![];
+    for discovered in shortcuts {
+        let key = canonical(&discovered.executable);
+        targets.insert(key.clone());
+        if seen.insert((key, discovered.arguments.clone())) {
+            found.push(discovered);
+        }
+    }
+    for discovered in executables {
+        let key = canonical(&discovered.executable);
+        if !targets.contains(&key) && seen.insert((key, vec![])) {
+            found.push(discovered);
+        }
+    }
+    found.sort_by_cached_key(|discovered| discovered.title.to_lowercase());
+    Ok(found)
+}
+
+/// The folders of the prefix users.
+fn users(drive: &Path) -> Vec<PathBuf> {
+    let Ok(users) = fs::read_dir(drive.join("users")) else {
+        return vec![];
+    };
+    users.flatten().map(|user| user.path()).collect()
+}
+
+/// Calls `visit` with the files of `dir`, going down at most `depth` folders
+/// without following symlinks.
+fn walk(dir: &Path, depth: usize, cancel: &Cancel, visit: &mut dyn FnMut(&Path)) -> Result<()> {
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // Missing folders and folders the user can't read have nothing to offer.
+        Err(_) => return Ok(()),
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if depth > 0 && !IGNORED_DIRS.contains(&name.as_str()) {
+                walk(&entry.path(), depth - 1, cancel, visit)?;
+            }
+        } else if file_type.is_file() {
+            visit(&entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// The program started by the shortcut at `path`, if it starts one worth listing.
+fn shortcut(drive: &Path, path: &Path) -> Option<Discovered> {
+    if !has_extension(path, "lnk") {
+        return None;
+    }
+    let shortcut = Shortcut::read(path, drive)
+        .inspect_err(|why| eprintln!("skipping shortcut {}: {why}", path.display()))
+        .ok()?;
+    let executable = lnk::unix_path(drive, &shortcut.target)?;
+    let name = executable.file_name()?.to_string_lossy().to_lowercase();
+    if !is_program(&name) || !executable.is_file() {
+        return None;
+    }
+    Some(Discovered {
+        title: path.file_stem()?.to_string_lossy().to_string(),
+        location: shortcut.target,
+        arguments: shortcut
+            .arguments
+            .as_deref()
+            .map(program::split_arguments)
+            .unwrap_or_default(),
+        working_dir: shortcut
+            .working_dir
+            .and_then(|dir| lnk::unix_path(drive, &dir)),
+        executable,
+    })
+}
+
+/// The program at `path`, if it is one worth listing.
+fn executable(drive: &Path, path: &Path) -> Option<Discovered> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if !is_program(&name) {
+        return None;
+    }
+    Some(Discovered {
+        title: path.file_stem()?.to_string_lossy().to_string(),
+        location: windows_path(drive, path),
+        arguments: vec![],
+        working_dir: None,
+        executable: path.to_path_buf(),
+    })
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_program(name: &str) -> bool {
+    name.ends_with(".exe")
+        && !IGNORED_NAMES
+            .iter()
+            .any(|ignored| name.starts_with(ignored))
+}
+
+/// Turns a path inside `drive` into the path Windows programs see.
+fn windows_path(drive: &Path, path: &Path) -> String {
+    match path.strip_prefix(drive) {
+        Ok(relative) => {
+            let relative = relative.to_string_lossy().replace('/', "\\");
+            format!("C:\\{relative}")
+        }
+        Err(_) => path.display().to_string(),
+    }
+}
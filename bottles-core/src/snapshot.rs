@@ -0,0 +1,447 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Snapshots of bottle prefixes.
+//!
+//! A snapshot records every folder, file and symlink of a prefix in a manifest
+//! stored under the `snapshots` folder of the bottle. File contents are
+//! compressed into an object store addressed by their SHA-256 hash and shared by
+//! every snapshot of the bottle, so files that don't change between snapshots are
+//! only stored once.
+
+use std::collections::HashSet;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bottle::Bottle;
+use crate::error::{Error, Result};
+use crate::store::{self, read_ron, write_ron};
+
+pub const SNAPSHOTS: &str = "snapshots";
+const OBJECTS: &str = "objects";
+const LOCK: &str = ".lock";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub description: String,
+    pub created: DateTime<Utc>,
+    /// Size of the files of the prefix when the snapshot was taken, in bytes.
+    pub size: u64,
+}
+
+/// How many snapshots of a bottle are kept around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    /// Number of snapshots to keep, older ones are deleted. Zero keeps them all.
+    pub keep_last: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self { keep_last: 10 }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    snapshot: Snapshot,
+    /// Every entry of the prefix, parents before their children.
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Entry {
+    Dir {
+        path: PathBuf,
+        mode: u32,
+    },
+    File {
+        path: PathBuf,
+        mode: u32,
+        hash: String,
+    },
+    Symlink {
+        path: PathBuf,
+        target: PathBuf,
+    },
+}
+
+/// The snapshots of a single bottle.
+///
+/// Operations on the same bottle wait for each other through a lock file, even
+/// across processes: deleting a snapshot collects the objects no manifest refers
+/// to, which would include those of a snapshot still being created.
+#[derive(Clone, Debug)]
+pub struct Snapshots {
+    root: PathBuf,
+}
+
+impl Snapshots {
+    pub fn new(bottle: &Bottle) -> Self {
+        Self {
+            root: bottle.path.join(SNAPSHOTS),
+        }
+    }
+
+    /// Lists the snapshots of the bottle, newest first.
+    pub async fn list(&self) -> Result<Vec<Snapshot>> {
+        let mut snapshots = vec![];
+        for path in self.manifests().await? {
+            match read_ron::<Manifest>(&path).await {
+                Ok(manifest) => snapshots.push(manifest.snapshot),
+                // A broken manifest shouldn't hide the other snapshots.
+                Err(why) => eprintln!("skipping snapshot {}: {why}", path.display()),
+            }
+        }
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created));
+        Ok(snapshots)
+    }
+
+    /// Captures the contents of `prefix`, calling `progress` with the fraction of
+    /// the prefix stored so far.
+    pub async fn create(
+        &self,
+        prefix: &Path,
+        description: impl Into<String>,
+        progress: impl Fn(f32) + Send + 'static,
+    ) -> Result<Snapshot> {
+        let _lock = self.lock().await?;
+        let prefix = prefix.to_path_buf();
+        let objects = self.root.join(OBJECTS);
+        let (entries, size) = blocking(move || capture(&prefix, &objects, &progress)).await?;
+
+        let snapshot = Snapshot {
+            id: uuid::Uuid::new_v4().to_string(),
+            description: description.into(),
+            created: Utc::now(),
+            size,
+        };
+        let manifest = Manifest {
+            snapshot: snapshot.clone(),
+            entries,
+        };
+        write_ron(&self.manifest(&snapshot.id)?, &manifest).await?;
+        Ok(snapshot)
+    }
+
+    /// Replaces `prefix` with the contents of the snapshot `id`.
+    ///
+    /// The snapshot is extracted next to the prefix first and swapped in once
+    /// complete, so a failed restore leaves the current prefix untouched.
+    pub async fn restore(
+        &self,
+        id: &str,
+        prefix: &Path,
+        progress: impl Fn(f32) + Send + 'static,
+    ) -> Result<()> {
+        let _lock = self.lock().await?;
+        let manifest: Manifest = read_ron(&self.manifest(id)?)
+            .await
+            .map_err(|_| Error::Snapshot(format!("snapshot {id} not found")))?;
+
+        let prefix = prefix.to_path_buf();
+        let objects = self.root.join(OBJECTS);
+        blocking(move || {
+            let restored = prefix.with_extension("restoring");
+            if restored.exists() {
+                fs::remove_dir_all(&restored)?;
+            }
+            if let Err(why) = extract(&manifest.entries, &objects, &restored, &progress) {
+                let _ = fs::remove_dir_all(&restored);
+                return Err(why);
+            }
+            swap(&prefix, &restored)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Deletes the snapshot `id` along with the objects only it referred to.
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        let _lock = self.lock().await?;
+        tokio::fs::remove_file(self.manifest(id)?).await?;
+        self.collect_garbage().await
+    }
+
+    /// Deletes the snapshots `retention` doesn't keep, returning them.
+    pub async fn prune(&self, retention: Retention) -> Result<Vec<Snapshot>> {
+        let _lock = self.lock().await?;
+        let mut snapshots = self.list().await?;
+        if retention.keep_last == 0 || snapshots.len() <= retention.keep_last {
+            return Ok(vec![]);
+        }
+
+        let expired = snapshots.split_off(retention.keep_last);
+        for snapshot in &expired {
+            tokio::fs::remove_file(self.manifest(&snapshot.id)?).await?;
+        }
+        self.collect_garbage().await?;
+        Ok(expired)
+    }
+
+    /// Waits for the other operations on the snapshots of the bottle, and keeps
+    /// them waiting until the returned lock is dropped.
+    async fn lock(&self) -> Result<File> {
+        let path = self.root.join(LOCK);
+        blocking(move || {
+            fs::create_dir_all(path.parent().expect("the lock lives in the snapshots"))?;
+            let file = File::options()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)?;
+            // SAFETY: the descriptor belongs to `file`, which outlives the call. The
+            // lock goes away when the file is closed.
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            Ok(file)
+        })
+        .await
+    }
+
+    /// Removes the objects no snapshot refers to anymore.
+    async fn collect_garbage(&self) -> Result<()> {
+        let mut referenced = HashSet::new();
+        for path in self.manifests().await? {
+            // Bail out on unreadable manifests rather than dropping their objects.
+            let manifest: Manifest = read_ron(&path).await?;
+            referenced.extend(
+                manifest
+                    .entries
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        Entry::File { hash, .. } => Some(hash),
+                        _ => None,
+                    }),
+            );
+        }
+
+        let objects = self.root.join(OBJECTS);
+        blocking(move || {
+            if !objects.exists() {
+                return Ok(());
+            }
+            for shard in fs::read_dir(&objects)? {
+                let shard = shard?.path();
+                if !shard.is_dir() {
+                    fs::remove_file(&shard)?;
+                    continue;
+                }
+                for object in fs::read_dir(&shard)? {
+                    let object = object?.path();
+                    let name = object.file_name().and_then(|name| name.to_str());
+                    if !name.is_some_and(|name| referenced.contains(name)) {
+                        fs::remove_file(&object)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn manifests(&self) -> Result<Vec<PathBuf>> {
+        let mut manifests = vec![];
+        let mut entries = match tokio::fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(manifests),
+            Err(why) => return Err(why.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "ron") {
+                manifests.push(path);
+            }
+        }
+        Ok(manifests)
+    }
+
+    /// The manifest of the snapshot `id`. Snapshot ids are UUIDs like bottle ids,
+    /// and anything else is refused before it can name a file outside the
+    /// snapshots.
+    fn manifest(&self, id: &str) -> Result<PathBuf> {
+        if !store::is_valid_id(id) {
+            return Err(Error::Snapshot(format!("snapshot {id} not found")));
+        }
+        Ok(self.root.join(format!("{id}.ron")))
+    }
+}
+
+/// Runs filesystem heavy work away from the async runtime.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|why| Error::Snapshot(why.to_string()))?
+}
+
+/// Stores the files of `prefix` in `objects`, returning the entries of the prefix
+/// and the size of its files.
+fn capture(prefix: &Path, objects: &Path, progress: &dyn Fn(f32)) -> Result<(Vec<Entry>, u64)> {
+    let mut found = vec![];
+    walk(prefix, &mut found)?;
+    let total: u64 = found
+        .iter()
+        .filter(|(_, metadata)| metadata.is_file())
+        .map(|(_, metadata)| metadata.len())
+        .sum();
+
+    let mut stored = 0;
+    let mut entries = Vec::with_capacity(found.len());
+    for (path, metadata) in found {
+        let relative = path
+            .strip_prefix(prefix)
+            .expect("walked paths are inside the prefix")
+            .to_path_buf();
+        let mode = metadata.permissions().mode();
+
+        let entry = if metadata.is_symlink() {
+            Entry::Symlink {
+                path: relative,
+                target: fs::read_link(&path)?,
+            }
+        } else if metadata.is_dir() {
+            Entry::Dir {
+                path: relative,
+                mode,
+            }
+        } else if metadata.is_file() {
+            let hash = store_object(objects, &path)?;
+            stored += metadata.len();
+            progress(stored as f32 / total.max(1) as f32);
+            Entry::File {
+                path: relative,
+                mode,
+                hash,
+            }
+        } else {
+            // Sockets and pipes left behind by wineserver are meaningless later.
+            continue;
+        };
+        entries.push(entry);
+    }
+    Ok((entries, total))
+}
+
+/// Collects every entry under `dir` without following symlinks, parents first.
+fn walk(dir: &Path, found: &mut Vec<(PathBuf, Metadata)>) -> io::Result<()> {
+    let mut children = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    children.sort_by_key(|child| child.file_name());
+    for child in children {
+        let path = child.path();
+        let metadata = fs::symlink_metadata(&path)?;
+        let is_dir = metadata.is_dir();
+        found.push((path.clone(), metadata));
+        if is_dir {
+            walk(&path, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Compresses `path` into the object store, returning the hash of its contents.
+///
+/// The file is hashed while it is compressed so the object always matches its
+/// name, even if the file changes in the meantime.
+fn store_object(objects: &Path, path: &Path) -> io::Result<String> {
+    fs::create_dir_all(objects)?;
+    let tmp = objects.join(format!("{}.tmp", uuid::Uuid::new_v4()));
+    let mut encoder = GzEncoder::new(File::create(&tmp)?, Compression::default());
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path)?;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        encoder.write_all(&buffer[..read])?;
+    }
+    encoder.finish()?;
+
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let object = object_path(objects, &hash);
+    if object.exists() {
+        fs::remove_file(&tmp)?;
+    } else {
+        fs::create_dir_all(object.parent().expect("objects live in a shard"))?;
+        fs::rename(&tmp, &object)?;
+    }
+    Ok(hash)
+}
+
+fn object_path(objects: &Path, hash: &str) -> PathBuf {
+    objects.join(&hash[..2]).join(hash)
+}
+
+/// Recreates `entries` under `target`.
+fn extract(entries: &[Entry], objects: &Path, target: &Path, progress: &dyn Fn(f32)) -> Result<()> {
+    fs::create_dir_all(target)?;
+    for (index, entry) in entries.iter().enumerate() {
+        match entry {
+            Entry::Dir { path, .. } => fs::create_dir(target.join(path))?,
+            Entry::File { path, mode, hash } => {
+                let object = File::open(object_path(objects, hash)).map_err(|_| {
+                    Error::Snapshot(format!("missing contents of {}", path.display()))
+                })?;
+                let destination = target.join(path);
+                io::copy(
+                    &mut GzDecoder::new(object),
+                    &mut File::create(&destination)?,
+                )?;
+                fs::set_permissions(&destination, fs::Permissions::from_mode(*mode))?;
+            }
+            Entry::Symlink { path, target: link } => {
+                std::os::unix::fs::symlink(link, target.join(path))?
+            }
+        }
+        progress((index + 1) as f32 / entries.len() as f32);
+    }
+
+    // Folders get their permissions last, read-only ones would refuse their files.
+    for entry in entries.iter().rev() {
+        if let Entry::Dir { path, mode } = entry {
+            fs::set_permissions(target.join(path), fs::Permissions::from_mode(*mode))?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves `restored` in place of `prefix`.
+fn swap(prefix: &Path, restored: &Path) -> io::Result<()> {
+    let old = prefix.with_extension("old");
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    if prefix.exists() {
+        fs::rename(prefix, &old)?;
+    }
+    if let Err(why) = fs::rename(restored, prefix) {
+        let _ = fs::rename(&old, prefix);
+        return Err(why);
+    }
+    if let Err(why) = fs::remove_dir_all(&old) {
+        if why.kind() != io::ErrorKind::NotFound {
+            eprintln!(
+                "failed to remove the previous prefix {}: {why}",
+                old.display()
+            );
+        }
+    }
+    Ok(())
+}
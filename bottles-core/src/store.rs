@@ -0,0 +1,334 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! On-disk storage for bottles.
+//!
+//! Every bottle lives in its own folder under the store root. The folder holds a
+//! `bottle.ron` manifest, a `programs.ron` library, the `activity.ron` play
+//! statistics of those programs and the `dependencies.ron` and `components.ron`
+//! ledgers of the redistributables and components installed next to the Wine
+//! prefix used by the bottle.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{fs, sync::Mutex};
+
+use crate::bottle::Bottle;
+use crate::desktop::Desktop;
+use crate::error::{Error, Result};
+use crate::program::{Activity, Program};
+
+pub const MANIFEST: &str = "bottle.ron";
+pub const PROGRAMS: &str = "programs.ron";
+pub const ACTIVITY: &str = "activity.ron";
+pub const DEPENDENCIES: &str = "dependencies.ron";
+pub const COMPONENTS: &str = "components.ron";
+
+#[derive(Clone, Debug)]
+pub struct BottleStore {
+    root: PathBuf,
+    /// Where the menu entries of programs are removed from along with them.
+    desktop: Desktop,
+    /// Serializes read-modify-write cycles on program manifests.
+    programs_lock: Arc<Mutex<()>>,
+}
+
+impl Default for BottleStore {
+    fn default() -> Self {
+        Self::new(data_dir().join("bottles"))
+    }
+}
+
+impl BottleStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            desktop: Desktop::default(),
+            programs_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Keeps the menu entries of programs in `desktop` instead of the
+    /// applications menu of the user.
+    pub fn with_desktop(self, desktop: Desktop) -> Self {
+        Self { desktop, ..self }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn desktop(&self) -> &Desktop {
+        &self.desktop
+    }
+
+    /// Discovers every bottle under the store root, oldest first.
+    ///
+    /// Folders without a readable manifest are skipped so a single broken bottle
+    /// doesn't hide the rest.
+    pub async fn load(&self) -> Result<Vec<Bottle>> {
+        fs::create_dir_all(&self.root).await?;
+
+        let mut bottles = vec![];
+        let mut entries = fs::read_dir(&self.root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            match self.read(&entry.path()).await {
+                Ok(bottle) => bottles.push(bottle),
+                Err(why) => eprintln!("skipping bottle at {}: {why}", entry.path().display()),
+            }
+        }
+
+        bottles.sort_by_key(|bottle| bottle.created);
+        Ok(bottles)
+    }
+
+    /// Writes a new bottle to disk, returning it with its folder filled in.
+    pub async fn create(&self, mut bottle: Bottle) -> Result<Bottle> {
+        bottle.path = self.bottle_dir(&bottle.id)?;
+        fs::create_dir_all(bottle.prefix()).await?;
+        self.save(&bottle).await?;
+        Ok(bottle)
+    }
+
+    pub async fn rename(&self, id: &str, name: impl Into<String>) -> Result<Bottle> {
+        let mut bottle = self.get(id).await?;
+        bottle.name = name.into();
+        self.save(&bottle).await?;
+        Ok(bottle)
+    }
+
+    /// Deletes a bottle along with the menu entries of its programs.
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        let bottle = self.get(id).await?;
+        fs::remove_dir_all(&bottle.path).await?;
+        self.desktop.remove_bottle(id).await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Bottle> {
+        let path = self.bottle_dir(id)?;
+        if !fs::try_exists(path.join(MANIFEST)).await? {
+            return Err(Error::BottleNotFound(id.to_string()));
+        }
+        self.read(&path).await
+    }
+
+    /// Adds up the size of every file of a bottle, snapshots included. Symbolic
+    /// links are counted as links and never followed.
+    pub async fn disk_usage(&self, id: &str) -> Result<u64> {
+        let mut size = 0;
+        let mut pending = vec![self.bottle_dir(id)?];
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = fs::symlink_metadata(entry.path()).await?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    size += metadata.len();
+                }
+            }
+        }
+        Ok(size)
+    }
+
+    pub async fn save(&self, bottle: &Bottle) -> Result<()> {
+        write_ron(&bottle.path.join(MANIFEST), bottle).await
+    }
+
+    /// Reads the programs registered in a bottle.
+    pub async fn programs(&self, bottle: &str) -> Result<Vec<Program>> {
+        let path = self.bottle_dir(bottle)?.join(PROGRAMS);
+        if !fs::try_exists(&path).await? {
+            return Ok(vec![]);
+        }
+
+        let mut programs: Vec<Program> = read_ron(&path).await?;
+        let activity = self.activity(bottle).await?;
+        for program in &mut programs {
+            program.bottle = bottle.to_string();
+            program.activity = activity.get(&program.id).copied().unwrap_or_default();
+        }
+        Ok(programs)
+    }
+
+    /// Collects the programs of every bottle into a single library.
+    pub async fn library(&self, bottles: &[Bottle]) -> Result<Vec<Program>> {
+        let mut library = vec![];
+        for bottle in bottles {
+            match self.programs(&bottle.id).await {
+                Ok(programs) => library.extend(programs),
+                Err(why) => eprintln!("skipping programs of {}: {why}", bottle.name),
+            }
+        }
+        Ok(library)
+    }
+
+    /// Adds `program` to its bottle, replacing any program with the same id.
+    pub async fn save_program(&self, program: Program) -> Result<Program> {
+        let _guard = self.programs_lock.lock().await;
+        let mut programs = self.programs(&program.bottle).await?;
+        match programs.iter_mut().find(|p| p.id == program.id) {
+            Some(existing) => *existing = program.clone(),
+            None => programs.push(program.clone()),
+        }
+        write_ron(&self.bottle_dir(&program.bottle)?.join(PROGRAMS), &programs).await?;
+        Ok(program)
+    }
+
+    /// Removes a program from its bottle and from the applications menu.
+    pub async fn remove_program(&self, bottle: &str, id: &str) -> Result<()> {
+        let dir = self.bottle_dir(bottle)?;
+        let _guard = self.programs_lock.lock().await;
+        let mut programs = self.programs(bottle).await?;
+        // Only ids of actual programs name menu entries to remove.
+        if programs.iter().any(|program| program.id == id) {
+            self.desktop.remove(bottle, id).await?;
+        }
+        programs.retain(|program| program.id != id);
+        write_ron(&dir.join(PROGRAMS), &programs).await?;
+
+        let mut activity = self.activity(bottle).await?;
+        if activity.remove(id).is_some() {
+            write_ron(&dir.join(ACTIVITY), &activity).await?;
+        }
+        Ok(())
+    }
+
+    /// Remembers that a program was started at `started`.
+    pub async fn record_start(
+        &self,
+        bottle: &str,
+        program: &str,
+        started: DateTime<Utc>,
+    ) -> Result<Activity> {
+        self.update_activity(bottle, program, |activity| {
+            activity.last_started = Some(started);
+        })
+        .await
+    }
+
+    /// Remembers that a program started at `started` ran until `stopped`.
+    pub async fn record_stop(
+        &self,
+        bottle: &str,
+        program: &str,
+        started: DateTime<Utc>,
+        stopped: DateTime<Utc>,
+    ) -> Result<Activity> {
+        let elapsed = (stopped - started).num_seconds().max(0) as u64;
+        self.update_activity(bottle, program, |activity| {
+            activity.last_stopped = Some(stopped);
+            activity.play_time += elapsed;
+        })
+        .await
+    }
+
+    async fn activity(&self, bottle: &str) -> Result<BTreeMap<String, Activity>> {
+        let path = self.bottle_dir(bottle)?.join(ACTIVITY);
+        if !fs::try_exists(&path).await? {
+            return Ok(BTreeMap::new());
+        }
+        read_ron(&path).await
+    }
+
+    async fn update_activity(
+        &self,
+        bottle: &str,
+        program: &str,
+        update: impl FnOnce(&mut Activity),
+    ) -> Result<Activity> {
+        let _guard = self.programs_lock.lock().await;
+        let mut activity = self.activity(bottle).await?;
+        let entry = activity.entry(program.to_string()).or_default();
+        update(entry);
+        let updated = *entry;
+        write_ron(&self.bottle_dir(bottle)?.join(ACTIVITY), &activity).await?;
+        Ok(updated)
+    }
+
+    /// The folder of the bottle `id`. Ids come from links, the command line and
+    /// D-Bus too, so anything but a UUID is refused before it can name a path
+    /// outside the store.
+    fn bottle_dir(&self, id: &str) -> Result<PathBuf> {
+        if !is_valid_id(id) {
+            return Err(Error::BottleNotFound(id.to_string()));
+        }
+        Ok(self.root.join(id))
+    }
+
+    /// Reads the bottle in the folder at `path`, which is named after its id.
+    async fn read(&self, path: &Path) -> Result<Bottle> {
+        let folder = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| is_valid_id(name))
+            .ok_or_else(|| Error::Manifest {
+                path: path.display().to_string(),
+                reason: "the folder isn't named after a bottle id".to_string(),
+            })?;
+        let mut bottle: Bottle = read_ron(&path.join(MANIFEST)).await?;
+        if bottle.id != folder {
+            // The folder is what every lookup goes through, so it wins over a
+            // manifest that was copied or edited by hand. The next save fixes
+            // the manifest.
+            eprintln!(
+                "bottle {} is stored under {folder}, using the folder name",
+                bottle.id
+            );
+            bottle.id = folder.to_string();
+        }
+        bottle.path = path.to_path_buf();
+        Ok(bottle)
+    }
+}
+
+/// Whether `id` can be the id of a bottle: a UUID in its hyphenated form, as
+/// [`Bottle::new`] creates them.
+pub fn is_valid_id(id: &str) -> bool {
+    uuid::Uuid::try_parse(id).is_ok_and(|uuid| uuid.hyphenated().to_string() == id)
+}
+
+/// The directory where Bottles Next keeps its data.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("bottles-next")
+}
+
+/// The directory where Bottles Next keeps files it can recreate.
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("bottles-next")
+}
+
+pub(crate) async fn read_ron<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = fs::read_to_string(path).await?;
+    ron::from_str(&contents).map_err(|why| Error::Manifest {
+        path: path.display().to_string(),
+        reason: why.to_string(),
+    })
+}
+
+/// Writes `value` next to `path` first and then renames it into place, so a crash
+/// never leaves a half-written manifest behind.
+pub(crate) async fn write_ron<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let contents =
+        ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(|why| {
+            Error::Manifest {
+                path: path.display().to_string(),
+                reason: why.to_string(),
+            }
+        })?;
+
+    let tmp = path.with_extension("ron.tmp");
+    fs::write(&tmp, contents).await?;
+    fs::rename(&tmp, path).await?;
+    Ok(())
+}
@@ -0,0 +1,315 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Keeps track of the processes started by the application.
+//!
+//! Every launched program is watched by a background task that reports state
+//! changes as [`Event`]s, which the interface listens to through [`Supervisor::events`].
+//! Launch and exit times are recorded in the bottle store as they happen.
+
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use futures_util::Stream;
+use tokio::process::{Child, Command};
+use tokio::sync::{broadcast, oneshot, Mutex};
+
+use crate::bottle::Bottle;
+use crate::error::{Error, Result};
+use crate::program::{Activity, Program};
+use crate::runner::{self, Launch, Runner};
+use crate::store::BottleStore;
+
+/// How long a stopped program has to exit before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct Supervisor {
+    store: BottleStore,
+    processes: Arc<Mutex<HashMap<String, Process>>>,
+    events: broadcast::Sender<Event>,
+}
+
+#[derive(Debug)]
+struct Process {
+    bottle: String,
+    pid: u32,
+    /// Asks the watcher task to kill the process.
+    kill: oneshot::Sender<()>,
+}
+
+/// A change in the state of a supervised program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Started {
+        program: String,
+        pid: u32,
+    },
+    /// The program exited on its own.
+    Exited {
+        program: String,
+        code: i32,
+    },
+    /// The program was terminated by a signal it didn't ask for.
+    Crashed {
+        program: String,
+        signal: Option<i32>,
+    },
+    /// The program was stopped from the application.
+    Stopped {
+        program: String,
+    },
+    /// The play statistics of the program were updated.
+    Activity {
+        program: String,
+        activity: Activity,
+    },
+}
+
+impl Supervisor {
+    pub fn new(store: BottleStore) -> Self {
+        Self {
+            store,
+            processes: Arc::default(),
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    /// Starts `program` in `bottle` through `runner` and returns its process id.
+    ///
+    /// The Windows version of the bottle is applied to the prefix first when it
+    /// changed, then the pre-launch script of the program runs. The program isn't
+    /// started when either fails.
+    pub async fn launch(
+        &self,
+        runner: &dyn Runner,
+        bottle: &Bottle,
+        program: &Program,
+    ) -> Result<u32> {
+        if self.processes.lock().await.contains_key(&program.id) {
+            return Err(Error::AlreadyRunning(program.title.clone()));
+        }
+
+        let prefix = bottle.prefix();
+        let launch = Launch::new(bottle, program)?;
+        runner::apply_windows_version(runner, &prefix, bottle.settings.windows).await?;
+        if let Some(script) = &program.overrides.pre_launch {
+            let status = run_script(script, &prefix, &launch).await?;
+            if !status.success() {
+                return Err(Error::Script(format!(
+                    "pre-launch script exited with {status}"
+                )));
+            }
+        }
+
+        // The script gave another launch of the program time to get through.
+        let mut processes = self.processes.lock().await;
+        if processes.contains_key(&program.id) {
+            return Err(Error::AlreadyRunning(program.title.clone()));
+        }
+        let mut child = runner.spawn(&prefix, &launch)?;
+        let pid = child.id().unwrap_or_default();
+        let (kill, killed) = oneshot::channel();
+        processes.insert(
+            program.id.clone(),
+            Process {
+                bottle: bottle.id.clone(),
+                pid,
+                kill,
+            },
+        );
+        let _ = self.events.send(Event::Started {
+            program: program.id.clone(),
+            pid,
+        });
+
+        let started = Utc::now();
+        let supervisor = self.clone();
+        let post_launch = program.overrides.post_launch.clone();
+        let bottle = bottle.id.clone();
+        let program = program.id.clone();
+        tokio::spawn(async move {
+            supervisor
+                .record(
+                    &program,
+                    supervisor.store.record_start(&bottle, &program, started),
+                )
+                .await;
+
+            // A program asked to stop is reported as stopped, even when it was
+            // killed by the runner before this task got to look.
+            let event = tokio::select! {
+                biased;
+                Ok(()) = killed => {
+                    terminate(&mut child, pid, &program).await;
+                    Event::Stopped { program: program.clone() }
+                }
+                status = child.wait() => match status {
+                    Ok(status) => exit_event(program.clone(), status),
+                    Err(why) => {
+                        eprintln!("failed to wait for program {program}: {why}");
+                        Event::Crashed { program: program.clone(), signal: None }
+                    }
+                },
+            };
+
+            // The program may have been stopped and launched again in the meantime.
+            let mut processes = supervisor.processes.lock().await;
+            if processes
+                .get(&program)
+                .is_some_and(|process| process.pid == pid)
+            {
+                processes.remove(&program);
+            }
+            drop(processes);
+            let _ = supervisor.events.send(event);
+
+            if let Some(script) = post_launch {
+                match run_script(&script, &prefix, &launch).await {
+                    Ok(status) if !status.success() => {
+                        eprintln!("post-launch script of {program} exited with {status}")
+                    }
+                    Ok(_) => {}
+                    Err(why) => eprintln!("failed to run post-launch script of {program}: {why}"),
+                }
+            }
+
+            let stopped = Utc::now();
+            supervisor
+                .record(
+                    &program,
+                    supervisor
+                        .store
+                        .record_stop(&bottle, &program, started, stopped),
+                )
+                .await;
+        });
+
+        Ok(pid)
+    }
+
+    /// Terminates a program started by [`Supervisor::launch`].
+    pub async fn stop(&self, program: &str) -> Result<()> {
+        let process = self.processes.lock().await.remove(program);
+        if let Some(process) = process {
+            let _ = process.kill.send(());
+        }
+        Ok(())
+    }
+
+    /// Terminates every process running in `bottle`, including the ones started
+    /// outside of the application.
+    pub async fn stop_bottle(&self, runner: &dyn Runner, bottle: &Bottle) -> Result<()> {
+        // The watchers learn first that their programs are being stopped, so they
+        // don't report them as crashed once the runner kills them.
+        let mut processes = self.processes.lock().await;
+        let programs: Vec<String> = processes
+            .iter()
+            .filter(|(_, process)| process.bottle == bottle.id)
+            .map(|(program, _)| program.clone())
+            .collect();
+        for program in programs {
+            if let Some(process) = processes.remove(&program) {
+                let _ = process.kill.send(());
+            }
+        }
+        drop(processes);
+
+        runner.kill(&bottle.prefix()).await?;
+        Ok(())
+    }
+
+    /// The programs running right now, with their process ids.
+    pub async fn running(&self) -> Vec<(String, u32)> {
+        self.processes
+            .lock()
+            .await
+            .iter()
+            .map(|(program, process)| (program.clone(), process.pid))
+            .collect()
+    }
+
+    async fn record(
+        &self,
+        program: &str,
+        update: impl std::future::Future<Output = Result<Activity>>,
+    ) {
+        match update.await {
+            Ok(activity) => {
+                let _ = self.events.send(Event::Activity {
+                    program: program.to_string(),
+                    activity,
+                });
+            }
+            Err(why) => eprintln!("failed to record activity of {program}: {why}"),
+        }
+    }
+
+    /// Streams every event emitted from now on.
+    pub fn events(&self) -> impl Stream<Item = Event> + Send + 'static {
+        futures_util::stream::unfold(self.events.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => return Some((event, events)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+/// Stops the program started as `child` along with every process it started.
+///
+/// Runners start programs in a process group of their own, so wrappers such as
+/// `gamemoderun` or a terminal don't leave the actual program running. The group
+/// is asked to terminate, and whatever is left of it after [`STOP_TIMEOUT`] is
+/// killed.
+async fn terminate(child: &mut Child, pid: u32, program: &str) {
+    if let Err(why) = runner::signal_group(pid, libc::SIGTERM) {
+        eprintln!("failed to stop program {program}: {why}");
+    }
+
+    let deadline = tokio::time::Instant::now() + STOP_TIMEOUT;
+    let _ = tokio::time::timeout_at(deadline, child.wait()).await;
+    // The group outlives its leader while anything else in it still runs.
+    while tokio::time::Instant::now() < deadline && runner::group_exists(pid) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    if let Err(why) = runner::signal_group(pid, libc::SIGKILL) {
+        eprintln!("failed to kill program {program}: {why}");
+    }
+    if let Err(why) = child.kill().await {
+        eprintln!("failed to kill program {program}: {why}");
+    }
+}
+
+/// Runs a launch script of a program with `sh`, in the environment of the program.
+async fn run_script(script: &str, prefix: &Path, launch: &Launch) -> io::Result<ExitStatus> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(script)
+        .env("WINEPREFIX", prefix)
+        .envs(launch.env.iter().map(|(key, value)| (key, value)));
+    if let Some(working_dir) = &launch.working_dir {
+        command.current_dir(working_dir);
+    }
+    command.status().await
+}
+
+fn exit_event(program: String, status: ExitStatus) -> Event {
+    match status.code() {
+        Some(code) => Event::Exited { program, code },
+        None => Event::Crashed {
+            program,
+            signal: status.signal(),
+        },
+    }
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! `bottles-next://` links, used by desktop entries, browsers and shortcuts to
+//! reach a program.
+//!
+//! - `bottles-next://run/<bottle>/<program>` launches a program.
+//! - `bottles-next://open/<bottle>/<program>` shows a program in the window.
+//! - `bottles-next://open/<bottle>` shows a bottle in the window.
+//!
+//! Links come from anywhere, so ones whose bottle isn't a valid bottle id are
+//! refused before the id can reach the store.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::store;
+
+pub const SCHEME: &str = "bottles-next";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Uri {
+    Run {
+        bottle: String,
+        program: String,
+    },
+    Open {
+        bottle: String,
+        program: Option<String>,
+    },
+}
+
+impl FromStr for Uri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Uri(uri.to_string());
+        let path = uri
+            .strip_prefix(SCHEME)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .ok_or_else(invalid)?;
+        let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(invalid());
+        }
+        if segments
+            .get(1)
+            .is_some_and(|bottle| !store::is_valid_id(bottle))
+        {
+            return Err(invalid());
+        }
+
+        match segments.as_slice() {
+            ["run", bottle, program] => Ok(Self::Run {
+                bottle: bottle.to_string(),
+                program: program.to_string(),
+            }),
+            ["open", bottle] => Ok(Self::Open {
+                bottle: bottle.to_string(),
+                program: None,
+            }),
+            ["open", bottle, program] => Ok(Self::Open {
+                bottle: bottle.to_string(),
+                program: Some(program.to_string()),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Run { bottle, program } => write!(f, "{SCHEME}://run/{bottle}/{program}"),
+            Self::Open {
+                bottle,
+                program: None,
+            } => write!(f, "{SCHEME}://open/{bottle}"),
+            Self::Open {
+                bottle,
+                program: Some(program),
+            } => write!(f, "{SCHEME}://open/{bottle}/{program}"),
+        }
+    }
+}
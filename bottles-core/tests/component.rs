@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::component::{self, Components, Kind, Release};
+use bottles_core::error::Error;
+use bottles_core::fetch::Mirror;
+use bottles_core::runner::fake::Invocation;
+use bottles_core::runner::{self, Fake};
+use bottles_core::store::{self, BottleStore};
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+
+const DLLS: [&str; 2] = ["d3d11", "dxgi"];
+
+struct Setup {
+    store: BottleStore,
+    bottle: Bottle,
+    mirror: Mirror,
+    components: Components,
+    fake: Fake,
+    windows: PathBuf,
+}
+
+/// A 64-bit bottle holding builtin copies of the DLLs DXVK replaces.
+async fn setup(dir: &Path) -> Setup {
+    let store = BottleStore::new(dir.join("bottles"));
+    let bottle = store
+        .create(Bottle::new("Games", Environment::Gaming, Fake::ID))
+        .await
+        .unwrap();
+    let windows = bottle.prefix().join("drive_c/windows");
+    for folder in ["system32", "syswow64"] {
+        std::fs::create_dir_all(windows.join(folder)).unwrap();
+        for dll in DLLS {
+            let path = windows.join(folder).join(format!("{dll}.dll"));
+            std::fs::write(path, format!("builtin {folder}")).unwrap();
+        }
+    }
+
+    let mirror = Mirror::new(dir.join("mirror"));
+    let components = Components::new(Arc::new(mirror.clone())).with_dir(&dir.join("cache"));
+    // The registry tools are the only programs components run.
+    let fake = Fake::new(r#"[ "$FAKE_EXECUTABLE" = reg ]"#);
+    Setup {
+        store,
+        bottle,
+        mirror,
+        components,
+        fake,
+        windows,
+    }
+}
+
+/// Publishes DXVK `version` as a gzipped tarball laid out like the upstream
+/// ones, with DLLs holding `x64 <version>` and `x32 <version>`.
+fn publish(mirror: &Mirror, version: &str) -> Release {
+    let url = format!("https://example.com/dxvk-{version}.tar.gz");
+    let mut tar = tar::Builder::new(GzEncoder::new(vec![], Default::default()));
+    for architecture in ["x64", "x32"] {
+        for dll in DLLS {
+            let contents = format!("{architecture} {version}");
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            let path = format!("dxvk-{version}/{architecture}/{dll}.dll");
+            tar.append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+    }
+    let archive = tar.into_inner().unwrap().finish().unwrap();
+    let path = mirror.path(&url);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, &archive).unwrap();
+    Release {
+        kind: Kind::Dxvk,
+        version: version.to_string(),
+        url,
+        sha256: Some(format!("{:x}", Sha256::digest(&archive))),
+    }
+}
+
+/// The arguments `reg` was run with, in order.
+fn registry(fake: &Fake) -> Vec<Vec<String>> {
+    fake.invocations()
+        .into_iter()
+        .filter_map(|invocation| match invocation {
+            Invocation::Spawn { launch, .. } if launch.executable == Path::new("reg") => {
+                Some(launch.arguments)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn read(windows: &Path, folder: &str, dll: &str) -> String {
+    std::fs::read_to_string(windows.join(folder).join(format!("{dll}.dll"))).unwrap()
+}
+
+#[tokio::test]
+async fn components_are_installed_and_uninstalled() {
+    let dir = tempfile::tempdir().unwrap();
+    let Setup {
+        store,
+        bottle,
+        mirror,
+        components,
+        fake,
+        windows,
+    } = setup(dir.path()).await;
+    let release = publish(&mirror, "2.5.3");
+
+    let installed = components
+        .install(&store, &fake, &bottle, &release, |_, _| {})
+        .await
+        .unwrap();
+    for dll in DLLS {
+        assert_eq!(read(&windows, "system32", dll), "x64 2.5.3");
+        assert_eq!(read(&windows, "syswow64", dll), "x32 2.5.3");
+    }
+    let reg = registry(&fake);
+    assert_eq!(reg.len(), DLLS.len());
+    for (arguments, dll) in reg.iter().zip(DLLS) {
+        assert_eq!(arguments[..4], ["add", runner::DLL_OVERRIDES, "/v", dll]);
+        assert_eq!(arguments[7], "native,builtin");
+    }
+
+    // The version is saved with the bottle, and what it changed next to it.
+    assert_eq!(installed.components[&Kind::Dxvk], "2.5.3");
+    let saved = store.get(&bottle.id).await.unwrap();
+    assert_eq!(saved.components[&Kind::Dxvk], "2.5.3");
+    assert!(bottle.path.join(store::COMPONENTS).is_file());
+    let ledger = component::ledger(&bottle).await.unwrap();
+    let dxvk = &ledger[&Kind::Dxvk];
+    assert_eq!(dxvk.files.len(), 2 * DLLS.len());
+    assert_eq!(dxvk.replaced, dxvk.files);
+    assert_eq!(dxvk.overrides, DLLS);
+    // Backups don't share folders with dependency packages.
+    assert!(bottle.path.join("backups/component-dxvk").is_dir());
+    assert!(!bottle.path.join("backups/dxvk").exists());
+
+    let uninstalled = components
+        .uninstall(&store, &fake, &installed, Kind::Dxvk)
+        .await
+        .unwrap();
+    for dll in DLLS {
+        assert_eq!(read(&windows, "system32", dll), "builtin system32");
+        assert_eq!(read(&windows, "syswow64", dll), "builtin syswow64");
+    }
+    let reg = registry(&fake);
+    assert_eq!(reg.len(), 2 * DLLS.len());
+    assert!(reg[DLLS.len()..]
+        .iter()
+        .all(|arguments| arguments[0] == "delete"));
+    assert!(!bottle.path.join("backups/component-dxvk").exists());
+    assert!(uninstalled.components.is_empty());
+    assert!(store.get(&bottle.id).await.unwrap().components.is_empty());
+    assert!(component::ledger(&bottle).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn installing_another_version_reverts_the_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let Setup {
+        store,
+        bottle,
+        mirror,
+        components,
+        fake,
+        windows,
+    } = setup(dir.path()).await;
+    let old = publish(&mirror, "2.4.1");
+    let new = publish(&mirror, "2.5.3");
+
+    let bottle = components
+        .install(&store, &fake, &bottle, &old, |_, _| {})
+        .await
+        .unwrap();
+    let bottle = components
+        .install(&store, &fake, &bottle, &new, |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(read(&windows, "system32", "d3d11"), "x64 2.5.3");
+    assert_eq!(bottle.components[&Kind::Dxvk], "2.5.3");
+
+    // The backups are still the builtin DLLs, not the ones of the first version.
+    components
+        .uninstall(&store, &fake, &bottle, Kind::Dxvk)
+        .await
+        .unwrap();
+    assert_eq!(read(&windows, "system32", "d3d11"), "builtin system32");
+    assert_eq!(read(&windows, "syswow64", "d3d11"), "builtin syswow64");
+}
+
+#[tokio::test]
+async fn downloads_not_matching_their_checksum_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let Setup {
+        store,
+        bottle,
+        mirror,
+        components,
+        fake,
+        windows,
+    } = setup(dir.path()).await;
+    let release = Release {
+        sha256: Some(format!("{:x}", Sha256::digest(b"another archive"))),
+        ..publish(&mirror, "2.5.3")
+    };
+
+    assert!(matches!(
+        components
+            .install(&store, &fake, &bottle, &release, |_, _| {})
+            .await,
+        Err(Error::Checksum { .. })
+    ));
+    assert_eq!(read(&windows, "system32", "d3d11"), "builtin system32");
+    assert!(registry(&fake).is_empty());
+    assert!(store.get(&bottle.id).await.unwrap().components.is_empty());
+    assert!(component::ledger(&bottle).await.unwrap().is_empty());
+    assert!(!dir
+        .path()
+        .join("cache/components/dxvk-2.5.3.tar.gz")
+        .exists());
+}
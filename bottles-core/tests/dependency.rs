@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::dependency::{self, Dependencies, Manifest, Package};
+use bottles_core::error::Error;
+use bottles_core::fetch::Mirror;
+use bottles_core::runner::fake::Invocation;
+use bottles_core::runner::{self, Fake};
+use bottles_core::store::{self, BottleStore};
+use sha2::{Digest, Sha256};
+
+const MANIFEST: &str = r#"[
+    (
+        id: "settings",
+        name: "Settings",
+        requires: ["runtime"],
+        actions: [Registry(key: "HKEY_CURRENT_USER\\Software\\Game", name: "Mode", value: "fast")],
+    ),
+    (
+        id: "runtime",
+        name: "Runtime",
+        sources: [(url: "https://example.com/runtime.dll", file: "runtime.dll", sha256: SHA256)],
+        actions: [
+            Copy(file: "runtime.dll", destination: "C:\\windows\\system32"),
+            Override(dll: "runtime", mode: "native,builtin"),
+        ],
+    ),
+    (
+        id: "broken",
+        name: "Broken",
+        sources: [
+            (url: "https://example.com/runtime.dll", file: "runtime.dll"),
+            (url: "https://example.com/setup.exe", file: "setup.exe"),
+        ],
+        actions: [
+            Copy(file: "runtime.dll", destination: "C:\\windows\\system32"),
+            Run(file: "setup.exe"),
+        ],
+    ),
+]"#;
+
+const RUNTIME: &[u8] = b"native runtime";
+
+struct Setup {
+    bottle: Bottle,
+    mirror: Mirror,
+    dependencies: Dependencies,
+    fake: Fake,
+    /// The DLL the runtime package replaces.
+    dll: PathBuf,
+}
+
+/// A bottle holding a builtin copy of the DLL of the runtime package, with
+/// every package published in a mirror.
+async fn setup(dir: &Path, sha256: &[u8]) -> Setup {
+    let store = BottleStore::new(dir.join("bottles"));
+    let bottle = store
+        .create(Bottle::new("Games", Environment::Gaming, Fake::ID))
+        .await
+        .unwrap();
+    let dll = bottle.prefix().join("drive_c/windows/system32/runtime.dll");
+    std::fs::create_dir_all(dll.parent().unwrap()).unwrap();
+    std::fs::write(&dll, "builtin runtime").unwrap();
+
+    let mirror = Mirror::new(dir.join("mirror"));
+    for (url, contents) in [
+        ("https://example.com/runtime.dll", RUNTIME),
+        ("https://example.com/setup.exe", b"setup"),
+    ] {
+        let path = mirror.path(url);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    let sha256 = format!("Some(\"{:x}\")", Sha256::digest(sha256));
+    let packages: Vec<Package> = ron::from_str(&MANIFEST.replace("SHA256", &sha256)).unwrap();
+    let dependencies = Dependencies::new(Manifest::new(packages), Arc::new(mirror.clone()))
+        .with_dir(&dir.join("cache"));
+    // Installers fail, the registry tools don't.
+    let fake = Fake::new(r#"[ "$FAKE_EXECUTABLE" = reg ]"#);
+    Setup {
+        bottle,
+        mirror,
+        dependencies,
+        fake,
+        dll,
+    }
+}
+
+/// The arguments `reg` was run with, in order.
+fn registry(fake: &Fake) -> Vec<Vec<String>> {
+    fake.invocations()
+        .into_iter()
+        .filter_map(|invocation| match invocation {
+            Invocation::Spawn { launch, .. } if launch.executable == Path::new("reg") => {
+                Some(launch.arguments)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn packages_are_installed_and_uninstalled() {
+    let dir = tempfile::tempdir().unwrap();
+    let Setup {
+        bottle,
+        mirror,
+        dependencies,
+        fake,
+        dll,
+    } = setup(dir.path(), RUNTIME).await;
+
+    dependencies
+        .install(&fake, &bottle, "settings", |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dll).unwrap(), RUNTIME);
+    let reg = registry(&fake);
+    assert_eq!(reg.len(), 2);
+    assert_eq!(reg[0][1], runner::DLL_OVERRIDES);
+    assert_eq!(reg[0][3], "runtime");
+    assert_eq!(reg[1][1], "HKEY_CURRENT_USER\\Software\\Game");
+
+    // The ledger is kept on disk next to the bottle.
+    assert!(bottle.path.join(store::DEPENDENCIES).is_file());
+    let ledger = dependency::ledger(&bottle).await.unwrap();
+    assert_eq!(ledger.keys().collect::<Vec<_>>(), ["runtime", "settings"]);
+    let runtime = &ledger["runtime"];
+    let relative = PathBuf::from("drive_c/windows/system32/runtime.dll");
+    assert_eq!(runtime.files, [relative]);
+    assert_eq!(runtime.replaced, runtime.files);
+    assert_eq!(runtime.overrides, ["runtime"]);
+    assert_eq!(
+        ledger["settings"].registry,
+        [(
+            "HKEY_CURRENT_USER\\Software\\Game".to_string(),
+            "Mode".to_string()
+        )]
+    );
+
+    // Installed packages aren't fetched nor installed again.
+    std::fs::remove_dir_all(mirror.path("https://example.com")).unwrap();
+    dependencies
+        .install(&fake, &bottle, "settings", |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(registry(&fake).len(), 2);
+
+    // Packages other packages need stay until those are gone.
+    assert!(matches!(
+        dependencies.uninstall(&fake, &bottle, "runtime").await,
+        Err(Error::Dependency(_))
+    ));
+    dependencies
+        .uninstall(&fake, &bottle, "settings")
+        .await
+        .unwrap();
+    dependencies
+        .uninstall(&fake, &bottle, "runtime")
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dll).unwrap(), b"builtin runtime");
+    assert!(!bottle.path.join("backups/runtime").exists());
+    let reg = registry(&fake);
+    assert_eq!(reg.len(), 4);
+    assert!(reg[2..].iter().all(|arguments| arguments[0] == "delete"));
+    assert!(dependency::ledger(&bottle).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn downloads_not_matching_their_checksum_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let Setup {
+        bottle,
+        dependencies,
+        fake,
+        dll,
+        ..
+    } = setup(dir.path(), b"another runtime").await;
+
+    assert!(matches!(
+        dependencies
+            .install(&fake, &bottle, "settings", |_, _| {})
+            .await,
+        Err(Error::Checksum { .. })
+    ));
+    assert_eq!(std::fs::read(&dll).unwrap(), b"builtin runtime");
+    assert!(registry(&fake).is_empty());
+    assert!(dependency::ledger(&bottle).await.unwrap().is_empty());
+    assert!(!dir
+        .path()
+        .join("cache/dependencies/runtime/runtime.dll")
+        .exists());
+}
+
+#[tokio::test]
+async fn failed_installations_are_undone() {
+    let dir = tempfile::tempdir().unwrap();
+    let Setup {
+        bottle,
+        dependencies,
+        fake,
+        dll,
+        ..
+    } = setup(dir.path(), RUNTIME).await;
+
+    assert!(matches!(
+        dependencies
+            .install(&fake, &bottle, "broken", |_, _| {})
+            .await,
+        Err(Error::Dependency(_))
+    ));
+    assert_eq!(std::fs::read(&dll).unwrap(), b"builtin runtime");
+    assert!(!bottle.path.join("backups/broken").exists());
+    assert!(dependency::ledger(&bottle).await.unwrap().is_empty());
+}
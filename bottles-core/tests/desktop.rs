@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::desktop::Desktop;
+use bottles_core::program::Program;
+use bottles_core::store::BottleStore;
+
+fn setup(dir: &Path) -> BottleStore {
+    BottleStore::new(dir.join("bottles"))
+        .with_desktop(Desktop::new(dir.join("applications"), dir.join("icons")))
+}
+
+fn files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    files
+}
+
+async fn program(store: &BottleStore, bottle: &Bottle, title: &str) -> Program {
+    let program = Program::new(&bottle.id, title, PathBuf::from("C:\\missing.exe"));
+    let program = store.save_program(program).await.unwrap();
+    store.desktop().install(bottle, &program).await.unwrap();
+    program
+}
+
+fn entry(bottle: &Bottle, program: &Program) -> String {
+    format!("bottles-next-{}-{}.desktop", bottle.id, program.id)
+}
+
+#[tokio::test]
+async fn entries_launch_their_program() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = setup(dir.path());
+    let games = store
+        .create(Bottle::new("Games", Environment::Gaming, "wine"))
+        .await
+        .unwrap();
+    let tools = store
+        .create(Bottle::new("Tools", Environment::Software, "wine"))
+        .await
+        .unwrap();
+    let game = program(&store, &games, "Tower \"Defense\"\n2").await;
+    let tool = program(&store, &tools, "Tool").await;
+
+    let applications = dir.path().join("applications");
+    let mut expected = [entry(&games, &game), entry(&tools, &tool)];
+    expected.sort();
+    assert_eq!(files(&applications), expected);
+
+    let contents = std::fs::read_to_string(applications.join(entry(&games, &game))).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines[0], "[Desktop Entry]");
+    assert!(lines.contains(&"Type=Application"));
+    // The title stays on its line.
+    assert!(lines.contains(&"Name=Tower \"Defense\"\\n2"), "{contents}");
+    // Programs without an icon get the one of the application.
+    assert!(lines.contains(&"Icon=dev.edfloreshz.Bottles.Next"));
+    assert!(lines.contains(&"Categories=Game;"));
+    let exec = lines
+        .iter()
+        .find_map(|line| line.strip_prefix("Exec="))
+        .unwrap();
+    let link = format!("bottles-next://run/{}/{}", games.id, game.id);
+    assert!(exec.ends_with(&format!(" {link}")), "{exec}");
+
+    let contents = std::fs::read_to_string(applications.join(entry(&tools, &tool))).unwrap();
+    assert!(!contents.contains("Categories="));
+}
+
+#[tokio::test]
+async fn entries_go_with_their_program_or_bottle() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = setup(dir.path());
+    let games = store
+        .create(Bottle::new("Games", Environment::Gaming, "wine"))
+        .await
+        .unwrap();
+    let tools = store
+        .create(Bottle::new("Tools", Environment::Software, "wine"))
+        .await
+        .unwrap();
+    let game = program(&store, &games, "Game").await;
+    let editor = program(&store, &games, "Editor").await;
+    let tool = program(&store, &tools, "Tool").await;
+    // Icons are named after their entry.
+    let icons = dir.path().join("icons");
+    std::fs::create_dir_all(&icons).unwrap();
+    for (bottle, program) in [(&games, &game), (&games, &editor), (&tools, &tool)] {
+        let icon = entry(bottle, program).replace(".desktop", ".png");
+        std::fs::write(icons.join(icon), "").unwrap();
+    }
+
+    let applications = dir.path().join("applications");
+    store.remove_program(&games.id, &game.id).await.unwrap();
+    assert!(!files(&applications).contains(&entry(&games, &game)));
+    assert_eq!(files(&applications).len(), 2);
+    assert_eq!(files(&icons).len(), 2);
+
+    store.delete(&games.id).await.unwrap();
+    assert_eq!(files(&applications), [entry(&tools, &tool)]);
+    assert_eq!(
+        files(&icons),
+        [entry(&tools, &tool).replace(".desktop", ".png")]
+    );
+
+    // Removing entries that aren't there is fine.
+    store.desktop().remove(&tools.id, &game.id).await.unwrap();
+    store.desktop().remove_bottle(&games.id).await.unwrap();
+    assert_eq!(files(&applications), [entry(&tools, &tool)]);
+}
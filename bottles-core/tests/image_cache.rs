@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bottles_core::error::Error;
+use bottles_core::image_cache::{ImageCache, THUMBNAIL_HEIGHT};
+use image::{ImageFormat, Rgb, RgbImage};
+
+/// Writes a `width` by `height` cover filled with `color` to `path`.
+fn cover(path: &Path, width: u32, height: u32, color: [u8; 3]) -> PathBuf {
+    RgbImage::from_pixel(width, height, Rgb(color))
+        .save_with_format(path, ImageFormat::Png)
+        .unwrap();
+    path.to_path_buf()
+}
+
+fn thumbnails(dir: &Path) -> Vec<PathBuf> {
+    let mut thumbnails: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    thumbnails.sort();
+    thumbnails
+}
+
+#[tokio::test]
+async fn covers_are_scaled_down_to_cards() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ImageCache::new(dir.path().join("thumbnails"), u64::MAX);
+
+    let tall = cover(&dir.path().join("tall.png"), 400, 1200, [200, 0, 0]);
+    let thumbnail = image::open(cache.thumbnail(&tall).await.unwrap()).unwrap();
+    assert_eq!(
+        (thumbnail.width(), thumbnail.height()),
+        (100, THUMBNAIL_HEIGHT)
+    );
+
+    // Small covers are never scaled up.
+    let small = cover(&dir.path().join("small.png"), 60, 30, [0, 200, 0]);
+    let thumbnail = image::open(cache.thumbnail(&small).await.unwrap()).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (60, 30));
+}
+
+#[tokio::test]
+async fn identical_covers_are_cached_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("thumbnails");
+    let cache = ImageCache::new(&root, u64::MAX);
+    let original = cover(&dir.path().join("cover.png"), 40, 40, [0, 0, 200]);
+    let copy = dir.path().join("copy.png");
+    std::fs::copy(&original, &copy).unwrap();
+
+    let first = cache.thumbnail(&original).await.unwrap();
+    let second = cache.thumbnail(&copy).await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(thumbnails(&root), [first]);
+
+    // Thumbnails follow the contents of the cover, not its name.
+    cover(&original, 40, 40, [200, 200, 0]);
+    let changed = cache.thumbnail(&original).await.unwrap();
+    assert_ne!(changed, second);
+    assert_eq!(thumbnails(&root).len(), 2);
+}
+
+#[tokio::test]
+async fn unreadable_covers_are_not_cached() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("thumbnails");
+    let cache = ImageCache::new(&root, u64::MAX);
+    let broken = dir.path().join("broken.png");
+    std::fs::write(&broken, "not an image").unwrap();
+
+    assert!(matches!(
+        cache.thumbnail(&broken).await,
+        Err(Error::Image(_))
+    ));
+    assert!(cache
+        .thumbnail(&dir.path().join("missing.png"))
+        .await
+        .is_err());
+    assert!(!root.exists() || thumbnails(&root).is_empty());
+}
+
+#[tokio::test]
+async fn least_recently_used_thumbnails_are_evicted_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let covers = [[255, 0, 0], [0, 255, 0], [0, 0, 255]]
+        .into_iter()
+        .enumerate()
+        .map(|(index, color)| cover(&dir.path().join(format!("{index}.png")), 50, 50, color))
+        .collect::<Vec<_>>();
+
+    // Thumbnails are the same whatever the cache, so a cache without a budget
+    // tells how large they are.
+    let unbounded = ImageCache::new(dir.path().join("unbounded"), u64::MAX);
+    let mut size = 0;
+    for cover in &covers {
+        size += std::fs::metadata(unbounded.thumbnail(cover).await.unwrap())
+            .unwrap()
+            .len();
+    }
+
+    // Room for all three thumbnails but a byte.
+    let root = dir.path().join("thumbnails");
+    let cache = ImageCache::new(&root, size - 1);
+    let first = cache.thumbnail(&covers[0]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let second = cache.thumbnail(&covers[1]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    // Using the first thumbnail again makes the second one the oldest.
+    assert_eq!(cache.thumbnail(&covers[0]).await.unwrap(), first);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let third = cache.thumbnail(&covers[2]).await.unwrap();
+
+    assert!(!second.exists());
+    let mut kept = vec![first, third];
+    kept.sort();
+    assert_eq!(thumbnails(&root), kept);
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bottles_core::error::Error;
+use bottles_core::fetch::Mirror;
+use bottles_core::installer::{Installer, Recipe, Step, Target};
+use bottles_core::runner::fake::Invocation;
+use bottles_core::runner::{Fake, Runner};
+use bottles_core::store::BottleStore;
+use sha2::{Digest, Sha256};
+
+const URL: &str = "https://example.com/setup.exe";
+const SETUP: &[u8] = b"setup";
+
+/// An installer that puts the game in the prefix, once `first` let it through.
+fn fake(first: &str) -> Fake {
+    Fake::new(format!(
+        r#"case "$FAKE_EXECUTABLE" in winecfg|wineboot|reg) exit 0;; esac
+        {first}
+        mkdir -p "$WINEPREFIX/drive_c/Game" && touch "$WINEPREFIX/drive_c/Game/game.exe""#
+    ))
+}
+
+fn recipe() -> Recipe {
+    Recipe {
+        id: "game".to_string(),
+        name: "Game".to_string(),
+        description: String::new(),
+        environment: Default::default(),
+        steps: vec![
+            Step::Download {
+                url: URL.to_string(),
+                file: "setup.exe".to_string(),
+                sha256: Some(format!("{:x}", Sha256::digest(SETUP))),
+            },
+            Step::Run {
+                file: "setup.exe".to_string(),
+                arguments: vec!["/S".to_string()],
+            },
+            Step::Register {
+                title: "Game".to_string(),
+                executable: "C:\\Game\\game.exe".to_string(),
+                arguments: vec![],
+                cover: None,
+            },
+        ],
+    }
+}
+
+struct Setup {
+    store: BottleStore,
+    mirror: Mirror,
+    installer: Installer,
+    downloads: PathBuf,
+}
+
+fn setup(dir: &Path, fake: &Fake) -> Setup {
+    let store = BottleStore::new(dir.join("bottles"));
+    let mirror = Mirror::new(dir.join("mirror"));
+    let runners = [Arc::new(fake.clone()) as Arc<dyn Runner>]
+        .into_iter()
+        .collect();
+    let installer = Installer::new(store.clone(), runners, Arc::new(mirror.clone()))
+        .with_dir(&dir.join("cache"));
+    Setup {
+        store,
+        mirror,
+        installer,
+        downloads: dir.join("cache/downloads/game"),
+    }
+}
+
+fn publish(mirror: &Mirror, contents: &[u8]) {
+    let path = mirror.path(URL);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+fn target() -> Target {
+    Target::New {
+        name: "Game".to_string(),
+        runner: Fake::ID.to_string(),
+    }
+}
+
+async fn install(setup: &Setup) -> bottles_core::error::Result<String> {
+    let bottle = setup
+        .installer
+        .install(&recipe(), target(), &HashMap::new(), |_| {})
+        .await?;
+    Ok(bottle.id)
+}
+
+/// How many times the downloaded installer was started.
+fn runs(fake: &Fake) -> usize {
+    fake.invocations()
+        .iter()
+        .filter(|invocation| match invocation {
+            Invocation::Spawn { launch, .. } => launch.executable.ends_with("setup.exe"),
+            Invocation::Kill { .. } => false,
+        })
+        .count()
+}
+
+/// Checks that `bottle` is the only bottle and holds the game, and that nothing
+/// of the installation is left behind.
+async fn assert_installed(setup: &Setup, bottle: &str) {
+    let bottles = setup.store.load().await.unwrap();
+    assert_eq!(bottles.len(), 1);
+    assert_eq!(bottles[0].id, bottle);
+    let programs = setup.store.programs(bottle).await.unwrap();
+    assert_eq!(programs.len(), 1);
+    assert_eq!(programs[0].title, "Game");
+    assert_eq!(programs[0].executable, Path::new("C:\\Game\\game.exe"));
+    assert_eq!(setup.installer.unfinished(&recipe()).await, None);
+    assert!(!setup.downloads.exists());
+}
+
+#[tokio::test]
+async fn downloads_not_matching_their_checksum_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = fake("");
+    let setup = setup(dir.path(), &fake);
+    publish(&setup.mirror, b"tampered");
+
+    assert!(matches!(install(&setup).await, Err(Error::Checksum { .. })));
+    // Neither the file nor a part of it is kept, and nothing ran.
+    assert!(!setup.downloads.join("setup.exe").exists());
+    assert!(!setup.downloads.join("setup.part").exists());
+    assert_eq!(runs(&fake), 0);
+    let bottle = setup.installer.unfinished(&recipe()).await.unwrap();
+
+    publish(&setup.mirror, SETUP);
+    assert_eq!(install(&setup).await.unwrap(), bottle);
+    assert_installed(&setup, &bottle).await;
+}
+
+#[tokio::test]
+async fn failed_installations_resume_where_they_stopped() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = fake(r#"[ -e "$WINEPREFIX/../ran" ] || { touch "$WINEPREFIX/../ran"; exit 1; }"#);
+    let setup = setup(dir.path(), &fake);
+    publish(&setup.mirror, SETUP);
+
+    assert!(matches!(install(&setup).await, Err(Error::Installer(_))));
+    let bottle = setup.installer.unfinished(&recipe()).await.unwrap();
+    assert!(setup.downloads.join("setup.exe").is_file());
+
+    // The download went through, so it isn't fetched again.
+    std::fs::remove_file(setup.mirror.path(URL)).unwrap();
+    assert_eq!(install(&setup).await.unwrap(), bottle);
+    assert_installed(&setup, &bottle).await;
+    assert_eq!(runs(&fake), 2);
+}
+
+#[tokio::test]
+async fn cancelled_installations_resume_where_they_stopped() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake =
+        fake(r#"[ -e "$WINEPREFIX/../ran" ] || { touch "$WINEPREFIX/../ran"; exec sleep 30; }"#);
+    let setup = setup(dir.path(), &fake);
+    publish(&setup.mirror, SETUP);
+
+    // Dropping the installation while the installer runs cancels it.
+    tokio::select! {
+        result = install(&setup) => panic!("the installation wasn't cancelled: {result:?}"),
+        _ = async {
+            let bottles = dir.path().join("bottles");
+            loop {
+                let ran = std::fs::read_dir(&bottles)
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .any(|entry| entry.path().join("ran").exists());
+                if ran {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        } => {}
+    }
+    let bottle = setup.installer.unfinished(&recipe()).await.unwrap();
+    let prefix = setup.store.get(&bottle).await.unwrap().prefix();
+    fake.kill(&prefix).await.unwrap();
+
+    std::fs::remove_file(setup.mirror.path(URL)).unwrap();
+    assert_eq!(install(&setup).await.unwrap(), bottle);
+    assert_installed(&setup, &bottle).await;
+    assert_eq!(runs(&fake), 2);
+}
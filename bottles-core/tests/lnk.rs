@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use bottles_core::error::Error;
+use bottles_core::lnk::{self, Shortcut};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/lnk")
+        .join(name)
+}
+
+fn parse(name: &str) -> Shortcut {
+    Shortcut::parse(&std::fs::read(fixture(name)).unwrap()).unwrap()
+}
+
+#[test]
+fn local_targets_are_read_from_the_link_info() {
+    assert_eq!(
+        parse("local.lnk"),
+        Shortcut {
+            target: "C:\\Games\\Solitaire\\sol.exe".to_string(),
+            arguments: Some("/deal 7".to_string()),
+            working_dir: Some("C:\\Games\\Solitaire".to_string()),
+            icon: Some(("C:\\Games\\Solitaire\\cards.dll".to_string(), 2)),
+            description: Some("Solitaire".to_string()),
+        }
+    );
+}
+
+#[test]
+fn unicode_strings_are_preferred() {
+    // The ANSI copy of the path in the link info lost the characters the code
+    // page has no room for.
+    assert_eq!(
+        parse("unicode.lnk"),
+        Shortcut {
+            target: "C:\\Jeux\\Café\\ゲーム.exe".to_string(),
+            arguments: Some("--langue=français".to_string()),
+            working_dir: None,
+            icon: Some(("C:\\Jeux\\Café\\ゲーム.exe".to_string(), 0)),
+            description: Some("Café ゲーム".to_string()),
+        }
+    );
+}
+
+#[test]
+fn the_environment_block_wins_over_the_relative_path() {
+    let shortcut = parse("environment.lnk");
+    assert_eq!(shortcut.target, "C:\\Windows\\notepad.exe");
+    assert_eq!(shortcut.arguments, None);
+}
+
+#[test]
+fn relative_targets_are_resolved_against_the_shortcut() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = dir.path().join("drive_c");
+    let tools = drive.join("Tools");
+    std::fs::create_dir_all(tools.join("Bin")).unwrap();
+    std::fs::write(tools.join("Bin/Tool.exe"), "").unwrap();
+    std::fs::copy(fixture("relative.lnk"), tools.join("tool.lnk")).unwrap();
+
+    let shortcut = Shortcut::read(&tools.join("tool.lnk"), &drive).unwrap();
+    assert_eq!(shortcut.target, "C:\\Tools\\bin\\tool.exe");
+    assert_eq!(shortcut.working_dir.as_deref(), Some("."));
+    // Files are found whatever the case of their name.
+    assert_eq!(
+        lnk::unix_path(&drive, &shortcut.target),
+        Some(tools.join("Bin/Tool.exe"))
+    );
+
+    // Relative targets of shortcuts outside the prefix have nothing to resolve to.
+    std::fs::copy(fixture("relative.lnk"), dir.path().join("tool.lnk")).unwrap();
+    assert!(matches!(
+        Shortcut::read(&dir.path().join("tool.lnk"), &drive),
+        Err(Error::Shortcut(_))
+    ));
+}
+
+#[test]
+fn paths_stay_on_the_drive() {
+    let dir = tempfile::tempdir().unwrap();
+    let drive = dir.path().join("drive_c");
+    std::fs::create_dir_all(drive.join("windows")).unwrap();
+
+    assert_eq!(
+        lnk::unix_path(&drive, "c:/WINDOWS/./notepad.exe"),
+        Some(drive.join("windows/notepad.exe"))
+    );
+    for path in [
+        "C:\\..\\dosdevices\\z:\\etc\\passwd",
+        "C:\\windows\\..\\..\\user.reg",
+        "D:\\game.exe",
+        "\\\\server\\share\\game.exe",
+        "game.exe",
+    ] {
+        assert_eq!(lnk::unix_path(&drive, path), None, "{path}");
+    }
+}
+
+#[test]
+fn malformed_shortcuts_are_refused() {
+    for name in [
+        "local.lnk",
+        "unicode.lnk",
+        "relative.lnk",
+        "environment.lnk",
+    ] {
+        let data = std::fs::read(fixture(name)).unwrap();
+        let full = Shortcut::parse(&data).unwrap();
+        for len in 0..data.len() {
+            match Shortcut::parse(&data[..len]) {
+                // Extra data blocks are optional, so only cuts among them go
+                // unnoticed, leaving the target found before them.
+                Ok(shortcut) if shortcut == full => {}
+                Ok(shortcut) => {
+                    assert_eq!(name, "environment.lnk", "{len}: {shortcut:?}");
+                    assert_eq!(shortcut.target, "notepad.exe");
+                }
+                Err(Error::Shortcut(_)) => {}
+                Err(why) => panic!("{name} cut at {len}: {why}"),
+            }
+        }
+    }
+
+    let mut data = std::fs::read(fixture("local.lnk")).unwrap();
+    data[4] ^= 0xff;
+    assert!(matches!(Shortcut::parse(&data), Err(Error::Shortcut(_))));
+    assert!(matches!(Shortcut::parse(b""), Err(Error::Shortcut(_))));
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use bottles_core::error::Error;
+use bottles_core::pe;
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pe");
+    std::fs::read(path.join(name)).unwrap()
+}
+
+/// The `.ico` file [`pe::group_icon`] builds around a single image.
+fn ico(size: u8, bit_count: u16, image: &[u8]) -> Vec<u8> {
+    let mut ico = vec![0, 0, 1, 0, 1, 0, size, size, 0, 0, 1, 0];
+    ico.extend_from_slice(&bit_count.to_le_bytes());
+    ico.extend_from_slice(&(image.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+    ico.extend_from_slice(image);
+    ico
+}
+
+#[test]
+fn the_largest_image_of_the_first_group_is_picked() {
+    // The group also lists a 256 pixels image that was stripped from the file, and
+    // a second group holds one that isn't the icon of the executable.
+    assert_eq!(
+        pe::group_icon(&fixture("pe32.exe")).unwrap(),
+        ico(48, 32, b"icon 48x48 32 bits")
+    );
+    // Sizes of 256 pixels are written as 0.
+    assert_eq!(
+        pe::group_icon(&fixture("pe32plus.exe")).unwrap(),
+        ico(0, 32, b"icon 256x256 32 bits")
+    );
+}
+
+#[test]
+fn executables_without_icons_are_refused() {
+    for name in ["no-resources.exe", "truncated.exe"] {
+        assert!(
+            matches!(pe::group_icon(&fixture(name)), Err(Error::Icon(_))),
+            "{name}"
+        );
+    }
+    assert!(matches!(pe::group_icon(b""), Err(Error::Icon(_))));
+    assert!(matches!(pe::group_icon(b"MZ"), Err(Error::Icon(_))));
+}
+
+#[test]
+fn malformed_executables_never_panic() {
+    for name in ["pe32.exe", "pe32plus.exe"] {
+        let data = fixture(name);
+        let full = pe::group_icon(&data).unwrap();
+        for len in 0..data.len() {
+            // Cutting the padding after the image loses nothing.
+            match pe::group_icon(&data[..len]) {
+                Ok(ico) => assert_eq!(ico, full, "{name} cut at {len}"),
+                Err(Error::Icon(_)) => {}
+                Err(why) => panic!("{name} cut at {len}: {why}"),
+            }
+        }
+
+        // Flip bytes all over the headers and resources, the same way on every run.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..20_000 {
+            let mut data = data.clone();
+            for _ in 0..4 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let index = (state >> 32) as usize % data.len();
+                data[index] ^= state as u8 | 1;
+            }
+            let _ = pe::group_icon(&data);
+        }
+    }
+}
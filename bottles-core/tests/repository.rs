@@ -0,0 +1,200 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::error::Error;
+use bottles_core::fetch::Mirror;
+use bottles_core::program::Program;
+use bottles_core::runner::repository::{Index, Release, Repository};
+use bottles_core::runner::Runner;
+use bottles_core::store::BottleStore;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+
+const INDEX: &str = "https://example.com/runners.json";
+
+/// Publishes a gzipped tarball holding `files` at `url`, returning its SHA-256.
+fn publish(mirror: &Mirror, url: &str, files: &[(&str, &str)]) -> String {
+    let mut tar = tar::Builder::new(GzEncoder::new(vec![], Default::default()));
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        tar.append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+    let archive = tar.into_inner().unwrap().finish().unwrap();
+    let path = mirror.path(url);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, &archive).unwrap();
+    format!("{:x}", Sha256::digest(&archive))
+}
+
+/// Publishes a runner built like Proton, named `id` once installed.
+fn release(mirror: &Mirror, id: &str) -> Release {
+    let url = format!("https://example.com/{id}.tar.gz");
+    let folder = id.to_uppercase();
+    let sha256 = publish(
+        mirror,
+        &url,
+        &[
+            (&format!("{folder}/files/bin/wine"), "#!/bin/sh\n"),
+            (&format!("{folder}/version"), &format!("1700000000 {id}-1")),
+        ],
+    );
+    Release {
+        id: id.to_string(),
+        name: folder,
+        version: "1".to_string(),
+        url,
+        sha256,
+        size: None,
+    }
+}
+
+fn setup(dir: &Path, releases: &[Release]) -> Repository {
+    let mirror = Mirror::new(dir.join("mirror"));
+    let index = Index {
+        releases: releases.to_vec(),
+    };
+    let path = mirror.path(INDEX);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, serde_json::to_string(&index).unwrap()).unwrap();
+    Repository::new(INDEX, Arc::new(mirror)).with_dir(dir)
+}
+
+/// The entries of `dir`, sorted.
+fn entries(dir: &Path) -> Vec<String> {
+    let mut entries: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[tokio::test]
+async fn releases_are_downloaded_verified_and_unpacked() {
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = Mirror::new(dir.path().join("mirror"));
+    let release = release(&mirror, "proton-9");
+    let repository = setup(dir.path(), std::slice::from_ref(&release));
+
+    assert_eq!(
+        repository.releases().await.unwrap(),
+        std::slice::from_ref(&release)
+    );
+
+    let reported = Mutex::new(vec![]);
+    repository
+        .install(&release, |done, _| reported.lock().unwrap().push(done))
+        .await
+        .unwrap();
+    let reported = reported.into_inner().unwrap();
+    assert!(reported.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(reported.last(), Some(&1.0));
+
+    // The folder of the archive is renamed after the release, and nothing else
+    // is left behind.
+    assert_eq!(entries(&dir.path().join("runners")), ["proton-9"]);
+    assert!(dir.path().join("runners/proton-9/files/bin/wine").is_file());
+    assert!(entries(&dir.path().join("downloads")).is_empty());
+    let installed = repository.installed().await;
+    assert_eq!(installed.len(), 1);
+    assert_eq!(installed[0].id(), "proton-9");
+    assert_eq!(installed[0].version(), Some("proton-9-1"));
+
+    assert!(matches!(
+        repository.install(&release, |_, _| {}).await,
+        Err(Error::Runner(_))
+    ));
+}
+
+#[tokio::test]
+async fn broken_releases_leave_nothing_behind() {
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = Mirror::new(dir.path().join("mirror"));
+    let mut tampered = release(&mirror, "proton-9");
+    tampered.sha256 = format!("{:x}", Sha256::digest("something else"));
+    let mut empty = release(&mirror, "empty");
+    empty.sha256 = publish(&mirror, &empty.url, &[("EMPTY/readme.txt", "nothing")]);
+    let repository = setup(dir.path(), &[tampered.clone(), empty.clone()]);
+
+    assert!(matches!(
+        repository.install(&tampered, |_, _| {}).await,
+        Err(Error::Checksum { .. })
+    ));
+    assert!(matches!(
+        repository.install(&empty, |_, _| {}).await,
+        Err(Error::Runner(_))
+    ));
+    assert!(entries(&dir.path().join("runners")).is_empty());
+    assert!(repository.installed().await.is_empty());
+}
+
+#[tokio::test]
+async fn archives_must_be_named_by_their_url() {
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = Mirror::new(dir.path().join("mirror"));
+    let mut release = release(&mirror, "proton-9");
+    let repository = setup(dir.path(), &[]);
+
+    for url in [
+        "https://example.com/",
+        "https://example.com/runners/.",
+        "https://example.com/runners/..",
+        "https://example.com/runners/..?download=1",
+    ] {
+        release.url = url.to_string();
+        assert!(
+            matches!(
+                repository.install(&release, |_, _| {}).await,
+                Err(Error::Download(_))
+            ),
+            "{url}"
+        );
+    }
+    // Nothing was downloaded, let alone in place of the downloads.
+    assert_eq!(entries(dir.path()), ["mirror"]);
+}
+
+#[tokio::test]
+async fn runners_in_use_are_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = Mirror::new(dir.path().join("mirror"));
+    let releases = [
+        release(&mirror, "bottle"),
+        release(&mirror, "program"),
+        release(&mirror, "unused"),
+    ];
+    let repository = setup(dir.path(), &releases);
+    for release in &releases {
+        repository.install(release, |_, _| {}).await.unwrap();
+    }
+
+    let store = BottleStore::new(dir.path().join("bottles"));
+    store
+        .create(Bottle::new("Games", Environment::Gaming, "bottle"))
+        .await
+        .unwrap();
+    let tools = store
+        .create(Bottle::new("Tools", Environment::Software, "wine"))
+        .await
+        .unwrap();
+    let mut program = Program::new(&tools.id, "Tool", "C:\\tool.exe".into());
+    program.overrides.runner = Some("program".to_string());
+    store.save_program(program).await.unwrap();
+
+    for (id, user) in [("bottle", "Games"), ("program", "Tools")] {
+        match repository.remove(&store, id).await {
+            Err(Error::Runner(why)) => assert!(why.contains(user), "{why}"),
+            result => panic!("{id} was removed: {result:?}"),
+        }
+    }
+    assert!(repository.remove(&store, "../bottles").await.is_err());
+    assert!(dir.path().join("bottles").is_dir());
+
+    assert_eq!(repository.prune(&store).await.unwrap(), ["unused"]);
+    assert_eq!(entries(&dir.path().join("runners")), ["bottle", "program"]);
+}
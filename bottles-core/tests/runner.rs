@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::error::Error;
+use bottles_core::program::Program;
+use bottles_core::runner::{Fake, Launch, Runner, Runners};
+
+#[test]
+fn programs_use_the_runner_they_override() {
+    let runners: Runners = [Arc::new(Fake::new("true")) as Arc<dyn Runner>]
+        .into_iter()
+        .collect();
+    let bottle = Bottle::new("Games", Environment::Gaming, "wine");
+    let mut program = Program::new(&bottle.id, "Game", PathBuf::from("C:\\game.exe"));
+
+    assert!(matches!(
+        runners.for_program(&bottle, &program),
+        Err(Error::RunnerNotFound(id)) if id == "wine"
+    ));
+
+    program.overrides.runner = Some(Fake::ID.to_string());
+    let runner = runners.for_program(&bottle, &program).unwrap();
+    assert_eq!(runner.id(), Fake::ID);
+
+    let bottle = Bottle::new("Games", Environment::Gaming, Fake::ID);
+    program.overrides.runner = Some("proton".to_string());
+    assert!(matches!(
+        runners.for_program(&bottle, &program),
+        Err(Error::RunnerNotFound(id)) if id == "proton"
+    ));
+    program.overrides.runner = None;
+    let runner = runners.for_program(&bottle, &program).unwrap();
+    assert_eq!(runner.id(), Fake::ID);
+}
+
+fn env<'a>(launch: &'a Launch, key: &str) -> Vec<&'a str> {
+    launch
+        .env
+        .iter()
+        .filter(|(k, _)| k == key)
+        .map(|(_, value)| value.as_str())
+        .collect()
+}
+
+#[test]
+fn launches_layer_the_program_the_bottle_and_the_overrides() {
+    let mut bottle = Bottle::new("Games", Environment::Custom, Fake::ID);
+    let mut program = Program::new(&bottle.id, "Game", PathBuf::from("C:\\game.exe"));
+    program.arguments = vec!["-windowed".to_string()];
+    program.working_dir = Some(PathBuf::from("/games"));
+
+    // The program alone.
+    let launch = Launch::new(&bottle, &program).unwrap();
+    assert_eq!(launch.executable, PathBuf::from("C:\\game.exe"));
+    assert_eq!(launch.arguments, ["-windowed"]);
+    assert_eq!(launch.working_dir, Some(PathBuf::from("/games")));
+    assert_eq!(
+        env(&launch, "WINEDLLOVERRIDES"),
+        ["d3d9,d3d10core,d3d11,dxgi=b;d3d12,d3d12core=b"]
+    );
+    assert!(launch.wrappers.is_empty());
+
+    // Then the settings of the bottle.
+    bottle.settings.dxvk = true;
+    bottle.settings.fsync = true;
+    bottle.settings.gamemode = true;
+    bottle.settings.mangohud = true;
+    bottle.settings.env = vec![
+        ("WINEFSYNC".to_string(), "0".to_string()),
+        ("LANG".to_string(), "fr_FR.UTF-8".to_string()),
+    ];
+    let launch = Launch::new(&bottle, &program).unwrap();
+    assert_eq!(
+        env(&launch, "WINEDLLOVERRIDES"),
+        ["d3d9,d3d10core,d3d11,dxgi=n,b;d3d12,d3d12core=b"]
+    );
+    assert!(env(&launch, "WINEESYNC").is_empty());
+    // Variables of the bottle come after the ones its settings set.
+    assert_eq!(env(&launch, "WINEFSYNC"), ["1", "0"]);
+    assert_eq!(launch.wrappers, ["gamemoderun", "mangohud"]);
+
+    // And finally the overrides of the program.
+    program.overrides.arguments = vec!["-skipintro".to_string()];
+    program.overrides.working_dir = Some(PathBuf::from("/saves"));
+    program.overrides.env = vec![("LANG".to_string(), "ja_JP.UTF-8".to_string())];
+    let launch = Launch::new(&bottle, &program).unwrap();
+    assert_eq!(launch.arguments, ["-windowed", "-skipintro"]);
+    assert_eq!(launch.working_dir, Some(PathBuf::from("/saves")));
+    assert_eq!(env(&launch, "LANG"), ["fr_FR.UTF-8", "ja_JP.UTF-8"]);
+    assert_eq!(launch.env.last().unwrap().1, "ja_JP.UTF-8");
+    assert_eq!(launch.wrappers, ["gamemoderun", "mangohud"]);
+}
+
+#[test]
+fn terminals_wrap_everything_else() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bottle = Bottle::new("Games", Environment::Custom, Fake::ID);
+    bottle.settings.gamemode = true;
+    let mut program = Program::new(&bottle.id, "Game", PathBuf::from("C:\\game.exe"));
+    program.overrides.terminal = true;
+
+    // No other test of this file looks at PATH.
+    std::env::set_var("PATH", dir.path());
+    assert!(matches!(
+        Launch::new(&bottle, &program),
+        Err(Error::TerminalNotFound)
+    ));
+
+    let xterm = dir.path().join("xterm");
+    std::fs::write(&xterm, "").unwrap();
+    let launch = Launch::new(&bottle, &program).unwrap();
+    let xterm = xterm.display().to_string();
+    assert_eq!(
+        launch.wrappers,
+        [xterm.as_str(), "-hold", "-e", "gamemoderun"]
+    );
+}
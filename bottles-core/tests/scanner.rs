@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+use bottles_core::error::Error;
+use bottles_core::scanner::{self, Cancel, Discovered};
+
+fn prefix() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/scanner")
+}
+
+#[tokio::test]
+async fn programs_are_found_once_under_their_best_name() {
+    let drive = prefix().join("drive_c");
+    let found = scanner::scan(&prefix(), Cancel::default()).await.unwrap();
+
+    // Broken shortcuts and shortcuts to missing files are skipped.
+    assert_eq!(
+        found,
+        [
+            Discovered {
+                title: "Editor".to_string(),
+                executable: drive.join("Program Files (x86)/Editor/bin/x64/Editor.exe"),
+                arguments: vec![],
+                working_dir: None,
+                location: "C:\\Program Files (x86)\\Editor\\bin\\x64\\Editor.exe".to_string(),
+            },
+            Discovered {
+                title: "Notes".to_string(),
+                executable: drive.join("users/player/Desktop/Notes.exe"),
+                arguments: vec![],
+                working_dir: None,
+                location: "C:\\users\\player\\Desktop\\Notes.exe".to_string(),
+            },
+            Discovered {
+                title: "Solitaire".to_string(),
+                executable: drive.join("Games/Solitaire/sol.exe"),
+                arguments: vec!["/deal".to_string(), "7".to_string()],
+                working_dir: None,
+                location: "C:\\Games\\Solitaire\\sol.exe".to_string(),
+            },
+            // Found from the Start Menu, the desktop and Program Files, and named
+            // after the shortcuts.
+            Discovered {
+                title: "Tower Defense".to_string(),
+                executable: drive.join("Program Files/Tower/TD.exe"),
+                arguments: vec!["-windowed".to_string(), "no intro".to_string()],
+                working_dir: Some(drive.join("Program Files/Tower")),
+                location: "C:\\PROGRAM FILES\\tower\\td.exe".to_string(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn helpers_are_left_out() {
+    let found = scanner::scan(&prefix(), Cancel::default()).await.unwrap();
+    let names: Vec<&str> = found
+        .iter()
+        .filter_map(|discovered| discovered.executable.file_name()?.to_str())
+        .collect();
+
+    // Uninstallers, installers and crash reporters, whether reached from a
+    // shortcut or not, along with redistributables, shared components and
+    // whatever is buried too deep.
+    for ignored in [
+        "unins000.exe",
+        "setup.exe",
+        "CrashReporter.exe",
+        "vcredist_x64.exe",
+        "Helper.exe",
+        "helper.exe",
+        "deep.exe",
+    ] {
+        assert!(!names.contains(&ignored), "{ignored} in {names:?}");
+    }
+}
+
+#[tokio::test]
+async fn cancelled_scans_stop() {
+    let cancel = Cancel::default();
+    let clone = cancel.clone();
+    clone.cancel();
+    assert!(cancel.is_cancelled());
+    assert!(matches!(
+        scanner::scan(&prefix(), cancel).await,
+        Err(Error::Cancelled)
+    ));
+}
+
+#[tokio::test]
+async fn empty_prefixes_have_nothing_to_offer() {
+    let dir = tempfile::tempdir().unwrap();
+    let found = scanner::scan(dir.path(), Cancel::default()).await.unwrap();
+    assert!(found.is_empty());
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::error::Error;
+use bottles_core::snapshot::{Snapshots, SNAPSHOTS};
+
+fn fill(prefix: &Path, files: usize, contents: &str) {
+    let dir = prefix.join("drive_c");
+    std::fs::create_dir_all(&dir).unwrap();
+    for index in 0..files {
+        std::fs::write(
+            dir.join(format!("{index}.txt")),
+            format!("{contents} {index}"),
+        )
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn deleting_waits_for_snapshots_being_created() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bottle = Bottle::new("Snapshots", Environment::Software, "wine");
+    bottle.path = dir.path().to_path_buf();
+    let prefix = bottle.prefix();
+    let snapshots = Snapshots::new(&bottle);
+
+    fill(&prefix, 10, "old");
+    let old = snapshots.create(&prefix, "Old", |_| {}).await.unwrap();
+
+    // Only the new snapshot refers to these files once the old one is deleted.
+    fill(&prefix, 500, "new");
+    let (new, deleted) = tokio::join!(
+        snapshots.create(&prefix, "New", |_| {}),
+        snapshots.delete(&old.id),
+    );
+    let new = new.unwrap();
+    deleted.unwrap();
+    assert_eq!(snapshots.list().await.unwrap(), vec![new.clone()]);
+
+    std::fs::remove_dir_all(&prefix).unwrap();
+    snapshots.restore(&new.id, &prefix, |_| {}).await.unwrap();
+    let restored = std::fs::read_to_string(prefix.join("drive_c").join("499.txt")).unwrap();
+    assert_eq!(restored, "new 499");
+}
+
+#[tokio::test]
+async fn ids_outside_the_snapshots_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bottle = Bottle::new("Snapshots", Environment::Software, "wine");
+    bottle.path = dir.path().join("bottle");
+    let prefix = bottle.prefix();
+    let snapshots = Snapshots::new(&bottle);
+    fill(&prefix, 1, "kept");
+    let snapshot = snapshots.create(&prefix, "Kept", |_| {}).await.unwrap();
+
+    // Manifests are named after the id, so these would name files next to them.
+    std::fs::write(dir.path().join("outside.ron"), "not a snapshot").unwrap();
+    for id in ["../../outside", "../bottle", "", "not-a-uuid"] {
+        assert!(
+            matches!(snapshots.delete(id).await, Err(Error::Snapshot(_))),
+            "{id}"
+        );
+        assert!(
+            matches!(
+                snapshots.restore(id, &prefix, |_| {}).await,
+                Err(Error::Snapshot(_))
+            ),
+            "{id}"
+        );
+    }
+    assert!(dir.path().join("outside.ron").exists());
+    assert!(bottle
+        .path
+        .join(SNAPSHOTS)
+        .join(format!("{}.ron", snapshot.id))
+        .exists());
+    assert_eq!(snapshots.list().await.unwrap(), vec![snapshot]);
+}
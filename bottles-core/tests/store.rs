@@ -0,0 +1,233 @@
+use std::path::PathBuf;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::error::Error;
+use bottles_core::program::{Activity, Program};
+use bottles_core::store::{self, BottleStore};
+use chrono::{TimeDelta, Utc};
+
+fn ids(programs: &[Program]) -> Vec<&str> {
+    programs.iter().map(|program| program.id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn ids_outside_the_store_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("bottles");
+    let store = BottleStore::new(&root);
+    let outside = dir.path().join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(outside.join(store::MANIFEST), "not a bottle").unwrap();
+
+    for id in ["../outside", "..", "/tmp", "", "not-a-uuid"] {
+        assert!(
+            matches!(store.get(id).await, Err(Error::BottleNotFound(_))),
+            "{id}"
+        );
+        assert!(
+            matches!(store.delete(id).await, Err(Error::BottleNotFound(_))),
+            "{id}"
+        );
+        assert!(store.disk_usage(id).await.is_err(), "{id}");
+    }
+    assert!(outside.join(store::MANIFEST).exists());
+
+    let mut bottle = Bottle::new("Escape", Environment::Software, "wine");
+    bottle.id = "../outside".to_string();
+    assert!(store.create(bottle).await.is_err());
+}
+
+#[tokio::test]
+async fn manifests_are_matched_with_their_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = BottleStore::new(dir.path());
+    let bottle = store
+        .create(Bottle::new("Copied", Environment::Gaming, "wine"))
+        .await
+        .unwrap();
+
+    // A copy of the folder keeps the id of the original in its manifest.
+    let copy = Bottle::new("", Environment::Gaming, "wine").id;
+    let copy_dir = dir.path().join(&copy);
+    std::fs::create_dir_all(&copy_dir).unwrap();
+    std::fs::copy(
+        bottle.path.join(store::MANIFEST),
+        copy_dir.join(store::MANIFEST),
+    )
+    .unwrap();
+    // Folders not named after an id are skipped.
+    let stray = dir.path().join("stray");
+    std::fs::create_dir_all(&stray).unwrap();
+    std::fs::copy(
+        bottle.path.join(store::MANIFEST),
+        stray.join(store::MANIFEST),
+    )
+    .unwrap();
+
+    let mut ids: Vec<String> = store
+        .load()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|bottle| bottle.id)
+        .collect();
+    ids.sort();
+    let mut expected = vec![bottle.id.clone(), copy.clone()];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let copied = store.get(&copy).await.unwrap();
+    assert_eq!(copied.id, copy);
+    assert_eq!(copied.path, copy_dir);
+}
+
+#[tokio::test]
+async fn bottles_are_created_renamed_and_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = BottleStore::new(dir.path());
+    let bottle = store
+        .create(Bottle::new("Games", Environment::Gaming, "wine"))
+        .await
+        .unwrap();
+    assert_eq!(bottle.path, dir.path().join(&bottle.id));
+    assert!(bottle.prefix().is_dir());
+    assert!(bottle.path.join(store::MANIFEST).is_file());
+
+    let renamed = store.rename(&bottle.id, "Old games").await.unwrap();
+    assert_eq!(renamed.name, "Old games");
+    assert_eq!(renamed.settings, bottle.settings);
+    let loaded = store.load().await.unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].id, bottle.id);
+    assert_eq!(loaded[0].name, "Old games");
+    assert_eq!(store.get(&bottle.id).await.unwrap().name, "Old games");
+
+    store.delete(&bottle.id).await.unwrap();
+    assert!(!bottle.path.exists());
+    assert!(matches!(
+        store.get(&bottle.id).await,
+        Err(Error::BottleNotFound(_))
+    ));
+    assert!(store.load().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn programs_are_saved_and_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = BottleStore::new(dir.path());
+    let games = store
+        .create(Bottle::new("Games", Environment::Gaming, "wine"))
+        .await
+        .unwrap();
+    let tools = store
+        .create(Bottle::new("Tools", Environment::Software, "wine"))
+        .await
+        .unwrap();
+
+    let mut game = store
+        .save_program(Program::new(
+            &games.id,
+            "Game",
+            PathBuf::from("C:\\game.exe"),
+        ))
+        .await
+        .unwrap();
+    let editor = store
+        .save_program(Program::new(
+            &games.id,
+            "Editor",
+            PathBuf::from("C:\\editor.exe"),
+        ))
+        .await
+        .unwrap();
+    let tool = store
+        .save_program(Program::new(
+            &tools.id,
+            "Tool",
+            PathBuf::from("C:\\tool.exe"),
+        ))
+        .await
+        .unwrap();
+
+    // Saving a program again replaces it in place.
+    game.title = "Game of the year".to_string();
+    game.arguments = vec!["-windowed".to_string()];
+    store.save_program(game.clone()).await.unwrap();
+    let programs = store.programs(&games.id).await.unwrap();
+    assert_eq!(ids(&programs), [game.id.as_str(), editor.id.as_str()]);
+    assert_eq!(programs[0].title, "Game of the year");
+    assert_eq!(programs[0].arguments, ["-windowed"]);
+    assert_eq!(programs[0].bottle, games.id);
+
+    let library = store.library(&store.load().await.unwrap()).await.unwrap();
+    let mut titles: Vec<&str> = library
+        .iter()
+        .map(|program| program.title.as_str())
+        .collect();
+    titles.sort();
+    assert_eq!(titles, ["Editor", "Game of the year", "Tool"]);
+
+    store.remove_program(&games.id, &editor.id).await.unwrap();
+    // Removing what isn't there changes nothing.
+    store.remove_program(&games.id, &tool.id).await.unwrap();
+    assert_eq!(ids(&store.programs(&games.id).await.unwrap()), [game.id]);
+    assert_eq!(ids(&store.programs(&tools.id).await.unwrap()), [tool.id]);
+}
+
+#[tokio::test]
+async fn activity_is_recorded_per_program() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = BottleStore::new(dir.path());
+    let bottle = store
+        .create(Bottle::new("Games", Environment::Gaming, "wine"))
+        .await
+        .unwrap();
+    let program = store
+        .save_program(Program::new(
+            &bottle.id,
+            "Game",
+            PathBuf::from("C:\\game.exe"),
+        ))
+        .await
+        .unwrap();
+    let other = store
+        .save_program(Program::new(
+            &bottle.id,
+            "Other",
+            PathBuf::from("C:\\other.exe"),
+        ))
+        .await
+        .unwrap();
+
+    let started = Utc::now();
+    let activity = store
+        .record_start(&bottle.id, &program.id, started)
+        .await
+        .unwrap();
+    assert_eq!(activity.last_started, Some(started));
+    assert_eq!(activity.last_stopped, None);
+
+    let stopped = started + TimeDelta::seconds(90);
+    store
+        .record_stop(&bottle.id, &program.id, started, stopped)
+        .await
+        .unwrap();
+    // Play time adds up, and clocks going backwards never take any away.
+    let activity = store
+        .record_stop(&bottle.id, &program.id, stopped, started)
+        .await
+        .unwrap();
+    assert_eq!(activity.play_time, 90);
+    assert_eq!(activity.last_stopped, Some(started));
+
+    let programs = store.programs(&bottle.id).await.unwrap();
+    assert_eq!(programs[0].activity, activity);
+    assert_eq!(programs[1].activity, Activity::default());
+
+    // The statistics of a removed program go with it.
+    store.remove_program(&bottle.id, &program.id).await.unwrap();
+    store.save_program(program.clone()).await.unwrap();
+    let programs = store.programs(&bottle.id).await.unwrap();
+    assert_eq!(ids(&programs), [other.id.as_str(), program.id.as_str()]);
+    assert_eq!(programs[1].activity, Activity::default());
+}
@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::program::Program;
+use bottles_core::runner::fake::Invocation;
+use bottles_core::runner::Fake;
+use bottles_core::store::BottleStore;
+use bottles_core::supervisor::{Event, Supervisor};
+use futures_util::{Stream, StreamExt};
+
+/// Writes where and with what the program was started to `$OUTPUT`.
+const REPORT: &str = r#"printf '%s\n' "$PWD" "$FROM" "$FAKE_EXECUTABLE" > "$OUTPUT"; exit 3"#;
+
+/// A runner running `script` for programs, and succeeding right away for the tools
+/// of Wine.
+fn fake(script: &str) -> Fake {
+    Fake::new(format!(
+        r#"case "$FAKE_EXECUTABLE" in winecfg|wineboot|reg) exit 0;; esac; {script}"#
+    ))
+}
+
+async fn setup(
+    dir: &Path,
+    configure: impl FnOnce(&mut Bottle, &mut Program),
+) -> (BottleStore, Bottle, Program) {
+    let store = BottleStore::new(dir.join("bottles"));
+    let mut bottle = Bottle::new("Games", Environment::Custom, Fake::ID);
+    let mut program = Program::new(&bottle.id, "Game", PathBuf::from("C:\\game.exe"));
+    configure(&mut bottle, &mut program);
+    let bottle = store.create(bottle).await.unwrap();
+    program.bottle = bottle.id.clone();
+    let program = store.save_program(program).await.unwrap();
+    (store, bottle, program)
+}
+
+/// The next event other than [`Event::Activity`].
+async fn next_state(events: &mut (impl Stream<Item = Event> + Unpin)) -> Event {
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .expect("no event in time")
+            .expect("events ended");
+        if !matches!(event, Event::Activity { .. }) {
+            return event;
+        }
+    }
+}
+
+/// Whether `pid` is alive, zombies aside.
+fn is_running(pid: u32) -> bool {
+    std::fs::read_to_string(format!("/proc/{pid}/stat"))
+        .ok()
+        .and_then(|stat| {
+            let state = stat.rsplit_once(") ")?.1.chars().next()?;
+            Some(state != 'Z' && state != 'X')
+        })
+        .unwrap_or(false)
+}
+
+#[tokio::test]
+async fn launches_are_recorded_and_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("output");
+    let (store, bottle, program) = setup(dir.path(), |bottle, program| {
+        bottle.settings.esync = true;
+        bottle.settings.gamemode = true;
+        bottle.settings.env = vec![("FROM".to_string(), "bottle".to_string())];
+        program.arguments = vec!["-windowed".to_string()];
+        program.working_dir = Some(PathBuf::from("/nonexistent"));
+        program.overrides.arguments = vec!["-skipintro".to_string()];
+        program.overrides.working_dir = Some(dir.path().to_path_buf());
+        program.overrides.env = vec![
+            ("FROM".to_string(), "program".to_string()),
+            ("OUTPUT".to_string(), output.display().to_string()),
+        ];
+    })
+    .await;
+
+    let fake = fake(REPORT);
+    let supervisor = Supervisor::new(store.clone());
+    let mut events = Box::pin(supervisor.events());
+    let pid = supervisor.launch(&fake, &bottle, &program).await.unwrap();
+
+    assert_eq!(
+        next_state(&mut events).await,
+        Event::Started {
+            program: program.id.clone(),
+            pid
+        }
+    );
+    assert_eq!(
+        next_state(&mut events).await,
+        Event::Exited {
+            program: program.id.clone(),
+            code: 3
+        }
+    );
+
+    // The Windows version of the bottle is applied before the program starts.
+    let invocations = fake.invocations();
+    let [Invocation::Spawn {
+        launch: winecfg, ..
+    }, Invocation::Spawn { prefix, launch }] = invocations.as_slice()
+    else {
+        panic!("expected winecfg and the program: {invocations:?}");
+    };
+    assert_eq!(winecfg.executable, Path::new("winecfg"));
+    assert_eq!(winecfg.arguments, ["-v", "win10"]);
+    assert_eq!(prefix, &bottle.prefix());
+    assert_eq!(launch.executable, PathBuf::from("C:\\game.exe"));
+    assert_eq!(launch.arguments, ["-windowed", "-skipintro"]);
+    assert_eq!(launch.working_dir.as_deref(), Some(dir.path()));
+    assert_eq!(launch.wrappers, ["gamemoderun"]);
+    let env = |key: &str| -> Vec<&str> {
+        launch
+            .env
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    };
+    assert_eq!(env("WINEESYNC"), ["1"]);
+    assert_eq!(env("FROM"), ["bottle", "program"]);
+    assert_eq!(env("WINEDLLOVERRIDES").len(), 1);
+
+    let output = std::fs::read_to_string(&output).unwrap();
+    let expected = format!("{}\nprogram\nC:\\game.exe\n", dir.path().display());
+    assert_eq!(output, expected);
+
+    // The activity is saved once the program is gone.
+    let activity = loop {
+        let programs = store.programs(&bottle.id).await.unwrap();
+        let activity = programs[0].activity;
+        if activity.last_stopped.is_some() {
+            break activity;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert!(activity.last_started <= activity.last_stopped);
+    assert!(supervisor.running().await.is_empty());
+}
+
+#[tokio::test]
+async fn stopped_programs_are_killed() {
+    let dir = tempfile::tempdir().unwrap();
+    let (store, bottle, program) = setup(dir.path(), |_, _| {}).await;
+    let fake = fake("exec sleep 30");
+    let supervisor = Supervisor::new(store);
+    let mut events = Box::pin(supervisor.events());
+
+    let pid = supervisor.launch(&fake, &bottle, &program).await.unwrap();
+    assert!(matches!(
+        next_state(&mut events).await,
+        Event::Started { .. }
+    ));
+    assert!(is_running(pid));
+    assert!(matches!(
+        supervisor.launch(&fake, &bottle, &program).await,
+        Err(bottles_core::error::Error::AlreadyRunning(_))
+    ));
+
+    supervisor.stop(&program.id).await.unwrap();
+    assert_eq!(
+        next_state(&mut events).await,
+        Event::Stopped {
+            program: program.id.clone()
+        }
+    );
+    assert!(!is_running(pid));
+    assert!(supervisor.running().await.is_empty());
+    assert!(!fake
+        .invocations()
+        .iter()
+        .any(|invocation| matches!(invocation, Invocation::Kill { .. })));
+}
+
+#[tokio::test]
+async fn stopping_a_bottle_kills_it_through_the_runner() {
+    let dir = tempfile::tempdir().unwrap();
+    let (store, bottle, program) = setup(dir.path(), |_, _| {}).await;
+    let fake = fake("exec sleep 30");
+    let supervisor = Supervisor::new(store);
+    let mut events = Box::pin(supervisor.events());
+
+    let pid = supervisor.launch(&fake, &bottle, &program).await.unwrap();
+    assert!(matches!(
+        next_state(&mut events).await,
+        Event::Started { .. }
+    ));
+
+    supervisor.stop_bottle(&fake, &bottle).await.unwrap();
+    assert_eq!(
+        next_state(&mut events).await,
+        Event::Stopped {
+            program: program.id.clone()
+        }
+    );
+    assert!(!is_running(pid));
+    assert!(fake.invocations().contains(&Invocation::Kill {
+        prefix: bottle.prefix()
+    }));
+
+    // Nothing is left to stop, and the program can be launched again without
+    // setting the Windows version another time.
+    supervisor.stop(&program.id).await.unwrap();
+    supervisor.launch(&fake, &bottle, &program).await.unwrap();
+    let winecfg = fake
+        .invocations()
+        .into_iter()
+        .filter(|invocation| match invocation {
+            Invocation::Spawn { launch, .. } => launch.executable == Path::new("winecfg"),
+            Invocation::Kill { .. } => false,
+        })
+        .count();
+    assert_eq!(winecfg, 1);
+    assert!(matches!(
+        next_state(&mut events).await,
+        Event::Started { .. }
+    ));
+    supervisor.stop(&program.id).await.unwrap();
+    assert!(matches!(
+        next_state(&mut events).await,
+        Event::Stopped { .. }
+    ));
+}
+
+#[tokio::test]
+async fn stopping_a_program_stops_what_it_started() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("output");
+    let (store, bottle, program) = setup(dir.path(), |_, program| {
+        program.overrides.env = vec![("OUTPUT".to_string(), output.display().to_string())];
+    })
+    .await;
+    // Like a wrapper, the shell keeps running next to the program it started.
+    let fake = fake(r#"sleep 30 & echo $! > "$OUTPUT"; wait"#);
+    let supervisor = Supervisor::new(store);
+    let mut events = Box::pin(supervisor.events());
+
+    let pid = supervisor.launch(&fake, &bottle, &program).await.unwrap();
+    assert!(matches!(
+        next_state(&mut events).await,
+        Event::Started { .. }
+    ));
+    let started = loop {
+        let written = std::fs::read_to_string(&output).unwrap_or_default();
+        if let Ok(started) = written.trim().parse::<u32>() {
+            break started;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert!(is_running(started));
+
+    supervisor.stop(&program.id).await.unwrap();
+    assert!(matches!(
+        next_state(&mut events).await,
+        Event::Stopped { .. }
+    ));
+    assert!(!is_running(pid));
+    for _ in 0..50 {
+        if !is_running(started) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{started} is still running");
+}
@@ -0,0 +1,51 @@
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::error::Error;
+use bottles_core::uri::Uri;
+
+#[test]
+fn links_round_trip() {
+    let bottle = Bottle::new("Games", Environment::Gaming, "wine").id;
+    let links = [
+        Uri::Run {
+            bottle: bottle.clone(),
+            program: "game".to_string(),
+        },
+        Uri::Open {
+            bottle: bottle.clone(),
+            program: Some("game".to_string()),
+        },
+        Uri::Open {
+            bottle: bottle.clone(),
+            program: None,
+        },
+    ];
+    for link in links {
+        assert_eq!(link.to_string().parse::<Uri>().unwrap(), link);
+    }
+    assert_eq!(
+        format!("bottles-next://open/{bottle}/")
+            .parse::<Uri>()
+            .unwrap(),
+        Uri::Open {
+            bottle,
+            program: None
+        }
+    );
+}
+
+#[test]
+fn links_to_anything_but_a_bottle_are_refused() {
+    let bottle = Bottle::new("Games", Environment::Gaming, "wine").id;
+    for link in [
+        "bottles-next://run/../game".to_string(),
+        "bottles-next://run/..%2F..%2Fhome/game".to_string(),
+        "bottles-next://open/games".to_string(),
+        format!("bottles-next://run/{}/game", bottle.to_uppercase()),
+        format!("bottles-next://run/{bottle}"),
+        format!("bottles-next://run/{bottle}//game"),
+        format!("bottles-next://stop/{bottle}/game"),
+        format!("https://run/{bottle}/game"),
+    ] {
+        assert!(matches!(link.parse::<Uri>(), Err(Error::Uri(_))), "{link}");
+    }
+}
@@ -4,3 +4,42 @@ view = View
 welcome = Welcome to COSMIC! ✨
 page-id = Page { $num }
 git-description = Git commit {$hash} on {$date}
+
+## Relative times
+time-just-now = Just now
+time-minutes-ago = { $count ->
+    [one] A minute ago
+   *[other] { $count } minutes ago
+}
+time-hours-ago = { $count ->
+    [one] An hour ago
+   *[other] { $count } hours ago
+}
+time-days-ago = { $count ->
+    [one] Yesterday
+   *[other] { $count } days ago
+}
+time-weeks-ago = { $count ->
+    [one] Last week
+   *[other] { $count } weeks ago
+}
+time-months-ago = { $count ->
+    [one] Last month
+   *[other] { $count } months ago
+}
+time-years-ago = { $count ->
+    [one] Last year
+   *[other] { $count } years ago
+}
+never-played = Never played
+
+## Durations
+duration-none = Not played yet
+duration-minutes = { $count ->
+    [one] 1 minute
+   *[other] { $count } minutes
+}
+duration-hours = { $count ->
+    [one] 1 hour
+   *[other] { $count } hours
+}
@@ -1,11 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::config::{AppExperience, Config};
-use crate::pages::home::Selected;
+use crate::pages::home::{self, Selected};
 use crate::{fl, pages};
-use cosmic::app::{self, Core, Task};
+use bottles_core::uri::Uri;
+use cosmic::app::{self, Core, CosmicFlags, Task};
 use cosmic::cosmic_config::{self, CosmicConfigEntry};
 use cosmic::cosmic_theme::ThemeBuilder;
+use cosmic::dbus_activation::{self, Details};
 use cosmic::iced::alignment::{Horizontal, Vertical};
 use cosmic::iced::{Length, Subscription};
 use cosmic::widget::{self, nav_bar};
@@ -28,6 +30,23 @@ pub struct AppModel {
     details: pages::details::Details,
 }
 
+/// Data the application is started with.
+#[derive(Clone, Debug, Default)]
+pub struct Flags {
+    /// Link given on the command line, handed over to the window that is
+    /// already open when there is one.
+    pub uri: Option<Uri>,
+}
+
+impl CosmicFlags for Flags {
+    type SubCommand = Uri;
+    type Args = Vec<String>;
+
+    fn action(&self) -> Option<&Uri> {
+        self.uri.as_ref()
+    }
+}
+
 /// Messages emitted by the application and its widgets.
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -44,7 +63,7 @@ impl Application for AppModel {
     type Executor = cosmic::executor::Default;
 
     /// Data that your application receives to its init method.
-    type Flags = ();
+    type Flags = Flags;
 
     /// Messages which the application and its widgets will emit.
     type Message = Message;
@@ -61,7 +80,7 @@ impl Application for AppModel {
     }
 
     /// Initializes the application with any given flags and startup commands.
-    fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
+    fn init(core: Core, flags: Self::Flags) -> (Self, Task<Self::Message>) {
         // Create a nav bar with three page items.
         let nav = nav_bar::Model::default();
 
@@ -82,9 +101,14 @@ impl Application for AppModel {
         };
 
         app.core.nav_bar_set_toggled(false);
+        app.home
+            .set_runner_index(app.config.runner_index.as_deref());
 
         // Create a startup command that sets the window title and the theme.
-        let mut tasks = vec![app.update_title()];
+        let mut tasks = vec![app.update_title(), app.home.load()];
+        if let Some(uri) = flags.uri {
+            tasks.push(app.home.update(home::Message::OpenUri(uri)));
+        }
 
         let theme_str = include_str!("../resources/themes/Bottles.ron");
         if let Ok(builder) = ron::from_str::<ThemeBuilder>(theme_str) {
@@ -110,7 +134,10 @@ impl Application for AppModel {
                     .into()]
             }
             (Some(AppExperience::Next), None) => {
-                vec![self.home.options_button().map(Message::Home)]
+                vec![
+                    self.home.runners_button().map(Message::Home),
+                    self.home.options_button().map(Message::Home),
+                ]
             }
             (Some(AppExperience::Classic), Some(Selected::Bottle(_))) => {
                 vec![widget::row()
@@ -135,7 +162,10 @@ impl Application for AppModel {
                     .into()]
             }
             (Some(AppExperience::Classic), None) => {
-                vec![self.home.new_button().map(Message::Home)]
+                vec![
+                    self.home.runners_button().map(Message::Home),
+                    self.home.new_button().map(Message::Home),
+                ]
             }
             (_, _) => vec![],
         }
@@ -209,10 +239,18 @@ impl Application for AppModel {
                     .width(Length::Fill)
                     .into()]
             }
+            (Some(AppExperience::Classic), None) => {
+                vec![self.home.search_bar().map(Message::Home)]
+            }
             (_, _) => vec![],
         }
     }
 
+    /// Displays a dialog on top of the window when one is open.
+    fn dialog(&self) -> Option<Element<Self::Message>> {
+        self.home.dialog().map(|dialog| dialog.map(Message::Home))
+    }
+
     /// Enables the COSMIC application to create a nav bar with this model.
     fn nav_model(&self) -> Option<&nav_bar::Model> {
         None
@@ -279,6 +317,8 @@ impl Application for AppModel {
 
                     Message::UpdateConfig(update.config)
                 }),
+            // Track the programs launched from the application.
+            self.home.subscription().map(Message::Home),
         ])
     }
 
@@ -298,12 +338,31 @@ impl Application for AppModel {
             }
 
             Message::UpdateConfig(config) => {
+                self.home.set_runner_index(config.runner_index.as_deref());
                 self.config = config;
             }
         }
         Task::batch(tasks)
     }
 
+    /// Handles the links given to other instances of the application.
+    fn dbus_activation(&mut self, message: dbus_activation::Message) -> Task<Self::Message> {
+        let uris: Vec<String> = match message.msg {
+            Details::ActivateAction { action, .. } => vec![action],
+            Details::Open { url } => url.into_iter().map(String::from).collect(),
+            Details::Activate => vec![],
+        };
+
+        let mut tasks = vec![];
+        for uri in uris {
+            match uri.parse() {
+                Ok(uri) => tasks.push(self.home.update(home::Message::OpenUri(uri))),
+                Err(why) => eprintln!("{why}"),
+            }
+        }
+        Task::batch(tasks)
+    }
+
     /// Called when a nav item is selected.
     fn on_nav_select(&mut self, id: nav_bar::Id) -> Task<Self::Message> {
         // Activate the page in the model.
@@ -0,0 +1,470 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Command-line interface, for using bottles from scripts without the window.
+//!
+//! Commands go through the same store, runners and supervisor as the interface.
+//! With `--json`, results are printed as JSON on stdout and errors as a JSON
+//! object with an `error` field on stderr.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bottles_core::bottle::{Bottle, Environment};
+use bottles_core::error::{Error, Result};
+use bottles_core::program::Program;
+use bottles_core::runner::{self, Runner, Runners};
+use bottles_core::snapshot::{Snapshot, Snapshots};
+use bottles_core::store::{self, BottleStore};
+use bottles_core::supervisor::{Event, Supervisor};
+use bottles_core::uri::Uri;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use crate::{dbus, i18n};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "bottles-next",
+    version,
+    about = "Run Windows software and games",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Args {
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    /// A bottles-next:// link of a program to run or show in the window.
+    #[arg(value_name = "URI")]
+    pub uri: Option<Uri>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the bottles and the programs installed in them.
+    List,
+    /// Create a bottle and initialize its prefix.
+    New {
+        name: String,
+        #[arg(long, value_enum, default_value_t = EnvironmentArg::Gaming)]
+        environment: EnvironmentArg,
+        /// Id of the runner, the default runner when unset.
+        #[arg(long)]
+        runner: Option<String>,
+    },
+    /// Start a program and wait for it to exit.
+    Run {
+        /// Name or id of the bottle.
+        bottle: String,
+        /// Title or id of the program.
+        program: String,
+    },
+    /// Terminate every process running in a bottle.
+    Kill {
+        /// Name or id of the bottle.
+        bottle: String,
+    },
+    /// Manage the snapshots of a bottle.
+    #[command(subcommand)]
+    Snapshot(SnapshotCommand),
+    /// Print the configuration of a bottle and its programs as JSON.
+    Export {
+        /// Name or id of the bottle.
+        bottle: String,
+        /// Write to this file instead of stdout.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Export the D-Bus interface without opening a window, until interrupted.
+    Serve,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum EnvironmentArg {
+    Gaming,
+    Software,
+    Custom,
+}
+
+impl From<EnvironmentArg> for Environment {
+    fn from(environment: EnvironmentArg) -> Self {
+        match environment {
+            EnvironmentArg::Gaming => Self::Gaming,
+            EnvironmentArg::Software => Self::Software,
+            EnvironmentArg::Custom => Self::Custom,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SnapshotCommand {
+    /// List the snapshots of a bottle, newest first.
+    List { bottle: String },
+    /// Take a snapshot of a bottle.
+    Create {
+        bottle: String,
+        #[arg(long, short, default_value = "")]
+        description: String,
+    },
+    /// Bring a bottle back to a snapshot, stopping its programs first.
+    Restore { bottle: String, snapshot: String },
+    /// Delete a snapshot.
+    Delete { bottle: String, snapshot: String },
+}
+
+#[derive(Serialize)]
+struct BottleSummary<'a> {
+    id: &'a str,
+    name: &'a str,
+    environment: Environment,
+    runner: &'a str,
+    created: DateTime<Utc>,
+    programs: Vec<ProgramSummary<'a>>,
+}
+
+#[derive(Serialize)]
+struct ProgramSummary<'a> {
+    id: &'a str,
+    title: &'a str,
+    executable: &'a PathBuf,
+    /// In seconds.
+    play_time: u64,
+    last_played: Option<DateTime<Utc>>,
+}
+
+impl<'a> From<&'a Program> for ProgramSummary<'a> {
+    fn from(program: &'a Program) -> Self {
+        Self {
+            id: &program.id,
+            title: &program.title,
+            executable: &program.executable,
+            play_time: program.activity.play_time,
+            last_played: program.activity.last_started,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    bottle: &'a Bottle,
+    programs: &'a [Program],
+}
+
+/// Runs the command of `args`, returning the exit code of the process.
+pub fn run(args: Args) -> i32 {
+    let Some(command) = args.command else {
+        return 0;
+    };
+    let result = tokio::runtime::Runtime::new()
+        .map_err(Error::from)
+        .and_then(|runtime| runtime.block_on(execute(command, args.json)));
+    match result {
+        Ok(code) => code,
+        Err(why) => {
+            if args.json {
+                eprintln!("{}", serde_json::json!({ "error": why.to_string() }));
+            } else {
+                eprintln!("error: {why}");
+            }
+            1
+        }
+    }
+}
+
+async fn execute(command: Command, json: bool) -> Result<i32> {
+    let store = BottleStore::default();
+    match command {
+        Command::List => {
+            let bottles = store.load().await?;
+            let library = store.library(&bottles).await?;
+            let summaries: Vec<BottleSummary> = bottles
+                .iter()
+                .map(|bottle| BottleSummary {
+                    id: &bottle.id,
+                    name: &bottle.name,
+                    environment: bottle.environment,
+                    runner: &bottle.runner,
+                    created: bottle.created,
+                    programs: library
+                        .iter()
+                        .filter(|program| program.bottle == bottle.id)
+                        .map(ProgramSummary::from)
+                        .collect(),
+                })
+                .collect();
+            if json {
+                return print_json(&summaries);
+            }
+            for bottle in summaries {
+                println!(
+                    "{} ({}, {})",
+                    bottle.name,
+                    bottle.environment.title(),
+                    bottle.runner
+                );
+                for program in bottle.programs {
+                    println!(
+                        "    {} [{}]",
+                        program.title,
+                        i18n::duration(program.play_time)
+                    );
+                }
+            }
+        }
+        Command::New {
+            name,
+            environment,
+            runner,
+        } => {
+            let runners = discover_runners().await;
+            let runner = match runner {
+                Some(id) => runners.get(&id).ok_or(Error::RunnerNotFound(id))?,
+                None => runners
+                    .default_id()
+                    .and_then(|id| runners.get(id))
+                    .ok_or_else(|| Error::RunnerNotFound("any".to_string()))?,
+            };
+
+            let bottle = store
+                .create(Bottle::new(name, environment.into(), runner.id()))
+                .await?;
+            if let Err(why) = runner::init_prefix(&*runner, &bottle.prefix()).await {
+                // Don't leave a bottle without a working prefix behind.
+                store.delete(&bottle.id).await?;
+                return Err(why);
+            }
+            if json {
+                return print_json(&bottle);
+            }
+            println!("{}", bottle.id);
+        }
+        Command::Run { bottle, program } => {
+            let bottle = find_bottle(&store, &bottle).await?;
+            let programs = store.programs(&bottle.id).await?;
+            let program = find(
+                &programs,
+                &program,
+                |program| [program.id.as_str(), program.title.as_str()],
+                Error::ProgramNotFound,
+            )?;
+            let runner = bottle_runner(&bottle, Some(program)).await?;
+            return run_program(store, &*runner, &bottle, program, json).await;
+        }
+        Command::Kill { bottle } => {
+            let bottle = find_bottle(&store, &bottle).await?;
+            let runner = bottle_runner(&bottle, None).await?;
+            runner.kill(&bottle.prefix()).await?;
+            if json {
+                return print_json(&serde_json::json!({ "bottle": bottle.id }));
+            }
+        }
+        Command::Snapshot(command) => return snapshot(&store, command, json).await,
+        Command::Export { bottle, output } => {
+            let bottle = find_bottle(&store, &bottle).await?;
+            let programs = store.programs(&bottle.id).await?;
+            let export = Export {
+                bottle: &bottle,
+                programs: &programs,
+            };
+            let contents = serde_json::to_string_pretty(&export).map_err(io::Error::other)?;
+            match output {
+                Some(path) => tokio::fs::write(path, contents + "\n").await?,
+                None => println!("{contents}"),
+            }
+        }
+        Command::Serve => {
+            let supervisor = Supervisor::new(store.clone());
+            tokio::select! {
+                result = dbus::serve(store, supervisor) => result.map_err(io::Error::other)?,
+                result = tokio::signal::ctrl_c() => result?,
+            }
+        }
+    }
+    Ok(0)
+}
+
+async fn snapshot(store: &BottleStore, command: SnapshotCommand, json: bool) -> Result<i32> {
+    // Snapshot progress is only worth showing to people.
+    let progress = move |progress: f32| {
+        if !json {
+            eprint!("\r{:3.0}%", progress * 100.);
+        }
+    };
+
+    match command {
+        SnapshotCommand::List { bottle } => {
+            let bottle = find_bottle(store, &bottle).await?;
+            let snapshots = Snapshots::new(&bottle).list().await?;
+            if json {
+                return print_json(&snapshots);
+            }
+            for snapshot in snapshots {
+                println!(
+                    "{}  {}  {}  {}",
+                    snapshot.id,
+                    snapshot.created.format("%Y-%m-%d %H:%M"),
+                    i18n::file_size(snapshot.size),
+                    snapshot.description
+                );
+            }
+        }
+        SnapshotCommand::Create {
+            bottle,
+            description,
+        } => {
+            let bottle = find_bottle(store, &bottle).await?;
+            let snapshots = Snapshots::new(&bottle);
+            let snapshot = snapshots
+                .create(&bottle.prefix(), description.trim(), progress)
+                .await?;
+            snapshots.prune(bottle.retention).await?;
+            if json {
+                return print_json(&snapshot);
+            }
+            eprintln!();
+            println!("{}", snapshot.id);
+        }
+        SnapshotCommand::Restore { bottle, snapshot } => {
+            let bottle = find_bottle(store, &bottle).await?;
+            let snapshot = find_snapshot(&bottle, &snapshot).await?;
+            // Programs must not write into the prefix while it is swapped.
+            if let Ok(runner) = bottle_runner(&bottle, None).await {
+                runner.kill(&bottle.prefix()).await?;
+            }
+            Snapshots::new(&bottle)
+                .restore(&snapshot.id, &bottle.prefix(), progress)
+                .await?;
+            if json {
+                return print_json(&snapshot);
+            }
+            eprintln!();
+        }
+        SnapshotCommand::Delete { bottle, snapshot } => {
+            let bottle = find_bottle(store, &bottle).await?;
+            let snapshot = find_snapshot(&bottle, &snapshot).await?;
+            Snapshots::new(&bottle).delete(&snapshot.id).await?;
+            if json {
+                return print_json(&snapshot);
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Starts `program` and waits until it exits and its play time is recorded,
+/// exiting with the code of the program.
+async fn run_program(
+    store: BottleStore,
+    runner: &dyn Runner,
+    bottle: &Bottle,
+    program: &Program,
+    json: bool,
+) -> Result<i32> {
+    let supervisor = Supervisor::new(store);
+    let mut events = Box::pin(supervisor.events());
+    let pid = supervisor.launch(runner, bottle, program).await?;
+    if !json {
+        eprintln!("started {} ({pid})", program.title);
+    }
+
+    let mut code = None;
+    while let Some(event) = events.next().await {
+        match event {
+            Event::Exited {
+                program: id,
+                code: exit,
+            } if id == program.id => code = Some(exit),
+            Event::Crashed { program: id, .. } if id == program.id => code = Some(1),
+            // Recorded once the program exited and its post-launch script ran.
+            Event::Activity { program: id, .. } if id == program.id && code.is_some() => break,
+            _ => {}
+        }
+    }
+
+    let code = code.unwrap_or(1);
+    if json {
+        print_json(&serde_json::json!({ "program": program.id, "pid": pid, "code": code }))?;
+    }
+    Ok(code)
+}
+
+async fn discover_runners() -> Runners {
+    Runners::discover(&store::data_dir().join("runners")).await
+}
+
+/// The runner of `program` or, without one, of `bottle`.
+async fn bottle_runner(bottle: &Bottle, program: Option<&Program>) -> Result<Arc<dyn Runner>> {
+    let runners = discover_runners().await;
+    match program {
+        Some(program) => runners.for_program(bottle, program),
+        None => runners
+            .get(&bottle.runner)
+            .ok_or_else(|| Error::RunnerNotFound(bottle.runner.clone())),
+    }
+}
+
+async fn find_bottle(store: &BottleStore, query: &str) -> Result<Bottle> {
+    let bottles = store.load().await?;
+    find(
+        &bottles,
+        query,
+        |bottle| [bottle.id.as_str(), bottle.name.as_str()],
+        Error::BottleNotFound,
+    )
+    .cloned()
+}
+
+async fn find_snapshot(bottle: &Bottle, id: &str) -> Result<Snapshot> {
+    Snapshots::new(bottle)
+        .list()
+        .await?
+        .into_iter()
+        .find(|snapshot| snapshot.id == id)
+        .ok_or_else(|| Error::Snapshot(format!("no snapshot {id} in {}", bottle.name)))
+}
+
+/// The item whose id or name is `query`, ignoring case. `names` gives the id
+/// and the name of an item.
+///
+/// Names aren't unique, so a name matching several items is refused with the
+/// ids of those items rather than picking one of them.
+fn find<'a, T>(
+    items: &'a [T],
+    query: &str,
+    names: impl Fn(&T) -> [&str; 2],
+    not_found: impl FnOnce(String) -> Error,
+) -> Result<&'a T> {
+    if let Some(item) = items.iter().find(|item| names(item)[0] == query) {
+        return Ok(item);
+    }
+    let matches: Vec<&T> = items
+        .iter()
+        .filter(|item| {
+            names(item)
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(query))
+        })
+        .collect();
+    match matches.as_slice() {
+        [] => Err(not_found(query.to_string())),
+        [item] => Ok(*item),
+        _ => Err(Error::Ambiguous {
+            name: query.to_string(),
+            ids: matches
+                .iter()
+                .map(|item| names(item)[0].to_string())
+                .collect(),
+        }),
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<i32> {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value).map_err(io::Error::other)?;
+    writeln!(stdout)?;
+    Ok(0)
+}
@@ -4,29 +4,44 @@ use cosmic::{
     Apply,
 };
 
-use crate::{icons, pages::home::program::Program};
+use crate::icons;
 
 #[derive(Clone, Debug)]
 pub struct Card {
     title: String,
     caption: String,
     image: image::Handle,
+    running: bool,
 }
 
-impl From<&Program> for Card {
-    fn from(program: &Program) -> Self {
+impl Card {
+    pub fn new(title: impl Into<String>, caption: impl Into<String>, image: image::Handle) -> Self {
         Self {
-            title: program.title.clone(),
-            caption: program.caption.clone(),
-            image: program.icon.clone(),
+            title: title.into(),
+            caption: caption.into(),
+            image,
+            running: false,
         }
     }
+
+    /// Shows a stop button instead of the play button.
+    pub fn running(mut self, running: bool) -> Self {
+        self.running = running;
+        self
+    }
 }
 
 pub fn card<'a, Message: 'a + Clone + 'static>(
     card: Card,
     width: usize,
+    on_action: Option<Message>,
 ) -> widget::Button<'a, Message> {
+    let action_icon = if card.running {
+        "power-symbolic"
+    } else {
+        "play-large-symbolic"
+    };
+
     widget::column()
         .push(
             widget::image(&card.image)
@@ -45,8 +60,9 @@ pub fn card<'a, Message: 'a + Clone + 'static>(
                 )
                 .push(widget::horizontal_space())
                 .push(
-                    widget::button::icon(icons::get_handle("play-large-symbolic", 18))
-                        .class(cosmic::style::Button::Standard),
+                    widget::button::icon(icons::get_handle(action_icon, 18))
+                        .class(cosmic::style::Button::Standard)
+                        .on_press_maybe(on_action),
                 ),
         )
         .spacing(5)
@@ -61,6 +77,7 @@ impl Default for Card {
             title: String::new(),
             caption: String::new(),
             image: image::Handle::from_bytes(vec![]),
+            running: false,
         }
     }
 }
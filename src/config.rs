@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 #[version = 1]
 pub struct Config {
     pub app_experience: Option<AppExperience>,
+    /// Where runner releases are listed, the official index when unset.
+    pub runner_index: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
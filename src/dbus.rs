@@ -0,0 +1,360 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Session bus interface, for desktop shells, launchers and scripts.
+//!
+//! [`Library`] is exported at [`PATH`] under the [`NAME`] well-known name, by the
+//! application window and by `bottles-next serve`. Programs launched through it
+//! are watched by the same [`Supervisor`] as the ones launched from the window,
+//! and every change of their state is broadcast as a signal.
+//!
+//! `dbus-run-session -- bottles-next serve` exports it on a private bus, away
+//! from the running desktop.
+//!
+//! ```sh
+//! busctl --user call dev.edfloreshz.Bottles.Next.Library \
+//!     /dev/edfloreshz/Bottles/Next/Library \
+//!     dev.edfloreshz.Bottles.Next.Library1 ListPrograms s ""
+//! ```
+
+use bottles_core::bottle::Bottle;
+use bottles_core::error::{Error, Result};
+use bottles_core::program::Program;
+use bottles_core::runner::Runners;
+use bottles_core::store::{self, BottleStore};
+use bottles_core::supervisor::{Event, Supervisor};
+use futures_util::StreamExt;
+use zbus::object_server::SignalContext;
+use zbus::{fdo, interface};
+
+pub const NAME: &str = "dev.edfloreshz.Bottles.Next.Library";
+pub const PATH: &str = "/dev/edfloreshz/Bottles/Next/Library";
+
+pub struct Library {
+    store: BottleStore,
+    supervisor: Supervisor,
+}
+
+#[interface(name = "dev.edfloreshz.Bottles.Next.Library1")]
+impl Library {
+    /// Id, name, environment and runner of every bottle.
+    async fn list_bottles(&self) -> fdo::Result<Vec<(String, String, String, String)>> {
+        let bottles = self.store.load().await.map_err(failed)?;
+        Ok(bottles
+            .into_iter()
+            .map(|bottle| {
+                let environment = bottle.environment.title().to_string();
+                (bottle.id, bottle.name, environment, bottle.runner)
+            })
+            .collect())
+    }
+
+    /// Id, title and bottle id of the programs in `bottle`, or of every program
+    /// when `bottle` is empty.
+    async fn list_programs(&self, bottle: &str) -> fdo::Result<Vec<(String, String, String)>> {
+        let programs = if bottle.is_empty() {
+            let bottles = self.store.load().await.map_err(failed)?;
+            self.store.library(&bottles).await
+        } else {
+            self.store.get(bottle).await.map_err(failed)?;
+            self.store.programs(bottle).await
+        };
+        Ok(programs
+            .map_err(failed)?
+            .into_iter()
+            .map(|program| (program.id, program.title, program.bottle))
+            .collect())
+    }
+
+    /// Id and process id of every running program.
+    async fn running_programs(&self) -> Vec<(String, u32)> {
+        self.supervisor.running().await
+    }
+
+    /// Starts a program and returns its process id.
+    async fn launch(&self, program: &str) -> fdo::Result<u32> {
+        let (bottle, program) = self.find(program).await.map_err(failed)?;
+        let runner = Runners::discover(&store::data_dir().join("runners"))
+            .await
+            .for_program(&bottle, &program)
+            .map_err(failed)?;
+        self.supervisor
+            .launch(&*runner, &bottle, &program)
+            .await
+            .map_err(failed)
+    }
+
+    /// Terminates a program started from the application.
+    async fn stop(&self, program: &str) -> fdo::Result<()> {
+        self.supervisor.stop(program).await.map_err(failed)
+    }
+
+    /// Terminates every process running in a bottle.
+    async fn stop_bottle(&self, bottle: &str) -> fdo::Result<()> {
+        let bottle = self.store.get(bottle).await.map_err(failed)?;
+        let runner = Runners::discover(&store::data_dir().join("runners"))
+            .await
+            .get(&bottle.runner)
+            .ok_or_else(|| failed(Error::RunnerNotFound(bottle.runner.clone())))?;
+        self.supervisor
+            .stop_bottle(&*runner, &bottle)
+            .await
+            .map_err(failed)
+    }
+
+    /// The program was started, from the application or from the bus.
+    #[zbus(signal)]
+    async fn program_started(ctxt: &SignalContext<'_>, program: &str, pid: u32)
+        -> zbus::Result<()>;
+
+    /// The program exited on its own.
+    #[zbus(signal)]
+    async fn program_exited(ctxt: &SignalContext<'_>, program: &str, code: i32)
+        -> zbus::Result<()>;
+
+    /// The program was terminated by a signal, 0 when it isn't known.
+    #[zbus(signal)]
+    async fn program_crashed(
+        ctxt: &SignalContext<'_>,
+        program: &str,
+        signal: i32,
+    ) -> zbus::Result<()>;
+
+    /// The program was stopped from the application.
+    #[zbus(signal)]
+    async fn program_stopped(ctxt: &SignalContext<'_>, program: &str) -> zbus::Result<()>;
+}
+
+impl Library {
+    async fn find(&self, id: &str) -> Result<(Bottle, Program)> {
+        let bottles = self.store.load().await?;
+        let program = self
+            .store
+            .library(&bottles)
+            .await?
+            .into_iter()
+            .find(|program| program.id == id)
+            .ok_or_else(|| Error::ProgramNotFound(id.to_string()))?;
+        let bottle = bottles
+            .into_iter()
+            .find(|bottle| bottle.id == program.bottle)
+            .ok_or_else(|| Error::BottleNotFound(program.bottle.clone()))?;
+        Ok((bottle, program))
+    }
+}
+
+/// Exports [`Library`] on the session bus, see [`export`].
+pub async fn serve(store: BottleStore, supervisor: Supervisor) -> zbus::Result<()> {
+    export(&zbus::Connection::session().await?, store, supervisor).await
+}
+
+/// Exports [`Library`] on the bus of `connection` and requests [`NAME`], then
+/// forwards the events of `supervisor` as signals for as long as it lives.
+pub async fn export(
+    connection: &zbus::Connection,
+    store: BottleStore,
+    supervisor: Supervisor,
+) -> zbus::Result<()> {
+    let mut events = Box::pin(supervisor.events());
+    connection
+        .object_server()
+        .at(PATH, Library { store, supervisor })
+        .await?;
+    connection.request_name(NAME).await?;
+
+    let ctxt = SignalContext::new(connection, PATH)?;
+    while let Some(event) = events.next().await {
+        let result = match event {
+            Event::Started { program, pid } => Library::program_started(&ctxt, &program, pid).await,
+            Event::Exited { program, code } => Library::program_exited(&ctxt, &program, code).await,
+            Event::Crashed { program, signal } => {
+                Library::program_crashed(&ctxt, &program, signal.unwrap_or_default()).await
+            }
+            Event::Stopped { program } => Library::program_stopped(&ctxt, &program).await,
+            Event::Activity { .. } => Ok(()),
+        };
+        if let Err(why) = result {
+            eprintln!("failed to emit D-Bus signal: {why}");
+        }
+    }
+    Ok(())
+}
+
+/// Invalid ids are the caller's fault, everything else is ours.
+fn failed(why: Error) -> fdo::Error {
+    match why {
+        Error::BottleNotFound(_) | Error::ProgramNotFound(_) | Error::RunnerNotFound(_) => {
+            fdo::Error::InvalidArgs(why.to_string())
+        }
+        why => fdo::Error::Failed(why.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::time::Duration;
+
+    use bottles_core::bottle::Environment;
+    use bottles_core::runner::{Fake, FAKE_RUNNER_ENV};
+    use futures_util::Stream;
+    use zbus::message::Message;
+
+    use super::*;
+
+    const INTERFACE: &str = "dev.edfloreshz.Bottles.Next.Library1";
+
+    /// A bus of its own, away from the session of whoever runs the tests.
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("dbus-daemon is installed");
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Self {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+
+        async fn connect(&self) -> zbus::Connection {
+            zbus::connection::Builder::address(self.address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    async fn next(signals: &mut (impl Stream<Item = Message> + Unpin)) -> Message {
+        tokio::time::timeout(Duration::from_secs(10), signals.next())
+            .await
+            .expect("no signal in time")
+            .expect("signals ended")
+    }
+
+    #[tokio::test]
+    async fn programs_are_listed_launched_and_stopped() {
+        // Programs wait to be stopped unless their overrides give an exit code.
+        std::env::set_var(
+            FAKE_RUNNER_ENV,
+            r#"case "$FAKE_EXECUTABLE" in winecfg|wineboot|reg) exit 0;; esac
+            [ -n "$EXIT" ] && exit "$EXIT"; exec sleep 30"#,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let store = BottleStore::new(dir.path());
+        let bottle = store
+            .create(Bottle::new("Games", Environment::Gaming, Fake::ID))
+            .await
+            .unwrap();
+        let game = store
+            .save_program(Program::new(
+                &bottle.id,
+                "Game",
+                PathBuf::from("C:\\game.exe"),
+            ))
+            .await
+            .unwrap();
+        let mut tool = Program::new(&bottle.id, "Tool", PathBuf::from("C:\\tool.exe"));
+        tool.overrides.env = vec![("EXIT".to_string(), "3".to_string())];
+        let tool = store.save_program(tool).await.unwrap();
+
+        let bus = Bus::start();
+        let server = bus.connect().await;
+        let supervisor = Supervisor::new(store.clone());
+        tokio::spawn(async move { export(&server, store, supervisor).await });
+
+        let client = bus.connect().await;
+        let proxy = zbus::Proxy::new(&client, NAME, PATH, INTERFACE)
+            .await
+            .unwrap();
+        let mut signals = proxy.receive_all_signals().await.unwrap();
+        // The name is owned once the interface is exported.
+        let dbus = fdo::DBusProxy::new(&client).await.unwrap();
+        while !dbus.name_has_owner(NAME.try_into().unwrap()).await.unwrap() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let bottles: Vec<(String, String, String, String)> =
+            proxy.call("ListBottles", &()).await.unwrap();
+        assert_eq!(
+            bottles,
+            [(
+                bottle.id.clone(),
+                "Games".to_string(),
+                "Gaming".to_string(),
+                Fake::ID.to_string()
+            )]
+        );
+        let programs: Vec<(String, String, String)> =
+            proxy.call("ListPrograms", &("")).await.unwrap();
+        assert_eq!(
+            programs,
+            [
+                (game.id.clone(), "Game".to_string(), bottle.id.clone()),
+                (tool.id.clone(), "Tool".to_string(), bottle.id.clone()),
+            ]
+        );
+        let unknown = proxy
+            .call::<_, _, Vec<(String, String, String)>>("ListPrograms", &("nothing"))
+            .await;
+        assert!(matches!(unknown, Err(zbus::Error::MethodError(name, _, _))
+            if name.as_str() == "org.freedesktop.DBus.Error.InvalidArgs"));
+
+        let pid: u32 = proxy.call("Launch", &(game.id.as_str())).await.unwrap();
+        let started = next(&mut signals).await;
+        assert_eq!(
+            started.header().member().unwrap().as_str(),
+            "ProgramStarted"
+        );
+        assert_eq!(
+            started.body().deserialize::<(String, u32)>().unwrap(),
+            (game.id.clone(), pid)
+        );
+        let running: Vec<(String, u32)> = proxy.call("RunningPrograms", &()).await.unwrap();
+        assert_eq!(running, [(game.id.clone(), pid)]);
+
+        let () = proxy.call("Stop", &(game.id.as_str())).await.unwrap();
+        let stopped = next(&mut signals).await;
+        assert_eq!(
+            stopped.header().member().unwrap().as_str(),
+            "ProgramStopped"
+        );
+        assert_eq!(stopped.body().deserialize::<String>().unwrap(), game.id);
+
+        let pid: u32 = proxy.call("Launch", &(tool.id.as_str())).await.unwrap();
+        let started = next(&mut signals).await;
+        assert_eq!(
+            started.body().deserialize::<(String, u32)>().unwrap(),
+            (tool.id.clone(), pid)
+        );
+        let exited = next(&mut signals).await;
+        assert_eq!(exited.header().member().unwrap().as_str(), "ProgramExited");
+        assert_eq!(
+            exited.body().deserialize::<(String, i32)>().unwrap(),
+            (tool.id.clone(), 3)
+        );
+
+        let unknown = proxy.call::<_, _, u32>("Launch", &("nothing")).await;
+        assert!(matches!(unknown, Err(zbus::Error::MethodError(name, _, _))
+            if name.as_str() == "org.freedesktop.DBus.Error.InvalidArgs"));
+    }
+}
@@ -4,6 +4,7 @@
 
 use std::sync::LazyLock;
 
+use chrono::{DateTime, Utc};
 use i18n_embed::{
     fluent::{fluent_language_loader, FluentLanguageLoader},
     unic_langid::LanguageIdentifier,
@@ -38,6 +39,56 @@ pub static LANGUAGE_LOADER: LazyLock<FluentLanguageLoader> = LazyLock::new(|| {
     loader
 });
 
+/// Describes how long ago `time` was, such as "3 hours ago".
+pub fn relative_time(time: DateTime<Utc>) -> String {
+    let elapsed = Utc::now().signed_duration_since(time);
+    let days = elapsed.num_days();
+
+    if elapsed.num_minutes() < 1 {
+        crate::fl!("time-just-now")
+    } else if elapsed.num_hours() < 1 {
+        crate::fl!("time-minutes-ago", count = elapsed.num_minutes())
+    } else if days < 1 {
+        crate::fl!("time-hours-ago", count = elapsed.num_hours())
+    } else if days < 7 {
+        crate::fl!("time-days-ago", count = days)
+    } else if days < 30 {
+        crate::fl!("time-weeks-ago", count = days / 7)
+    } else if days < 365 {
+        crate::fl!("time-months-ago", count = days / 30)
+    } else {
+        crate::fl!("time-years-ago", count = days / 365)
+    }
+}
+
+/// Describes a play time given in seconds, such as "12 hours".
+pub fn duration(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    if minutes < 1 {
+        crate::fl!("duration-none")
+    } else if minutes < 60 {
+        crate::fl!("duration-minutes", count = minutes)
+    } else {
+        crate::fl!("duration-hours", count = minutes / 60)
+    }
+}
+
+/// Formats a size in bytes with the largest unit that keeps it above one.
+pub fn file_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
 /// Request a localized string by ID from the i18n/ directory.
 #[macro_export]
 macro_rules! fl {
@@ -1,11 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod app;
+mod cli;
 mod components;
 mod config;
+mod dbus;
 mod i18n;
 mod icons;
 mod pages;
+mod search;
+
+use clap::Parser;
 
 fn main() -> cosmic::iced::Result {
     // Get the system's preferred languages.
@@ -14,6 +19,12 @@ fn main() -> cosmic::iced::Result {
     // Enable localizations to be applied.
     i18n::init(&requested_languages);
 
+    // Run the command given on the command line instead of opening the window.
+    let args = cli::Args::parse();
+    if args.command.is_some() {
+        std::process::exit(cli::run(args));
+    }
+
     // Initialize the icon cache.
     icons::init();
 
@@ -24,6 +35,7 @@ fn main() -> cosmic::iced::Result {
             .min_height(180.0),
     );
 
-    // Starts the application's event loop with `()` as the application's flags.
-    cosmic::app::run::<app::AppModel>(settings, ())
+    // Starts the application's event loop, or hands the link over to the window
+    // that is already open.
+    cosmic::app::run_single_instance::<app::AppModel>(settings, app::Flags { uri: args.uri })
 }
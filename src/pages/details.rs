@@ -1,70 +1,1101 @@
+use std::path::PathBuf;
+
+use bottles_core::{
+    bottle::{Bottle, Settings, WindowsVersion},
+    component::Release,
+    dependency::{Ledger, Package},
+    program::{self, Overrides, Program},
+    scanner::Discovered,
+    snapshot::Snapshot,
+};
 use cosmic::{
     app::Task,
-    iced::{Alignment, Length},
+    iced::{Alignment, ContentFit, Length},
     widget, Apply, Element,
 };
 
-use crate::{app, components::card::Card};
+use crate::{
+    app, fl, i18n, icons,
+    pages::home::{self, BottleTab, DialogPage, ProgramExt, ProgramTab},
+};
 
-use super::home::{GridMetrics, Selected};
+use super::home::Selected;
 
 pub struct Details {
     selected: Option<Selected>,
+    bottle_tab: BottleTab,
+    program_tab: ProgramTab,
+    snapshots: Vec<Snapshot>,
+    snapshot_job: Option<SnapshotJob>,
+    /// Every package of the dependency manifest.
+    packages: Vec<Package>,
+    /// Packages installed in the selected bottle.
+    ledger: Ledger,
+    /// Every release of the component catalog.
+    releases: Vec<Release>,
+    dependency_job: Option<DependencyJob>,
+    context: Context,
+    /// Space taken by the selected bottle, once measured.
+    disk_usage: Option<u64>,
+    scan: Option<Scan>,
+    /// Unsaved changes to the selected item.
+    draft: Option<Draft>,
+    runner_ids: Vec<String>,
+    runner_names: Vec<String>,
+    /// The runner names preceded by the runner of the bottle, for overrides.
+    runner_choices: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
     SetSelected(Option<Selected>),
+    SetBottleTab(BottleTab),
+    SetProgramTab(ProgramTab),
+    SetSnapshots(Vec<Snapshot>),
+    SetSnapshotJob(Option<SnapshotJob>),
+    SetPackages(Vec<Package>),
+    SetLedger(Ledger),
+    SetReleases(Vec<Release>),
+    SetDependencyJob(Option<DependencyJob>),
+    SetContext(Context),
+    SetDiskUsage(Option<u64>),
+    SetScan(Option<Scan>),
+    /// Forwards an action to the home page.
+    Home(home::Message),
+    SetRunners {
+        ids: Vec<String>,
+        names: Vec<String>,
+    },
+    EditSettings(Settings),
+    SelectRunner(usize),
+    SaveSettings,
+    EditOverrides(OverridesForm),
+    SaveOverrides,
+    Revert,
+}
+
+/// What surrounds the selected item, provided by the home page.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    /// The selected bottle, or the bottle of the selected program.
+    pub bottle: Option<Bottle>,
+    /// Name of the runner used by the selected item.
+    pub runner: Option<String>,
+    /// Programs installed in the selected bottle.
+    pub programs: Vec<Program>,
+}
+
+/// Unsaved changes to the selected bottle or program.
+#[derive(Clone, Debug, PartialEq)]
+enum Draft {
+    Bottle { runner: String, settings: Settings },
+    Program(OverridesForm),
+}
+
+impl Draft {
+    fn new(selected: &Selected) -> Self {
+        match selected {
+            Selected::Bottle(bottle) => Self::Bottle {
+                runner: bottle.runner.clone(),
+                settings: bottle.settings.clone(),
+            },
+            Selected::Program(program) => Self::Program(OverridesForm::new(&program.overrides)),
+        }
+    }
+}
+
+/// Fields of the advanced tab of a program, see [`Overrides`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OverridesForm {
+    pub arguments: String,
+    pub working_dir: String,
+    pub env: Vec<(String, String)>,
+    pub pre_launch: String,
+    pub post_launch: String,
+    pub runner: Option<String>,
+    pub terminal: bool,
+}
+
+impl OverridesForm {
+    fn new(overrides: &Overrides) -> Self {
+        Self {
+            arguments: program::join_arguments(&overrides.arguments),
+            working_dir: overrides
+                .working_dir
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            env: overrides.env.clone(),
+            pre_launch: overrides.pre_launch.clone().unwrap_or_default(),
+            post_launch: overrides.post_launch.clone().unwrap_or_default(),
+            runner: overrides.runner.clone(),
+            terminal: overrides.terminal,
+        }
+    }
+
+    fn overrides(&self) -> Overrides {
+        let text = |value: &str| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        Overrides {
+            arguments: program::split_arguments(&self.arguments),
+            working_dir: text(&self.working_dir).map(PathBuf::from),
+            env: self.env.clone(),
+            pre_launch: text(&self.pre_launch),
+            post_launch: text(&self.post_launch),
+            runner: self.runner.clone(),
+            terminal: self.terminal,
+        }
+    }
+}
+
+/// Progress of the snapshot operation running on the selected bottle.
+#[derive(Clone, Debug)]
+pub enum SnapshotJob {
+    Running { progress: f32, status: String },
+    Failed(String),
+}
+
+/// Progress of the dependency or component being installed in or uninstalled
+/// from the selected bottle.
+#[derive(Clone, Debug)]
+pub enum DependencyJob {
+    Running { progress: f32, status: String },
+    Failed(String),
+}
+
+/// Progress of the search for programs installed in the selected bottle.
+#[derive(Clone, Debug)]
+pub enum Scan {
+    Running,
+    Found(Vec<Discovered>),
+    Failed(String),
 }
 
 impl Details {
     pub fn new() -> Self {
-        Self { selected: None }
+        Self {
+            selected: None,
+            bottle_tab: BottleTab::Programs,
+            program_tab: ProgramTab::Program,
+            snapshots: vec![],
+            snapshot_job: None,
+            packages: vec![],
+            ledger: Ledger::new(),
+            releases: vec![],
+            dependency_job: None,
+            context: Context::default(),
+            disk_usage: None,
+            scan: None,
+            draft: None,
+            runner_ids: vec![],
+            runner_names: vec![],
+            runner_choices: vec![],
+        }
     }
 
     pub fn view(&self) -> Option<Element<Message>> {
-        self.selected.as_ref().map(|_| {
-            let spacing = cosmic::theme::active().cosmic().spacing;
-            let width = 800.0 as usize;
-            let GridMetrics {
-                cols,
-                item_width,
-                column_spacing,
-            } = GridMetrics::new(width, 260 + 2 * spacing.space_s as usize, spacing.space_s);
-
-            let mut grid = widget::grid();
-            let mut col = 0;
-            for _ in 0..10 {
-                if col >= cols {
-                    grid = grid.insert_row();
-                    col = 0;
-                }
-                grid = grid.push(crate::components::card::card(Card::default(), item_width));
-                col += 1;
+        match (&self.selected, &self.draft) {
+            (Some(Selected::Bottle(bottle)), Some(Draft::Bottle { runner, settings })) => {
+                Some(match self.bottle_tab {
+                    BottleTab::Programs => self.bottle(bottle),
+                    BottleTab::Settings => self.settings(bottle, runner, settings),
+                    BottleTab::Dependencies => self.dependencies(bottle),
+                    BottleTab::Snapshots => self.snapshots(bottle),
+                })
+            }
+            (Some(Selected::Program(program)), Some(Draft::Program(overrides))) => {
+                Some(match self.program_tab {
+                    ProgramTab::Program => self.program(program),
+                    ProgramTab::Advanced => self.advanced(program, overrides),
+                })
             }
+            _ => None,
+        }
+    }
+
+    fn program<'a>(&'a self, program: &'a Program) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let (action, on_action) = if program.status.is_running() {
+            ("Stop", home::Message::Stop(program.clone()))
+        } else {
+            ("Play", home::Message::Launch(program.clone()))
+        };
+        let menu_action = if program.desktop_entry {
+            "Remove from applications menu"
+        } else {
+            "Add to applications menu"
+        };
+
+        let header = widget::row()
+            .push(
+                widget::column()
+                    .push(widget::text(program.title.as_str()).size(24.))
+                    .push(widget::text::caption(program.caption())),
+            )
+            .push(widget::horizontal_space())
+            .push(
+                widget::button::standard(menu_action).on_press(Message::Home(
+                    home::Message::SetDesktopEntry(program.clone(), !program.desktop_entry),
+                )),
+            )
+            .push(
+                widget::button::standard("Edit")
+                    .on_press(Message::Home(home::Message::EditProgram(program.clone()))),
+            )
+            .push(widget::button::suggested(action).on_press(Message::Home(on_action)))
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_xs);
+
+        let activity = program.activity;
+        let info = widget::column()
+            .push(property("Play time", i18n::duration(activity.play_time)))
+            .push(property(
+                "Last played",
+                activity
+                    .last_started
+                    .map(i18n::relative_time)
+                    .unwrap_or_else(|| fl!("never-played")),
+            ))
+            .push_maybe(
+                self.context
+                    .bottle
+                    .as_ref()
+                    .map(|bottle| property("Bottle", bottle.name.clone())),
+            )
+            .push_maybe(
+                self.context
+                    .runner
+                    .as_ref()
+                    .map(|runner| property("Runner", runner.clone())),
+            )
+            .push(property(
+                "Executable",
+                program.executable.display().to_string(),
+            ))
+            .spacing(spacing.space_xs);
+
+        let cover = program.thumbnail.as_ref().map(|_| {
+            widget::image(program.image())
+                .width(Length::Fill)
+                .height(220.)
+                .content_fit(ContentFit::Cover)
+        });
+
+        widget::column()
+            .push_maybe(cover)
+            .push(header)
+            .push(info)
+            .spacing(spacing.space_s)
+            .apply(widget::scrollable)
+            .apply(widget::container)
+            .padding(spacing.space_s)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .class(cosmic::style::Container::Card)
+            .into()
+    }
+
+    fn bottle<'a>(&'a self, bottle: &'a Bottle) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+
+        let header = widget::row()
+            .push(icons::get_icon(bottle.icon(), 32))
+            .push(
+                widget::column()
+                    .push(widget::text(bottle.name.as_str()).size(24.))
+                    .push(widget::text::caption(bottle.environment.title())),
+            )
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s);
+
+        let info = widget::column()
+            .push_maybe(
+                self.context
+                    .runner
+                    .as_ref()
+                    .map(|runner| property("Runner", runner.clone())),
+            )
+            .push(property(
+                "Windows version",
+                bottle.settings.windows.title().to_string(),
+            ))
+            .push(property(
+                "Disk usage",
+                self.disk_usage
+                    .map(i18n::file_size)
+                    .unwrap_or_else(|| "Calculating...".to_string()),
+            ))
+            .push(property("Created", i18n::relative_time(bottle.created)))
+            .spacing(spacing.space_xs);
+
+        let mut programs = widget::column()
+            .push(widget::text("Programs").size(18.))
+            .spacing(spacing.space_xs);
+        if self.context.programs.is_empty() {
+            programs = programs.push(widget::text::caption(
+                "No programs are installed in this bottle yet.",
+            ));
+        }
+        for program in &self.context.programs {
+            let (icon, on_action) = if program.status.is_running() {
+                ("power-symbolic", home::Message::Stop(program.clone()))
+            } else {
+                (
+                    "play-large-symbolic",
+                    home::Message::Launch(program.clone()),
+                )
+            };
+            programs = programs.push(
+                widget::row()
+                    .push(
+                        widget::column()
+                            .push(widget::text(program.title.as_str()))
+                            .push(widget::text::caption(program.caption())),
+                    )
+                    .push(widget::horizontal_space())
+                    .push(
+                        widget::button::icon(icons::get_handle(icon, 18))
+                            .class(cosmic::style::Button::Standard)
+                            .on_press(Message::Home(on_action)),
+                    )
+                    .align_y(Alignment::Center)
+                    .padding(spacing.space_xs)
+                    .apply(widget::button::custom)
+                    .on_press(Message::Home(home::Message::Select(Some(
+                        Selected::Program(program.clone()),
+                    )))),
+            );
+        }
+
+        widget::column()
+            .push(header)
+            .push(info)
+            .push(programs)
+            .push(self.discovered(bottle))
+            .spacing(spacing.space_m)
+            .apply(widget::scrollable)
+            .apply(widget::container)
+            .padding(spacing.space_s)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .class(cosmic::style::Container::Card)
+            .into()
+    }
+
+    /// The programs found in the prefix of `bottle` that aren't in the library yet.
+    fn discovered<'a>(&'a self, bottle: &'a Bottle) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let found: Vec<&Discovered> = match &self.scan {
+            Some(Scan::Found(found)) => found
+                .iter()
+                .filter(|discovered| {
+                    !self
+                        .context
+                        .programs
+                        .iter()
+                        .any(|program| program.executable == discovered.executable)
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let action = match &self.scan {
+            Some(Scan::Running) => widget::button::standard("Cancel")
+                .on_press(Message::Home(home::Message::CancelScan)),
+            _ => widget::button::standard("Scan again")
+                .on_press(Message::Home(home::Message::ScanBottle(bottle.clone()))),
+        };
+        let add_all = (found.len() > 1).then(|| {
+            widget::button::suggested("Add all").on_press(Message::Home(
+                home::Message::AddPrograms(
+                    bottle.id.clone(),
+                    found.iter().map(|&discovered| discovered.clone()).collect(),
+                ),
+            ))
+        });
+        let header = widget::row()
+            .push(
+                widget::column()
+                    .push(widget::text("Found in this bottle").size(18.))
+                    .push(widget::text::caption(
+                        "Programs installed in the bottle that aren't in the library.",
+                    )),
+            )
+            .push(widget::horizontal_space())
+            .push(action)
+            .push_maybe(add_all)
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_xs);
+
+        let mut list = widget::column().push(header).spacing(spacing.space_xs);
+        match &self.scan {
+            Some(Scan::Running) => {
+                list = list.push(widget::text::caption("Looking for programs..."));
+            }
+            Some(Scan::Failed(why)) => list = list.push(widget::text::caption(why.as_str())),
+            Some(Scan::Found(_)) if found.is_empty() => {
+                list = list.push(widget::text::caption("No other programs were found."));
+            }
+            _ => {}
+        }
+        for discovered in found {
+            list = list.push(
+                widget::row()
+                    .push(
+                        widget::column()
+                            .push(widget::text(discovered.title.as_str()))
+                            .push(widget::text::caption(discovered.location.as_str())),
+                    )
+                    .push(widget::horizontal_space())
+                    .push(widget::button::standard("Add").on_press(Message::Home(
+                        home::Message::AddPrograms(bottle.id.clone(), vec![discovered.clone()]),
+                    )))
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_xs)
+                    .padding(spacing.space_xs),
+            );
+        }
+        list.into()
+    }
+
+    fn settings<'a>(
+        &'a self,
+        bottle: &'a Bottle,
+        runner: &'a String,
+        settings: &'a Settings,
+    ) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let changed = *runner != bottle.runner || *settings != bottle.settings;
+        let toggle = |value: bool, set: fn(&mut Settings, bool)| {
+            widget::toggler(value).on_toggle(move |value| {
+                let mut settings = settings.clone();
+                set(&mut settings, value);
+                Message::EditSettings(settings)
+            })
+        };
+
+        let list = widget::column()
+            .push(setting(
+                "Runner",
+                "Wine build used to start programs.",
+                widget::dropdown(
+                    &self.runner_names,
+                    self.runner_ids.iter().position(|id| id == runner),
+                    Message::SelectRunner,
+                ),
+            ))
+            .push(setting(
+                "Windows version",
+                "Version reported to programs.",
+                widget::dropdown(
+                    &WindowsVersion::TITLES[..],
+                    Some(settings.windows.index()),
+                    move |index| {
+                        Message::EditSettings(Settings {
+                            windows: WindowsVersion::ALL[index],
+                            ..settings.clone()
+                        })
+                    },
+                ),
+            ))
+            .push(setting(
+                "DXVK",
+                "Run Direct3D 9 to 11 over Vulkan, once installed from Dependencies.",
+                toggle(settings.dxvk, |settings, value| settings.dxvk = value),
+            ))
+            .push(setting(
+                "VKD3D",
+                "Run Direct3D 12 over Vulkan, once installed from Dependencies.",
+                toggle(settings.vkd3d, |settings, value| settings.vkd3d = value),
+            ))
+            .push(setting(
+                "Esync",
+                "Reduce synchronization overhead using eventfd.",
+                toggle(settings.esync, |settings, value| settings.esync = value),
+            ))
+            .push(setting(
+                "Fsync",
+                "Reduce synchronization overhead using futex.",
+                toggle(settings.fsync, |settings, value| settings.fsync = value),
+            ))
+            .push(setting(
+                "GameMode",
+                "Optimize the system while programs run.",
+                toggle(settings.gamemode, |settings, value| {
+                    settings.gamemode = value
+                }),
+            ))
+            .push(setting(
+                "MangoHud",
+                "Show an overlay with frame rate and usage.",
+                toggle(settings.mangohud, |settings, value| {
+                    settings.mangohud = value
+                }),
+            ))
+            .push(setting(
+                "Environment variables",
+                "Added to the environment of every program.",
+                variables(&settings.env, move |env| {
+                    Message::EditSettings(Settings {
+                        env,
+                        ..settings.clone()
+                    })
+                }),
+            ))
+            .spacing(spacing.space_xs);
 
-            widget::container(widget::scrollable(
-                grid.column_spacing(column_spacing)
-                    .row_spacing(column_spacing),
+        form(
+            "Settings",
+            "Changes apply the next time a program is started.",
+            changed.then_some(Message::SaveSettings),
+            list,
+        )
+    }
+
+    fn advanced<'a>(
+        &'a self,
+        program: &'a Program,
+        overrides: &'a OverridesForm,
+    ) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let changed = *overrides != OverridesForm::new(&program.overrides);
+        let edit = |overrides: OverridesForm| Message::EditOverrides(overrides);
+        let runner = overrides.runner.as_ref().map_or(Some(0), |runner| {
+            self.runner_ids
+                .iter()
+                .position(|id| id == runner)
+                .map(|index| index + 1)
+        });
+
+        let list = widget::column()
+            .push(setting(
+                "Arguments",
+                "Added after the arguments of the program.",
+                widget::text_input("Arguments", overrides.arguments.as_str()).on_input(
+                    move |arguments| {
+                        edit(OverridesForm {
+                            arguments,
+                            ..overrides.clone()
+                        })
+                    },
+                ),
+            ))
+            .push(setting(
+                "Working directory",
+                "Folder the program is started from.",
+                widget::text_input("Working directory", overrides.working_dir.as_str()).on_input(
+                    move |working_dir| {
+                        edit(OverridesForm {
+                            working_dir,
+                            ..overrides.clone()
+                        })
+                    },
+                ),
+            ))
+            .push(setting(
+                "Runner",
+                "Wine build used instead of the one of the bottle.",
+                widget::dropdown(&self.runner_choices, runner, move |index| {
+                    edit(OverridesForm {
+                        runner: index
+                            .checked_sub(1)
+                            .and_then(|index| self.runner_ids.get(index).cloned()),
+                        ..overrides.clone()
+                    })
+                }),
             ))
-            .max_width(1600.)
-            .padding(spacing.space_xs)
-            .align_x(Alignment::Center)
+            .push(setting(
+                "Pre-launch script",
+                "Shell commands run before the program starts.",
+                widget::text_input("Script", overrides.pre_launch.as_str()).on_input(
+                    move |pre_launch| {
+                        edit(OverridesForm {
+                            pre_launch,
+                            ..overrides.clone()
+                        })
+                    },
+                ),
+            ))
+            .push(setting(
+                "Post-launch script",
+                "Shell commands run once the program exits.",
+                widget::text_input("Script", overrides.post_launch.as_str()).on_input(
+                    move |post_launch| {
+                        edit(OverridesForm {
+                            post_launch,
+                            ..overrides.clone()
+                        })
+                    },
+                ),
+            ))
+            .push(setting(
+                "Run in terminal",
+                "Follow the output of the program to debug it.",
+                widget::toggler(overrides.terminal).on_toggle(move |terminal| {
+                    edit(OverridesForm {
+                        terminal,
+                        ..overrides.clone()
+                    })
+                }),
+            ))
+            .push(setting(
+                "Environment variables",
+                "Added after the variables of the bottle.",
+                variables(&overrides.env, move |env| {
+                    edit(OverridesForm {
+                        env,
+                        ..overrides.clone()
+                    })
+                }),
+            ))
+            .spacing(spacing.space_xs);
+
+        form(
+            "Advanced",
+            "Launch options of this program, applied over the settings of its bottle.",
+            changed.then_some(Message::SaveOverrides),
+            list,
+        )
+    }
+
+    fn dependencies<'a>(&'a self, bottle: &'a Bottle) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let running = matches!(self.dependency_job, Some(DependencyJob::Running { .. }));
+
+        let header = widget::column()
+            .push(widget::text("Dependencies").size(18.))
+            .push(widget::text::caption(
+                "Graphics components, runtimes and fonts the programs of this bottle need.",
+            ));
+
+        let job: Option<Element<Message>> = self.dependency_job.as_ref().map(|job| match job {
+            DependencyJob::Running { progress, status } => widget::column()
+                .push(widget::text::caption(status.as_str()))
+                .push(widget::progress_bar(0.0..=1.0, *progress))
+                .spacing(5.)
+                .into(),
+            DependencyJob::Failed(why) => widget::text::caption(why.as_str()).into(),
+        });
+
+        let mut list = widget::column()
+            .push(widget::text("Components").size(16.))
+            .spacing(spacing.space_xs);
+        for release in &self.releases {
+            let installed = bottle.components.get(&release.kind);
+            let caption = match installed {
+                Some(version) if *version == release.version => "Installed".to_string(),
+                Some(version) => format!("Replaces version {version}"),
+                None => "Not installed".to_string(),
+            };
+            let action = if installed == Some(&release.version) {
+                widget::button::standard("Uninstall").on_press_maybe((!running).then(|| {
+                    Message::Home(home::Message::UninstallComponent(
+                        bottle.clone(),
+                        release.kind,
+                    ))
+                }))
+            } else {
+                widget::button::suggested("Install").on_press_maybe((!running).then(|| {
+                    Message::Home(home::Message::InstallComponent(
+                        bottle.clone(),
+                        release.clone(),
+                    ))
+                }))
+            };
+            list = list.push(
+                widget::row()
+                    .push(
+                        widget::column()
+                            .push(widget::text(release.title()))
+                            .push(widget::text::caption(caption)),
+                    )
+                    .push(widget::horizontal_space())
+                    .push(action)
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_xs)
+                    .padding(spacing.space_xs)
+                    .apply(widget::container)
+                    .class(cosmic::style::Container::Card),
+            );
+        }
+
+        list = list.push(widget::text("Redistributables").size(16.));
+        if self.packages.is_empty() {
+            list = list.push(widget::text::caption("No dependencies are available."));
+        }
+        for package in &self.packages {
+            let installed = self.ledger.get(&package.id);
+            let caption = match installed {
+                Some(installed) => {
+                    format!("Installed {}", i18n::relative_time(installed.installed))
+                }
+                None => package.description.clone(),
+            };
+            let action = match installed {
+                Some(_) => {
+                    widget::button::standard("Uninstall").on_press_maybe((!running).then(|| {
+                        Message::Home(home::Message::UninstallDependency(
+                            bottle.clone(),
+                            package.id.clone(),
+                        ))
+                    }))
+                }
+                None => {
+                    widget::button::suggested("Install").on_press_maybe((!running).then(|| {
+                        Message::Home(home::Message::InstallDependency(
+                            bottle.clone(),
+                            package.id.clone(),
+                        ))
+                    }))
+                }
+            };
+            list = list.push(
+                widget::row()
+                    .push(
+                        widget::column()
+                            .push(widget::text(package.name.as_str()))
+                            .push(widget::text::caption(caption)),
+                    )
+                    .push(widget::horizontal_space())
+                    .push(action)
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_xs)
+                    .padding(spacing.space_xs)
+                    .apply(widget::container)
+                    .class(cosmic::style::Container::Card),
+            );
+        }
+
+        widget::column()
+            .push(header)
+            .push_maybe(job)
+            .push(widget::scrollable(list))
+            .spacing(spacing.space_s)
+            .padding(spacing.space_s)
+            .apply(widget::container)
             .width(Length::Fill)
             .height(Length::Fill)
+            .class(cosmic::style::Container::Card)
+            .into()
+    }
+
+    fn snapshots<'a>(&'a self, bottle: &'a Bottle) -> Element<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let running = matches!(self.snapshot_job, Some(SnapshotJob::Running { .. }));
+
+        let header = widget::row()
+            .push(
+                widget::column()
+                    .push(widget::text("Snapshots").size(18.))
+                    .push(widget::text::caption(
+                        "Save the state of the bottle and go back to it later.",
+                    )),
+            )
+            .push(widget::horizontal_space())
+            .push(
+                widget::button::suggested("New snapshot").on_press_maybe((!running).then(|| {
+                    Message::Home(home::Message::OpenDialog(DialogPage::NewSnapshot {
+                        bottle: bottle.clone(),
+                        description: String::new(),
+                    }))
+                })),
+            )
+            .align_y(Alignment::Center);
+
+        let job: Option<Element<Message>> = self.snapshot_job.as_ref().map(|job| match job {
+            SnapshotJob::Running { progress, status } => widget::column()
+                .push(widget::text::caption(status.as_str()))
+                .push(widget::progress_bar(0.0..=1.0, *progress))
+                .spacing(5.)
+                .into(),
+            SnapshotJob::Failed(why) => widget::text::caption(why.as_str()).into(),
+        });
+
+        let mut list = widget::column().spacing(spacing.space_xs);
+        if self.snapshots.is_empty() {
+            list = list.push(widget::text::caption("This bottle has no snapshots yet."));
+        }
+        for snapshot in &self.snapshots {
+            let title = if snapshot.description.is_empty() {
+                "Untitled snapshot"
+            } else {
+                snapshot.description.as_str()
+            };
+            list = list.push(
+                widget::row()
+                    .push(
+                        widget::column()
+                            .push(widget::text(title))
+                            .push(widget::text::caption(format!(
+                                "{} \u{b7} {}",
+                                i18n::relative_time(snapshot.created),
+                                i18n::file_size(snapshot.size)
+                            ))),
+                    )
+                    .push(widget::horizontal_space())
+                    .push(
+                        widget::button::standard("Restore").on_press_maybe((!running).then(|| {
+                            Message::Home(home::Message::OpenDialog(DialogPage::RestoreSnapshot {
+                                bottle: bottle.clone(),
+                                snapshot: snapshot.clone(),
+                            }))
+                        })),
+                    )
+                    .push(
+                        widget::button::icon(icons::get_handle("user-trash-symbolic", 18))
+                            .on_press_maybe((!running).then(|| {
+                                Message::Home(home::Message::OpenDialog(
+                                    DialogPage::DeleteSnapshot {
+                                        bottle: bottle.clone(),
+                                        snapshot: snapshot.clone(),
+                                    },
+                                ))
+                            })),
+                    )
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_xs)
+                    .padding(spacing.space_xs)
+                    .apply(widget::container)
+                    .class(cosmic::style::Container::Card),
+            );
+        }
+
+        widget::column()
+            .push(header)
+            .push_maybe(job)
+            .push(widget::scrollable(list))
+            .spacing(spacing.space_s)
+            .padding(spacing.space_s)
             .apply(widget::container)
+            .width(Length::Fill)
+            .height(Length::Fill)
             .class(cosmic::style::Container::Card)
             .into()
-        })
     }
 
     pub fn update(&mut self, message: Message) -> Task<app::Message> {
-        let tasks = vec![];
+        let mut tasks = vec![];
         match message {
             Message::SetSelected(selected) => {
+                // Refreshing the selected item keeps what the pane shows about it.
+                if self.selected.as_ref().map(Selected::id) != selected.as_ref().map(Selected::id) {
+                    self.bottle_tab = BottleTab::Programs;
+                    self.program_tab = ProgramTab::Program;
+                    self.snapshots.clear();
+                    self.snapshot_job = None;
+                    self.ledger.clear();
+                    self.dependency_job = None;
+                    self.disk_usage = None;
+                    self.scan = None;
+                    self.draft = selected.as_ref().map(Draft::new);
+                }
                 self.selected = selected;
             }
+            Message::SetBottleTab(tab) => {
+                self.bottle_tab = tab;
+            }
+            Message::SetProgramTab(tab) => {
+                self.program_tab = tab;
+            }
+            Message::SetSnapshots(snapshots) => {
+                self.snapshots = snapshots;
+            }
+            Message::SetSnapshotJob(job) => {
+                self.snapshot_job = job;
+            }
+            Message::SetPackages(packages) => {
+                self.packages = packages;
+            }
+            Message::SetLedger(ledger) => {
+                self.ledger = ledger;
+            }
+            Message::SetReleases(releases) => {
+                self.releases = releases;
+            }
+            Message::SetDependencyJob(job) => {
+                self.dependency_job = job;
+            }
+            Message::SetContext(context) => {
+                self.context = context;
+            }
+            Message::SetDiskUsage(size) => {
+                self.disk_usage = size;
+            }
+            Message::SetScan(scan) => {
+                self.scan = scan;
+            }
+            Message::Home(message) => {
+                tasks.push(cosmic::task::message(app::Message::Home(message)))
+            }
+            Message::SetRunners { ids, names } => {
+                self.runner_choices = std::iter::once("Bottle runner".to_string())
+                    .chain(names.iter().cloned())
+                    .collect();
+                self.runner_ids = ids;
+                self.runner_names = names;
+            }
+            Message::EditSettings(edited) => {
+                if let Some(Draft::Bottle { settings, .. }) = &mut self.draft {
+                    *settings = edited;
+                }
+            }
+            Message::SelectRunner(index) => {
+                if let (Some(Draft::Bottle { runner, .. }), Some(id)) =
+                    (&mut self.draft, self.runner_ids.get(index))
+                {
+                    *runner = id.clone();
+                }
+            }
+            Message::SaveSettings => {
+                if let (Some(Selected::Bottle(bottle)), Some(Draft::Bottle { runner, settings })) =
+                    (&self.selected, &mut self.draft)
+                {
+                    settings.env.retain(|(key, _)| !key.trim().is_empty());
+                    let bottle = Bottle {
+                        runner: runner.clone(),
+                        settings: settings.clone(),
+                        ..bottle.clone()
+                    };
+                    tasks.push(cosmic::task::message(app::Message::Home(
+                        home::Message::SaveBottle(bottle),
+                    )));
+                }
+            }
+            Message::EditOverrides(overrides) => {
+                if let Some(Draft::Program(draft)) = &mut self.draft {
+                    *draft = overrides;
+                }
+            }
+            Message::SaveOverrides => {
+                if let (Some(Selected::Program(program)), Some(Draft::Program(overrides))) =
+                    (&self.selected, &mut self.draft)
+                {
+                    overrides.env.retain(|(key, _)| !key.trim().is_empty());
+                    let overrides = overrides.overrides();
+                    // Keep the form in the shape the saved overrides will come back in.
+                    self.draft = Some(Draft::Program(OverridesForm::new(&overrides)));
+                    let program = Program {
+                        overrides,
+                        ..program.clone()
+                    };
+                    tasks.push(cosmic::task::message(app::Message::Home(
+                        home::Message::SaveProgram(program),
+                    )));
+                }
+            }
+            Message::Revert => {
+                self.draft = self.selected.as_ref().map(Draft::new);
+            }
         }
         Task::batch(tasks)
     }
 }
+
+/// An editable page of the details pane, saved or reverted from its header.
+fn form<'a>(
+    title: &'a str,
+    caption: &'a str,
+    save: Option<Message>,
+    fields: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    let spacing = cosmic::theme::active().cosmic().spacing;
+    let header = widget::row()
+        .push(
+            widget::column()
+                .push(widget::text(title).size(18.))
+                .push(widget::text::caption(caption)),
+        )
+        .push(widget::horizontal_space())
+        .push(
+            widget::button::standard("Revert")
+                .on_press_maybe(save.is_some().then_some(Message::Revert)),
+        )
+        .push(widget::button::suggested("Save").on_press_maybe(save))
+        .align_y(Alignment::Center)
+        .spacing(spacing.space_xs);
+
+    widget::column()
+        .push(header)
+        .push(widget::scrollable(fields))
+        .spacing(spacing.space_s)
+        .padding(spacing.space_s)
+        .apply(widget::container)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .class(cosmic::style::Container::Card)
+        .into()
+}
+
+/// A read-only row of a page.
+fn property<'a>(title: &'a str, value: String) -> Element<'a, Message> {
+    widget::row()
+        .push(widget::text(title).width(Length::FillPortion(1)))
+        .push(widget::text::caption(value).width(Length::FillPortion(1)))
+        .align_y(Alignment::Center)
+        .spacing(10.)
+        .into()
+}
+
+/// A titled row of an editable page.
+fn setting<'a>(
+    title: &'a str,
+    caption: &'a str,
+    control: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    widget::row()
+        .push(
+            widget::column()
+                .push(widget::text(title))
+                .push(widget::text::caption(caption))
+                .width(Length::FillPortion(1)),
+        )
+        .push(widget::container(control).width(Length::FillPortion(1)))
+        .align_y(Alignment::Center)
+        .spacing(10.)
+        .into()
+}
+
+/// Editable rows of environment variables, reporting every change through `edit`.
+fn variables<'a>(
+    env: &'a [(String, String)],
+    edit: impl Fn(Vec<(String, String)>) -> Message + Copy + 'a,
+) -> Element<'a, Message> {
+    let mut rows = widget::column().spacing(10.);
+    for (index, (key, value)) in env.iter().enumerate() {
+        rows = rows.push(
+            widget::row()
+                .push(
+                    widget::text_input("Name", key.as_str()).on_input(move |key| {
+                        let mut env = env.to_vec();
+                        env[index].0 = key;
+                        edit(env)
+                    }),
+                )
+                .push(
+                    widget::text_input("Value", value.as_str()).on_input(move |value| {
+                        let mut env = env.to_vec();
+                        env[index].1 = value;
+                        edit(env)
+                    }),
+                )
+                .push(
+                    widget::button::icon(icons::get_handle("user-trash-symbolic", 18)).on_press({
+                        let mut env = env.to_vec();
+                        env.remove(index);
+                        edit(env)
+                    }),
+                )
+                .align_y(Alignment::Center)
+                .spacing(10.),
+        );
+    }
+
+    rows.push(widget::button::standard("Add variable").on_press({
+        let mut env = env.to_vec();
+        env.push(Default::default());
+        edit(env)
+    }))
+    .into()
+}
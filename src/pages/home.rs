@@ -1,35 +1,180 @@
-use bottle::Bottle;
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bottles_core::{
+    bottle::Bottle,
+    component::{self, Catalog, Components, Kind},
+    dependency::{self, Dependencies, Ledger, Manifest},
+    error::{Error, Result},
+    fetch,
+    image_cache::ImageCache,
+    installer::{self, Installer, Recipe, Target, Update},
+    pe,
+    program::{Program, Status},
+    runner::{
+        self,
+        repository::{self, Release, Repository},
+        Runner, Runners,
+    },
+    scanner::{self, Discovered},
+    snapshot::{Snapshot, Snapshots},
+    store::{self, BottleStore},
+    supervisor::{self, Supervisor},
+    uri::Uri,
+};
 use cosmic::{
     app::Task,
-    iced::{alignment::Vertical, Alignment, Length},
+    iced::{
+        alignment::{Horizontal, Vertical},
+        keyboard::{self, key::Named, Key},
+        Alignment, Length, Subscription,
+    },
     widget,
     widget::segmented_button::{Entity, Model, SingleSelect},
     Apply, Element,
 };
-use program::Program;
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
 
-use crate::{app, icons};
+use crate::{
+    app,
+    components::card::Card,
+    dbus, fl, icons,
+    pages::details::{self, DependencyJob, Scan, SnapshotJob},
+    search,
+};
+
+pub use dialog::{
+    DialogPage, Install, InstallStep, ManageRunners, NewBottle, NewBottleStep, ProgramForm,
+    RunnerJob,
+};
+pub use presentation::ProgramExt;
 
-pub mod bottle;
-pub mod program;
+mod dialog;
+mod presentation;
 
 pub struct Home {
     classic_tabs_model: Model<SingleSelect>,
     program_tabs_model: Option<Model<SingleSelect>>,
     bottle_tabs_model: Option<Model<SingleSelect>>,
     query: String,
+    search_id: widget::Id,
     pub selected: Option<Selected>,
     library: Vec<Program>,
     bottles: Vec<Bottle>,
+    /// Bottle names in the same order as `bottles`, used by dropdowns.
+    bottle_names: Vec<String>,
+    /// Runner names in the same order as `runners`, used by dropdowns.
+    runner_names: Vec<String>,
+    /// "New bottle" followed by `bottle_names`, used to pick where to install.
+    target_names: Vec<String>,
+    recipes: Vec<Recipe>,
+    /// Bottles of unfinished installations, by recipe id.
+    unfinished: HashMap<String, String>,
+    /// Bottles with a snapshot operation in progress.
+    snapshot_jobs: HashSet<String>,
+    /// Bottles with a dependency being installed or uninstalled.
+    dependency_jobs: HashSet<String>,
+    manifest: Manifest,
+    /// The bottle being scanned for programs and how to stop the scan.
+    scan: Option<(String, scanner::Cancel)>,
+    store: BottleStore,
+    images: ImageCache,
+    runners: Runners,
+    /// Links to run received before the runners were discovered, `None` once
+    /// they are.
+    pending_runs: Option<Vec<Uri>>,
+    /// Where runner releases are listed.
+    runner_index: String,
+    supervisor: Supervisor,
+    dialog: Option<DialogPage>,
+    /// The last failure, shown above the programs until it is dismissed.
+    error: Option<String>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
     QueryInput(String),
+    FocusSearch,
+    ClearSearch,
     ProgramTabActivated(Entity),
     BottleTabActivated(Entity),
     ClassicTabActivated(Entity),
     Select(Option<Selected>),
+    BottlesLoaded(Result<Vec<Bottle>>),
+    RunnersDiscovered(Runners),
+    ManageRunners,
+    RunnersListed(Result<Vec<Release>>, Vec<String>),
+    InstallRunner(Release),
+    RemoveRunner(String),
+    /// Removes the downloaded runners no bottle uses.
+    PruneRunners,
+    RunnerProgress(f32, String),
+    RunnerDone(Result<()>),
+    CreateBottle(NewBottle),
+    CreationProgress(f32, String),
+    BottleCreated(Result<Bottle>),
+    RenameBottle(String, String),
+    /// Saves the runner and settings of the bottle. The rest is kept as stored,
+    /// since components or a new name may have been saved since it was loaded.
+    SaveBottle(Bottle),
+    BottleSaved(Result<Bottle>),
+    DeleteBottle(String),
+    BottleDeleted(String, Result<()>),
+    LibraryLoaded(Result<Vec<Program>>),
+    IconExtracted(String, PathBuf),
+    ThumbnailLoaded(String, PathBuf),
+    SaveProgram(Program),
+    ProgramSaved(Result<Program>),
+    RemoveProgram(Program),
+    EditProgram(Program),
+    /// Adds a program to the applications menu, or removes it from there.
+    SetDesktopEntry(Program, bool),
+    ProgramRemoved(Program, Result<()>),
+    Launch(Program),
+    Launched(String, Result<u32>),
+    Stop(Program),
+    /// Whether the supervisor was watching the program when it was stopped.
+    Stopped(String, Result<bool>),
+    StopBottle(Bottle),
+    BottleStopped(String, Result<()>),
+    SnapshotsLoaded(String, Result<Vec<Snapshot>>),
+    CreateSnapshot(Bottle, String),
+    RestoreSnapshot(Bottle, Snapshot),
+    DeleteSnapshot(Bottle, Snapshot),
+    SnapshotProgress(String, f32, String),
+    /// The bottle, whether its prefix was replaced, and how the operation went.
+    SnapshotDone(Bottle, bool, Result<()>),
+    ManifestLoaded(Manifest),
+    LedgerLoaded(String, Result<Ledger>),
+    InstallDependency(Bottle, String),
+    UninstallDependency(Bottle, String),
+    DependencyProgress(String, f32, String),
+    DependencyDone(Bottle, Result<()>),
+    CatalogLoaded(Catalog),
+    InstallComponent(Bottle, component::Release),
+    UninstallComponent(Bottle, Kind),
+    ComponentDone(Bottle, Result<Bottle>),
+    DiskUsage(String, Result<u64>),
+    RecipesLoaded(Vec<Recipe>, HashMap<String, String>),
+    InstallRecipe(Install),
+    InstallProgress(Update),
+    Installed(Result<Bottle>),
+    ScanBottle(Bottle),
+    CancelScan,
+    Scanned(String, Result<Vec<Discovered>>),
+    AddPrograms(String, Vec<Discovered>),
+    Process(supervisor::Event),
+    /// Follows a `bottles-next://` link.
+    OpenUri(Uri),
+    Opened(Result<Selected>),
+    OpenDialog(DialogPage),
+    UpdateDialog(DialogPage),
+    CloseDialog,
+    DismissError,
 }
 
 #[derive(Clone, Debug)]
@@ -38,17 +183,29 @@ pub enum Selected {
     Program(Program),
 }
 
+impl Selected {
+    pub fn id(&self) -> &str {
+        match self {
+            Selected::Bottle(bottle) => &bottle.id,
+            Selected::Program(program) => &program.id,
+        }
+    }
+}
+
 pub enum ClassicTab {
     Bottles,
     Library,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BottleTab {
     Programs,
     Settings,
+    Dependencies,
     Snapshots,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ProgramTab {
     Program,
     Advanced,
@@ -56,6 +213,7 @@ pub enum ProgramTab {
 
 impl Home {
     pub fn new() -> Self {
+        let store = BottleStore::default();
         Self {
             classic_tabs_model: Model::builder()
                 .insert(move |b| b.text("Bottles").data(ClassicTab::Bottles).activate())
@@ -64,141 +222,1350 @@ impl Home {
             program_tabs_model: None,
             bottle_tabs_model: None,
             query: String::new(),
+            search_id: widget::Id::unique(),
             selected: None,
-            library: vec![
-                Program::new(
-                    "Assassin's Creed Valhalla",
-                    "Running...",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/assassins-creed-valhalla.jpg")
-                            .to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Battle.net",
-                    "1h ago",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/blizzard.jpg").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Cyberpunk 2077",
-                    "4h ago",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/cyberpunk.jpg").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Steam",
-                    "6h ago",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/steam.jpg").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Need for Speed Unbound",
-                    "12 days ago",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/nfs-unbound.jpg").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Overwatch 2",
-                    "Last week",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/overwatch-2.jpg").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Need for Speed Heat",
-                    "Last week",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/nfs-heat.jpg").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Apex Legends",
-                    "2 weeks ago",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/apex-legends.webp").to_vec(),
-                    ),
-                ),
-                Program::new(
-                    "Warframe",
-                    "2 weeks ago",
-                    widget::image::Handle::from_bytes(
-                        include_bytes!("../../resources/images/warframe.jpg").to_vec(),
-                    ),
-                ),
-            ],
-            bottles: vec![
-                Bottle::new(
-                    "Gaming paradise",
-                    "Gaming",
-                    icons::get_handle("xbox-controller-symbolic", 18),
-                ),
-                Bottle::new(
-                    "Windows development",
-                    "Software",
-                    icons::get_handle("build-alt-symbolic", 18),
-                ),
-                Bottle::new(
-                    "Game engines",
-                    "Gaming",
-                    icons::get_handle("xbox-controller-symbolic", 18),
-                ),
-                Bottle::new(
-                    "Weird experiments",
-                    "Custom",
-                    icons::get_handle("issue-symbolic", 18),
-                ),
-            ],
+            library: vec![],
+            bottles: vec![],
+            bottle_names: vec![],
+            runner_names: vec![],
+            target_names: vec![],
+            recipes: vec![],
+            unfinished: HashMap::new(),
+            snapshot_jobs: HashSet::new(),
+            dependency_jobs: HashSet::new(),
+            manifest: Manifest::default(),
+            scan: None,
+            images: ImageCache::default(),
+            runners: Runners::default(),
+            pending_runs: Some(vec![]),
+            runner_index: repository::index_url(None),
+            supervisor: Supervisor::new(store.clone()),
+            store,
+            dialog: None,
+            error: None,
         }
     }
 
+    /// Loads the bottles, recipes and dependencies from disk and discovers
+    /// runners in the background.
+    pub fn load(&self) -> Task<app::Message> {
+        let store = self.store.clone();
+        Task::batch(vec![
+            perform(async move { Message::BottlesLoaded(store.load().await) }),
+            self.discover_runners(),
+            self.load_recipes(),
+            perform(async { Message::ManifestLoaded(Manifest::load().await) }),
+            perform(async { Message::CatalogLoaded(Catalog::load().await) }),
+        ])
+    }
+
+    fn load_recipes(&self) -> Task<app::Message> {
+        let installer = self.installer();
+        perform(async move {
+            let recipes = installer::recipes().await;
+            let mut unfinished = HashMap::new();
+            for recipe in &recipes {
+                if let Some(bottle) = installer.unfinished(recipe).await {
+                    unfinished.insert(recipe.id.clone(), bottle);
+                }
+            }
+            Message::RecipesLoaded(recipes, unfinished)
+        })
+    }
+
+    fn installer(&self) -> Installer {
+        Installer::new(
+            self.store.clone(),
+            self.runners.clone(),
+            Arc::new(fetch::Http::default()),
+        )
+    }
+
+    pub fn set_runner_index(&mut self, configured: Option<&str>) {
+        self.runner_index = repository::index_url(configured);
+    }
+
+    fn discover_runners(&self) -> Task<app::Message> {
+        perform(async move {
+            Message::RunnersDiscovered(Runners::discover(&store::data_dir().join("runners")).await)
+        })
+    }
+
+    fn repository(&self) -> Repository {
+        Repository::new(self.runner_index.clone(), Arc::new(fetch::Http::default()))
+    }
+
+    /// Lists the runners of the index and the ones already downloaded.
+    fn load_repository(&self) -> Task<app::Message> {
+        let repository = self.repository();
+        perform(async move {
+            let installed = repository
+                .installed()
+                .await
+                .iter()
+                .map(|runner| runner.id().to_string())
+                .collect();
+            Message::RunnersListed(repository.releases().await, installed)
+        })
+    }
+
+    /// Runs a change to the downloaded runners, showing its progress in the
+    /// runners dialog.
+    fn runner_job<F>(
+        &mut self,
+        status: &str,
+        job: impl FnOnce(Repository, Box<dyn Fn(f32, String) + Send + Sync>) -> F + Send + 'static,
+    ) -> Task<app::Message>
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        match &mut self.dialog {
+            Some(DialogPage::Runners(manager))
+                if !matches!(manager.job, Some(RunnerJob::Running { .. })) =>
+            {
+                manager.job = Some(RunnerJob::Running {
+                    progress: 0.0,
+                    status: status.to_string(),
+                });
+            }
+            _ => return Task::none(),
+        }
+
+        let repository = self.repository();
+        run(move |output| async move {
+            let progress = {
+                let output = output.clone();
+                Box::new(move |progress, status| {
+                    let _ = output.send(Message::RunnerProgress(progress, status));
+                })
+            };
+            let result = job(repository, progress).await;
+            let _ = output.send(Message::RunnerDone(result));
+        })
+    }
+
+    fn load_library(&self) -> Task<app::Message> {
+        let store = self.store.clone();
+        let bottles = self.bottles.clone();
+        perform(async move { Message::LibraryLoaded(store.library(&bottles).await) })
+    }
+
+    /// Creates the bottle described by the wizard and initializes its prefix,
+    /// reporting progress back to the wizard.
+    fn create_bottle(&mut self, wizard: NewBottle) -> Task<app::Message> {
+        let runner = wizard
+            .runner
+            .and_then(|index| self.runners.iter().nth(index))
+            .cloned();
+        let Some(runner) = runner else {
+            // Runners may have been removed since the wizard was opened.
+            self.dialog = Some(DialogPage::NewBottle(NewBottle {
+                step: NewBottleStep::Failed("no runner is installed".to_string()),
+                ..wizard
+            }));
+            return Task::none();
+        };
+
+        let mut bottle = Bottle::new(wizard.name.trim(), wizard.environment, runner.id());
+        bottle.icon = wizard.icon.map(str::to_string);
+        let store = self.store.clone();
+
+        self.dialog = Some(DialogPage::NewBottle(NewBottle {
+            step: NewBottleStep::Creating {
+                progress: 0.0,
+                status: "Creating the bottle...".to_string(),
+            },
+            ..wizard
+        }));
+
+        run(move |output| async move {
+            let bottle = match store.create(bottle).await {
+                Ok(bottle) => bottle,
+                Err(why) => {
+                    let _ = output.send(Message::BottleCreated(Err(why)));
+                    return;
+                }
+            };
+
+            let _ = output.send(Message::CreationProgress(
+                0.3,
+                "Initializing the Wine prefix...".to_string(),
+            ));
+            if let Err(why) = runner::init_prefix(&*runner, &bottle.prefix()).await {
+                // Don't leave a bottle without a working prefix behind.
+                if let Err(why) = store.delete(&bottle.id).await {
+                    eprintln!("failed to clean up bottle {}: {why}", bottle.name);
+                }
+                let _ = output.send(Message::BottleCreated(Err(why)));
+                return;
+            }
+
+            let _ = output.send(Message::CreationProgress(1.0, "Done".to_string()));
+            let _ = output.send(Message::BottleCreated(Ok(bottle)));
+        })
+    }
+
+    /// Installs the recipe chosen in the wizard, reporting progress back to the
+    /// wizard.
+    fn install_recipe(&mut self, wizard: Install) -> Task<app::Message> {
+        let Some(recipe) = wizard
+            .recipe
+            .and_then(|index| self.recipes.get(index))
+            .cloned()
+        else {
+            return Task::none();
+        };
+        let existing = wizard
+            .bottle
+            .checked_sub(1)
+            .and_then(|index| self.bottles.get(index));
+        let mut resumed = None;
+        let target = if let Some(bottle) = self.unfinished.get(&recipe.id) {
+            // The unfinished installation carries on in its own bottle, whichever
+            // one was chosen on the way here.
+            if wizard.step == InstallStep::Bottle
+                && existing.is_none_or(|existing| existing.id != *bottle)
+            {
+                resumed = Some(
+                    self.bottle(bottle)
+                        .map_or(bottle.clone(), |b| b.name.clone()),
+                );
+            }
+            Target::Existing(bottle.clone())
+        } else if let Some(bottle) = existing {
+            Target::Existing(bottle.id.clone())
+        } else {
+            let runner = wizard
+                .runner
+                .and_then(|index| self.runners.iter().nth(index));
+            let Some(runner) = runner else {
+                // Runners may have been removed since the wizard was opened.
+                self.dialog = Some(DialogPage::Install(Install {
+                    step: InstallStep::Failed("no runner is installed".to_string()),
+                    ..wizard
+                }));
+                return Task::none();
+            };
+            Target::New {
+                name: recipe.name.clone(),
+                runner: runner.id().to_string(),
+            }
+        };
+
+        let installer = self.installer();
+        self.dialog = Some(DialogPage::Install(Install {
+            step: InstallStep::Installing {
+                progress: 0.0,
+                status: "Preparing the bottle...".to_string(),
+            },
+            resumed: resumed.or(wizard.resumed.clone()),
+            ..wizard
+        }));
+
+        run(move |output| async move {
+            let progress = output.clone();
+            let result = installer
+                .install(&recipe, target, &HashMap::new(), move |update| {
+                    let _ = progress.send(Message::InstallProgress(update));
+                })
+                .await;
+            let _ = output.send(Message::Installed(result));
+        })
+    }
+
+    /// Looks for the programs installed in `bottle`, replacing any running scan.
+    fn scan_bottle(&mut self, bottle: &Bottle) -> Task<app::Message> {
+        self.cancel_scan();
+        let cancel = scanner::Cancel::default();
+        self.scan = Some((bottle.id.clone(), cancel.clone()));
+
+        let id = bottle.id.clone();
+        let prefix = bottle.prefix();
+        Task::batch([
+            to_details(details::Message::SetScan(Some(Scan::Running))),
+            perform(async move { Message::Scanned(id, scanner::scan(&prefix, cancel).await) }),
+        ])
+    }
+
+    fn cancel_scan(&mut self) {
+        if let Some((_, cancel)) = self.scan.take() {
+            cancel.cancel();
+        }
+    }
+
+    /// Loads the thumbnails of the covers of `programs`, one after the other.
+    fn load_thumbnails<'a>(
+        &self,
+        programs: impl IntoIterator<Item = &'a Program>,
+    ) -> Task<app::Message> {
+        let covers: Vec<(String, PathBuf)> = programs
+            .into_iter()
+            .filter_map(|program| Some((program.id.clone(), program.cover.clone()?)))
+            .collect();
+        if covers.is_empty() {
+            return Task::none();
+        }
+        let images = self.images.clone();
+        run(move |output| async move {
+            for (id, cover) in covers {
+                match images.thumbnail(&cover).await {
+                    Ok(thumbnail) => {
+                        let _ = output.send(Message::ThumbnailLoaded(id, thumbnail));
+                    }
+                    Err(why) => eprintln!("failed to load cover {}: {why}", cover.display()),
+                }
+            }
+        })
+    }
+
+    /// Extracts the icons of the programs without a cover, one after the other.
+    fn load_icons<'a>(
+        &self,
+        programs: impl IntoIterator<Item = &'a Program>,
+    ) -> Task<app::Message> {
+        let executables: Vec<(String, PathBuf)> = programs
+            .into_iter()
+            .filter(|program| program.cover.is_none() && program.icon.is_none())
+            .filter_map(|program| {
+                let bottle = self.bottle(&program.bottle)?;
+                Some((program.id.clone(), program.host_executable(bottle)?))
+            })
+            .collect();
+        if executables.is_empty() {
+            return Task::none();
+        }
+        run(move |output| async move {
+            for (id, executable) in executables {
+                match pe::icon(&executable).await {
+                    Ok(icon) => {
+                        let _ = output.send(Message::IconExtracted(id, icon));
+                    }
+                    Err(why) => eprintln!("no icon for {}: {why}", executable.display()),
+                }
+            }
+        })
+    }
+
+    fn load_snapshots(&self, bottle: &Bottle) -> Task<app::Message> {
+        let id = bottle.id.clone();
+        let snapshots = Snapshots::new(bottle);
+        perform(async move { Message::SnapshotsLoaded(id, snapshots.list().await) })
+    }
+
+    fn load_ledger(&self, bottle: &Bottle) -> Task<app::Message> {
+        let bottle = bottle.clone();
+        perform(async move {
+            Message::LedgerLoaded(bottle.id.clone(), dependency::ledger(&bottle).await)
+        })
+    }
+
+    /// Installs or uninstalls the package `id` in `bottle`, one package at a time
+    /// per bottle, showing the progress in the details pane.
+    fn dependency_job(&mut self, bottle: Bottle, id: String, install: bool) -> Task<app::Message> {
+        let Some(runner) = self.runners.get(&bottle.runner) else {
+            let why = Error::RunnerNotFound(bottle.runner.clone());
+            return to_details(details::Message::SetDependencyJob(Some(
+                DependencyJob::Failed(why.to_string()),
+            )));
+        };
+        if !self.dependency_jobs.insert(bottle.id.clone()) {
+            return Task::none();
+        }
+
+        let dependencies =
+            Dependencies::new(self.manifest.clone(), Arc::new(fetch::Http::default()));
+        let name = self
+            .manifest
+            .get(&id)
+            .map_or(id.clone(), |package| package.name.clone());
+        run(move |output| async move {
+            let result = if install {
+                let progress = {
+                    let output = output.clone();
+                    let bottle = bottle.id.clone();
+                    move |progress, status| {
+                        let _ = output.send(Message::DependencyProgress(
+                            bottle.clone(),
+                            progress,
+                            status,
+                        ));
+                    }
+                };
+                dependencies.install(&*runner, &bottle, &id, progress).await
+            } else {
+                let _ = output.send(Message::DependencyProgress(
+                    bottle.id.clone(),
+                    0.0,
+                    format!("Uninstalling {name}..."),
+                ));
+                dependencies.uninstall(&*runner, &bottle, &id).await
+            };
+            let _ = output.send(Message::DependencyDone(bottle, result));
+        })
+    }
+
+    /// Installs `release` in `bottle`, or uninstalls the component `kind` when
+    /// there is no release, sharing the one job per bottle of dependencies.
+    fn component_job(
+        &mut self,
+        bottle: Bottle,
+        kind: Kind,
+        release: Option<component::Release>,
+    ) -> Task<app::Message> {
+        let Some(runner) = self.runners.get(&bottle.runner) else {
+            let why = Error::RunnerNotFound(bottle.runner.clone());
+            return to_details(details::Message::SetDependencyJob(Some(
+                DependencyJob::Failed(why.to_string()),
+            )));
+        };
+        if !self.dependency_jobs.insert(bottle.id.clone()) {
+            return Task::none();
+        }
+
+        let store = self.store.clone();
+        let components = Components::new(Arc::new(fetch::Http::default()));
+        run(move |output| async move {
+            let result = match release {
+                Some(release) => {
+                    let progress = {
+                        let output = output.clone();
+                        let bottle = bottle.id.clone();
+                        move |progress, status| {
+                            let _ = output.send(Message::DependencyProgress(
+                                bottle.clone(),
+                                progress,
+                                status,
+                            ));
+                        }
+                    };
+                    components
+                        .install(&store, &*runner, &bottle, &release, progress)
+                        .await
+                }
+                None => {
+                    let _ = output.send(Message::DependencyProgress(
+                        bottle.id.clone(),
+                        0.0,
+                        format!("Uninstalling {}...", kind.title()),
+                    ));
+                    components.uninstall(&store, &*runner, &bottle, kind).await
+                }
+            };
+            let _ = output.send(Message::ComponentDone(bottle, result));
+        })
+    }
+
+    /// Runs a snapshot operation on `bottle`, one at a time per bottle, showing
+    /// its progress in the details pane. Bottles whose prefix the operation
+    /// `replaces` are scanned for programs again afterwards.
+    fn snapshot_job<F>(
+        &mut self,
+        bottle: Bottle,
+        status: &str,
+        replaces: bool,
+        job: impl FnOnce(Bottle, Box<dyn Fn(f32) + Send>) -> F + Send + 'static,
+    ) -> Task<app::Message>
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.dialog = None;
+        if !self.snapshot_jobs.insert(bottle.id.clone()) {
+            return Task::none();
+        }
+
+        let status = status.to_string();
+        run(move |output| async move {
+            let id = bottle.id.clone();
+            let _ = output.send(Message::SnapshotProgress(id.clone(), 0.0, status.clone()));
+            let progress = {
+                let output = output.clone();
+                Box::new(move |progress| {
+                    let _ = output.send(Message::SnapshotProgress(
+                        id.clone(),
+                        progress,
+                        status.clone(),
+                    ));
+                })
+            };
+            let result = job(bottle.clone(), progress).await;
+            let _ = output.send(Message::SnapshotDone(bottle, replaces, result));
+        })
+    }
+
+    fn refresh_bottle_names(&mut self) {
+        self.bottle_names = self.bottles.iter().map(|b| b.name.clone()).collect();
+        self.target_names = std::iter::once("New bottle".to_string())
+            .chain(self.bottle_names.iter().cloned())
+            .collect();
+    }
+
+    fn is_selected_bottle(&self, id: &str) -> bool {
+        matches!(&self.selected, Some(Selected::Bottle(bottle)) if bottle.id == id)
+    }
+
+    fn is_selected_program(&self, id: &str) -> bool {
+        matches!(&self.selected, Some(Selected::Program(program)) if program.id == id)
+    }
+
+    fn program(&self, id: &str) -> Option<&Program> {
+        self.library.iter().find(|program| program.id == id)
+    }
+
+    fn set_status(&mut self, id: &str, status: Status) -> Task<app::Message> {
+        self.update_program(id, |program| program.status = status)
+    }
+
+    /// Applies `update` to a program of the library, refreshing the details pane if
+    /// it shows that program or its bottle.
+    fn update_program(&mut self, id: &str, update: impl Fn(&mut Program)) -> Task<app::Message> {
+        let Some(program) = self.library.iter_mut().find(|program| program.id == id) else {
+            return Task::none();
+        };
+        update(program);
+        let bottle = program.bottle.clone();
+        match &mut self.selected {
+            Some(Selected::Program(program)) if program.id == id => {
+                update(program);
+                self.refresh_details()
+            }
+            Some(Selected::Bottle(selected)) if selected.id == bottle => self.refresh_details(),
+            _ => Task::none(),
+        }
+    }
+
+    /// Sends the selection and what surrounds it to the details pane.
+    fn refresh_details(&self) -> Task<app::Message> {
+        Task::batch([
+            to_details(details::Message::SetSelected(self.selected.clone())),
+            to_details(details::Message::SetContext(self.details_context())),
+        ])
+    }
+
+    fn details_context(&self) -> details::Context {
+        let (bottle, runner) = match &self.selected {
+            Some(Selected::Bottle(bottle)) => (Some(bottle), None),
+            Some(Selected::Program(program)) => (
+                self.bottle(&program.bottle),
+                program.overrides.runner.as_ref(),
+            ),
+            None => return details::Context::default(),
+        };
+        let programs = match &self.selected {
+            Some(Selected::Bottle(bottle)) => self
+                .library
+                .iter()
+                .filter(|program| program.bottle == bottle.id)
+                .cloned()
+                .collect(),
+            _ => vec![],
+        };
+        details::Context {
+            runner: runner
+                .or(bottle.map(|bottle| &bottle.runner))
+                .map(|id| self.runner_name(id)),
+            bottle: bottle.cloned(),
+            programs,
+        }
+    }
+
+    /// The display name of a runner, which may no longer be installed.
+    fn runner_name(&self, id: &str) -> String {
+        self.runners
+            .iter()
+            .position(|runner| runner.id() == id)
+            .and_then(|index| self.runner_names.get(index))
+            .cloned()
+            .unwrap_or_else(|| format!("{id} (not installed)"))
+    }
+
+    fn load_disk_usage(&self, bottle: &Bottle) -> Task<app::Message> {
+        let store = self.store.clone();
+        let id = bottle.id.clone();
+        perform(async move {
+            let result = store.disk_usage(&id).await;
+            Message::DiskUsage(id, result)
+        })
+    }
+
+    /// Listens to the processes started from the application and to the search
+    /// keyboard shortcuts, and exports the D-Bus interface.
+    pub fn subscription(&self) -> Subscription<Message> {
+        let serve = dbus::serve(self.store.clone(), self.supervisor.clone());
+        Subscription::batch(vec![
+            Subscription::run_with_id(
+                TypeId::of::<Supervisor>(),
+                self.supervisor.events().map(Message::Process),
+            ),
+            Subscription::run_with_id(
+                TypeId::of::<dbus::Library>(),
+                futures_util::stream::once(serve).filter_map(|result| async move {
+                    if let Err(why) = result {
+                        eprintln!("failed to export the D-Bus interface: {why}");
+                    }
+                    None
+                }),
+            ),
+            keyboard::on_key_press(|key, modifiers| match key.as_ref() {
+                Key::Character("f") if modifiers.control() => Some(Message::FocusSearch),
+                Key::Named(Named::Escape) => Some(Message::ClearSearch),
+                _ => None,
+            }),
+        ])
+    }
+
+    /// How well a program matches the search query, `None` if it doesn't.
+    fn program_score(&self, program: &Program) -> Option<u32> {
+        let caption = program.caption();
+        let bottle = self
+            .bottle(&program.bottle)
+            .map(|bottle| bottle.name.as_str());
+        search::best(
+            &self.query,
+            [program.title.as_str(), caption.as_str()]
+                .into_iter()
+                .chain(bottle),
+        )
+    }
+
+    /// How well a bottle matches the search query, `None` if it doesn't.
+    fn bottle_score(&self, bottle: &Bottle) -> Option<u32> {
+        search::best(
+            &self.query,
+            [bottle.name.as_str(), bottle.environment.title()],
+        )
+    }
+
+    fn bottle(&self, id: &str) -> Option<&Bottle> {
+        self.bottles.iter().find(|bottle| bottle.id == id)
+    }
+
+    /// Tells the user that `what` failed because of `why`, replacing the failure
+    /// shown before.
+    fn report(&mut self, what: &str, why: impl std::fmt::Display) {
+        let error = format!("{what}: {why}");
+        eprintln!("{error}");
+        self.error = Some(error);
+    }
+
     pub fn update(&mut self, message: Message) -> Task<app::Message> {
         let mut tasks = vec![];
         match message {
+            Message::BottlesLoaded(result) => match result {
+                Ok(bottles) => {
+                    self.bottles = bottles;
+                    self.refresh_bottle_names();
+                    tasks.push(self.load_library());
+                }
+                Err(why) => self.report("Couldn't load the bottles", why),
+            },
+            Message::RunnersDiscovered(runners) => {
+                self.runner_names = runners
+                    .iter()
+                    .map(|runner| match runner.version() {
+                        Some(version) => format!("{} ({version})", runner.name()),
+                        None => runner.name().to_string(),
+                    })
+                    .collect();
+                tasks.push(to_details(details::Message::SetRunners {
+                    ids: runners
+                        .iter()
+                        .map(|runner| runner.id().to_string())
+                        .collect(),
+                    names: self.runner_names.clone(),
+                }));
+                self.runners = runners;
+                tasks.push(self.refresh_details());
+                for uri in self.pending_runs.take().unwrap_or_default() {
+                    tasks.push(self.update(Message::OpenUri(uri)));
+                }
+            }
+            Message::ManageRunners => {
+                self.dialog = Some(DialogPage::Runners(ManageRunners::default()));
+                tasks.push(self.load_repository());
+            }
+            Message::RunnersListed(releases, installed) => {
+                if let Some(DialogPage::Runners(manager)) = &mut self.dialog {
+                    manager.installed = installed;
+                    match releases {
+                        Ok(releases) => manager.releases = Some(releases),
+                        Err(why) => {
+                            manager.releases = Some(vec![]);
+                            if manager.job.is_none() {
+                                manager.job = Some(RunnerJob::Failed(format!(
+                                    "The runner index couldn't be loaded: {why}"
+                                )));
+                            }
+                        }
+                    }
+                }
+            }
+            Message::InstallRunner(release) => {
+                let status = format!("Downloading {}...", release.name);
+                tasks.push(
+                    self.runner_job(&status, move |repository, progress| async move {
+                        repository.install(&release, progress).await
+                    }),
+                );
+            }
+            Message::RemoveRunner(id) => {
+                let store = self.store.clone();
+                let status = format!("Removing {id}...");
+                tasks.push(self.runner_job(&status, move |repository, _| async move {
+                    repository.remove(&store, &id).await
+                }));
+            }
+            Message::PruneRunners => {
+                let store = self.store.clone();
+                tasks.push(self.runner_job(
+                    "Removing unused runners...",
+                    move |repository, _| async move { repository.prune(&store).await.map(|_| ()) },
+                ));
+            }
+            Message::RunnerProgress(progress, status) => {
+                if let Some(DialogPage::Runners(manager)) = &mut self.dialog {
+                    manager.job = Some(RunnerJob::Running { progress, status });
+                }
+            }
+            Message::RunnerDone(result) => {
+                if let Some(DialogPage::Runners(manager)) = &mut self.dialog {
+                    manager.job = result.err().map(|why| RunnerJob::Failed(why.to_string()));
+                    tasks.push(self.load_repository());
+                }
+                tasks.push(self.discover_runners());
+            }
+            Message::CreateBottle(wizard) => tasks.push(self.create_bottle(wizard)),
+            Message::CreationProgress(progress, status) => {
+                if let Some(DialogPage::NewBottle(wizard)) = &mut self.dialog {
+                    wizard.step = NewBottleStep::Creating { progress, status };
+                }
+            }
+            Message::BottleCreated(result) => match result {
+                Ok(bottle) => {
+                    if let Some(DialogPage::NewBottle(_)) = self.dialog {
+                        self.dialog = None;
+                    }
+                    self.bottles.push(bottle);
+                    self.refresh_bottle_names();
+                }
+                Err(why) => match &mut self.dialog {
+                    Some(DialogPage::NewBottle(wizard)) => {
+                        wizard.step = NewBottleStep::Failed(why.to_string());
+                    }
+                    _ => self.report("Couldn't create the bottle", why),
+                },
+            },
+            Message::RenameBottle(id, name) => {
+                self.dialog = None;
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    Message::BottleSaved(store.rename(&id, name).await)
+                }));
+            }
+            Message::SaveBottle(edited) => {
+                let runner = self.runners.get(&edited.runner);
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    let result = async {
+                        let mut bottle = store.get(&edited.id).await?;
+                        if bottle.settings.windows != edited.settings.windows {
+                            let runner = runner
+                                .ok_or_else(|| Error::RunnerNotFound(edited.runner.clone()))?;
+                            runner::set_windows_version(
+                                &*runner,
+                                &bottle.prefix(),
+                                edited.settings.windows,
+                            )
+                            .await?;
+                        }
+                        bottle.runner = edited.runner;
+                        bottle.settings = edited.settings;
+                        store.save(&bottle).await?;
+                        Ok::<_, Error>(bottle)
+                    };
+                    Message::BottleSaved(result.await)
+                }));
+            }
+            Message::BottleSaved(result) => match result {
+                Ok(bottle) => {
+                    if self.is_selected_bottle(&bottle.id) {
+                        self.selected = Some(Selected::Bottle(bottle.clone()));
+                        tasks.push(self.refresh_details());
+                    }
+                    if let Some(existing) = self.bottles.iter_mut().find(|b| b.id == bottle.id) {
+                        *existing = bottle;
+                    }
+                    self.refresh_bottle_names();
+                }
+                Err(why) => self.report("Couldn't save the bottle", why),
+            },
+            Message::DeleteBottle(id) => {
+                self.dialog = None;
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    let result = store.delete(&id).await;
+                    Message::BottleDeleted(id, result)
+                }));
+            }
+            Message::BottleDeleted(id, result) => match result {
+                Ok(()) => {
+                    self.bottles.retain(|bottle| bottle.id != id);
+                    self.library.retain(|program| program.bottle != id);
+                    self.refresh_bottle_names();
+                    if self.is_selected_bottle(&id) {
+                        tasks.push(self.update(Message::Select(None)));
+                    }
+                }
+                Err(why) => self.report("Couldn't delete the bottle", why),
+            },
+            Message::LibraryLoaded(result) => match result {
+                Ok(library) => {
+                    self.library = library;
+                    tasks.push(self.load_thumbnails(&self.library));
+                    tasks.push(self.load_icons(&self.library));
+                    tasks.push(self.refresh_details());
+                }
+                Err(why) => self.report("Couldn't load the library", why),
+            },
+            Message::IconExtracted(id, icon) => {
+                tasks.push(self.update_program(&id, |program| program.icon = Some(icon.clone())))
+            }
+            Message::ThumbnailLoaded(id, thumbnail) => tasks.push(
+                self.update_program(&id, |program| program.thumbnail = Some(thumbnail.clone())),
+            ),
+            Message::SaveProgram(program) => {
+                self.dialog = None;
+                let store = self.store.clone();
+                let bottle = self.bottle(&program.bottle).cloned();
+                tasks.push(perform(async move {
+                    // The title or the executable of the entry may have changed.
+                    if let Some(bottle) = bottle.filter(|_| program.desktop_entry) {
+                        if let Err(why) = store.desktop().install(&bottle, &program).await {
+                            eprintln!(
+                                "failed to update the menu entry of {}: {why}",
+                                program.title
+                            );
+                        }
+                    }
+                    Message::ProgramSaved(store.save_program(program).await)
+                }));
+            }
+            Message::ProgramSaved(result) => match result {
+                Ok(program) => {
+                    let program = match self.library.iter_mut().find(|p| p.id == program.id) {
+                        Some(existing) => {
+                            *existing = Program {
+                                status: existing.status,
+                                ..program
+                            };
+                            existing.clone()
+                        }
+                        None => {
+                            self.library.push(program.clone());
+                            program
+                        }
+                    };
+                    // The cover or the executable may have changed, both are cached otherwise.
+                    tasks.push(self.load_thumbnails([&program]));
+                    tasks.push(self.load_icons([&program]));
+                    if self.is_selected_program(&program.id) {
+                        // Stay on the current tab, only its title may have changed.
+                        if let Some(model) = &mut self.program_tabs_model {
+                            let entity = model.iter().find(|entity| {
+                                matches!(
+                                    model.data::<ProgramTab>(*entity),
+                                    Some(ProgramTab::Program)
+                                )
+                            });
+                            if let Some(entity) = entity {
+                                model.text_set(entity, program.title.clone());
+                            }
+                        }
+                        self.selected = Some(Selected::Program(program));
+                        tasks.push(self.refresh_details());
+                    } else if self.is_selected_bottle(&program.bottle) {
+                        tasks.push(self.refresh_details());
+                    }
+                }
+                Err(why) => self.report("Couldn't save the program", why),
+            },
+            Message::RemoveProgram(program) => {
+                self.dialog = None;
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    let result = store.remove_program(&program.bottle, &program.id).await;
+                    Message::ProgramRemoved(program, result)
+                }));
+            }
+            Message::ProgramRemoved(program, result) => match result {
+                Ok(()) => {
+                    self.library.retain(|p| p.id != program.id);
+                    if self.is_selected_program(&program.id) {
+                        tasks.push(self.update(Message::Select(None)));
+                    } else if self.is_selected_bottle(&program.bottle) {
+                        tasks.push(self.refresh_details());
+                    }
+                }
+                Err(why) => self.report("Couldn't remove the program", why),
+            },
+            Message::SetDesktopEntry(mut program, enabled) => {
+                let Some(bottle) = self.bottle(&program.bottle).cloned() else {
+                    let why = Error::BottleNotFound(program.bottle.clone());
+                    self.report("Couldn't update the applications menu", why);
+                    return Task::none();
+                };
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    let result = if enabled {
+                        store.desktop().install(&bottle, &program).await
+                    } else {
+                        store.desktop().remove(&bottle.id, &program.id).await
+                    };
+                    Message::ProgramSaved(match result {
+                        Ok(()) => {
+                            program.desktop_entry = enabled;
+                            store.save_program(program).await
+                        }
+                        Err(why) => Err(why),
+                    })
+                }));
+            }
+            Message::Launch(program) => {
+                let Some(bottle) = self.bottle(&program.bottle).cloned() else {
+                    let why = Error::BottleNotFound(program.bottle.clone());
+                    self.report(&format!("Couldn't launch {}", program.title), why);
+                    return Task::none();
+                };
+                let runner = self.runners.for_program(&bottle, &program);
+                let supervisor = self.supervisor.clone();
+                tasks.push(perform(async move {
+                    let result = match runner {
+                        Ok(runner) => supervisor.launch(&*runner, &bottle, &program).await,
+                        Err(why) => Err(why),
+                    };
+                    Message::Launched(program.id, result)
+                }));
+            }
+            Message::OpenUri(Uri::Run { bottle, program }) => {
+                // The link opened the window, which is still looking for runners.
+                if let Some(pending) = &mut self.pending_runs {
+                    pending.push(Uri::Run { bottle, program });
+                    return Task::none();
+                }
+                let store = self.store.clone();
+                let runners = self.runners.clone();
+                let supervisor = self.supervisor.clone();
+                tasks.push(perform(async move {
+                    let result = async {
+                        let (bottle, program) = find_program(&store, &bottle, &program).await?;
+                        let runner = runners.for_program(&bottle, &program)?;
+                        supervisor.launch(&*runner, &bottle, &program).await
+                    };
+                    Message::Launched(program.clone(), result.await)
+                }));
+            }
+            Message::OpenUri(Uri::Open { bottle, program }) => {
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    Message::Opened(match program {
+                        Some(program) => find_program(&store, &bottle, &program)
+                            .await
+                            .map(|(_, program)| Selected::Program(program)),
+                        None => store.get(&bottle).await.map(Selected::Bottle),
+                    })
+                }));
+            }
+            Message::Opened(result) => {
+                // The loaded copies carry the icons and state the stored ones lack.
+                let selected = match result {
+                    Ok(Selected::Program(program)) => Selected::Program(
+                        self.library
+                            .iter()
+                            .find(|p| p.id == program.id)
+                            .cloned()
+                            .unwrap_or(program),
+                    ),
+                    Ok(Selected::Bottle(bottle)) => {
+                        Selected::Bottle(self.bottle(&bottle.id).cloned().unwrap_or(bottle))
+                    }
+                    Err(why) => {
+                        self.report("Couldn't open the link", why);
+                        return Task::none();
+                    }
+                };
+                tasks.push(self.update(Message::Select(Some(selected))));
+            }
+            Message::Launched(id, result) => match result {
+                Ok(pid) => tasks.push(self.set_status(&id, Status::Running { pid })),
+                Err(why) => {
+                    let title = self.program(&id).map_or(id.clone(), |p| p.title.clone());
+                    self.report(&format!("Couldn't launch {title}"), why);
+                }
+            },
+            Message::Stop(program) => {
+                let supervisor = self.supervisor.clone();
+                tasks.push(perform(async move {
+                    let supervised = supervisor
+                        .running()
+                        .await
+                        .iter()
+                        .any(|(id, _)| *id == program.id);
+                    let result = supervisor.stop(&program.id).await;
+                    Message::Stopped(program.id, result.map(|()| supervised))
+                }));
+            }
+            Message::Stopped(id, result) => match result {
+                // The supervisor reports the program as stopped once it is gone.
+                Ok(true) => {}
+                // No event follows for programs it doesn't know, such as ones whose
+                // process already went away.
+                Ok(false) => tasks.push(self.set_status(&id, Status::Idle)),
+                Err(why) => self.report("Couldn't stop the program", why),
+            },
+            Message::StopBottle(bottle) => {
+                let runner = self.runners.get(&bottle.runner);
+                let supervisor = self.supervisor.clone();
+                tasks.push(perform(async move {
+                    let result = match runner {
+                        Some(runner) => supervisor.stop_bottle(&*runner, &bottle).await,
+                        None => Err(Error::RunnerNotFound(bottle.runner.clone())),
+                    };
+                    Message::BottleStopped(bottle.id, result)
+                }));
+            }
+            Message::BottleStopped(id, result) => {
+                if let Err(why) = result {
+                    self.report("Couldn't stop the bottle", why);
+                }
+                let programs: Vec<String> = self
+                    .library
+                    .iter()
+                    .filter(|program| program.bottle == id)
+                    .map(|program| program.id.clone())
+                    .collect();
+                for program in programs {
+                    tasks.push(self.set_status(&program, Status::Idle));
+                }
+            }
+            Message::ManifestLoaded(manifest) => {
+                tasks.push(to_details(details::Message::SetPackages(
+                    manifest.packages().to_vec(),
+                )));
+                self.manifest = manifest;
+            }
+            Message::LedgerLoaded(id, result) => match result {
+                Ok(ledger) if self.is_selected_bottle(&id) => {
+                    tasks.push(to_details(details::Message::SetLedger(ledger)));
+                }
+                Ok(_) => {}
+                Err(why) => eprintln!("failed to load dependencies: {why}"),
+            },
+            Message::InstallDependency(bottle, id) => {
+                tasks.push(self.dependency_job(bottle, id, true))
+            }
+            Message::UninstallDependency(bottle, id) => {
+                tasks.push(self.dependency_job(bottle, id, false))
+            }
+            Message::DependencyProgress(id, progress, status) => {
+                if self.is_selected_bottle(&id) {
+                    tasks.push(to_details(details::Message::SetDependencyJob(Some(
+                        DependencyJob::Running { progress, status },
+                    ))));
+                }
+            }
+            Message::DependencyDone(bottle, result) => {
+                self.dependency_jobs.remove(&bottle.id);
+                if self.is_selected_bottle(&bottle.id) {
+                    let job = result
+                        .err()
+                        .map(|why| DependencyJob::Failed(why.to_string()));
+                    tasks.push(to_details(details::Message::SetDependencyJob(job)));
+                    tasks.push(self.load_ledger(&bottle));
+                    tasks.push(self.load_disk_usage(&bottle));
+                } else if let Err(why) = result {
+                    self.report(
+                        &format!("Couldn't change the dependencies of {}", bottle.name),
+                        why,
+                    );
+                }
+            }
+            Message::CatalogLoaded(catalog) => {
+                tasks.push(to_details(details::Message::SetReleases(
+                    catalog.releases().to_vec(),
+                )));
+            }
+            Message::InstallComponent(bottle, release) => {
+                tasks.push(self.component_job(bottle, release.kind, Some(release)))
+            }
+            Message::UninstallComponent(bottle, kind) => {
+                tasks.push(self.component_job(bottle, kind, None))
+            }
+            Message::ComponentDone(bottle, result) => {
+                self.dependency_jobs.remove(&bottle.id);
+                if self.is_selected_bottle(&bottle.id) {
+                    let job = result
+                        .as_ref()
+                        .err()
+                        .map(|why| DependencyJob::Failed(why.to_string()));
+                    tasks.push(to_details(details::Message::SetDependencyJob(job)));
+                    tasks.push(self.load_disk_usage(&bottle));
+                } else if let Err(why) = &result {
+                    self.report(
+                        &format!("Couldn't change the components of {}", bottle.name),
+                        why,
+                    );
+                }
+                // A failed change may still have removed the version installed
+                // before, so the bottle is read again either way.
+                let store = self.store.clone();
+                tasks.push(perform(async move {
+                    Message::BottleSaved(store.get(&bottle.id).await)
+                }));
+            }
+            Message::SnapshotsLoaded(id, result) => match result {
+                Ok(snapshots) if self.is_selected_bottle(&id) => {
+                    tasks.push(to_details(details::Message::SetSnapshots(snapshots)));
+                }
+                Ok(_) => {}
+                Err(why) => eprintln!("failed to load snapshots: {why}"),
+            },
+            Message::CreateSnapshot(bottle, description) => tasks.push(self.snapshot_job(
+                bottle,
+                "Creating snapshot...",
+                false,
+                move |bottle, progress| async move {
+                    let snapshots = Snapshots::new(&bottle);
+                    snapshots
+                        .create(&bottle.prefix(), description.trim(), progress)
+                        .await?;
+                    snapshots.prune(bottle.retention).await?;
+                    Ok(())
+                },
+            )),
+            Message::RestoreSnapshot(bottle, snapshot) => {
+                let runner = self.runners.get(&bottle.runner);
+                let supervisor = self.supervisor.clone();
+                tasks.push(self.snapshot_job(
+                    bottle,
+                    "Restoring snapshot...",
+                    true,
+                    move |bottle, progress| async move {
+                        // Nothing may write into the prefix while it is swapped,
+                        // including programs started outside of the application.
+                        let runner =
+                            runner.ok_or_else(|| Error::RunnerNotFound(bottle.runner.clone()))?;
+                        supervisor.stop_bottle(&*runner, &bottle).await?;
+                        Snapshots::new(&bottle)
+                            .restore(&snapshot.id, &bottle.prefix(), progress)
+                            .await
+                    },
+                ))
+            }
+            Message::DeleteSnapshot(bottle, snapshot) => tasks.push(self.snapshot_job(
+                bottle,
+                "Deleting snapshot...",
+                false,
+                move |bottle, _| async move { Snapshots::new(&bottle).delete(&snapshot.id).await },
+            )),
+            Message::DiskUsage(id, result) => match result {
+                Ok(size) if self.is_selected_bottle(&id) => {
+                    tasks.push(to_details(details::Message::SetDiskUsage(Some(size))));
+                }
+                Ok(_) => {}
+                Err(why) => eprintln!("failed to measure bottle: {why}"),
+            },
+            Message::RecipesLoaded(recipes, unfinished) => {
+                self.recipes = recipes;
+                self.unfinished = unfinished;
+            }
+            Message::InstallRecipe(wizard) => tasks.push(self.install_recipe(wizard)),
+            Message::InstallProgress(update) => {
+                if let Some(DialogPage::Install(wizard)) = &mut self.dialog {
+                    wizard.step = InstallStep::Installing {
+                        progress: update.overall(),
+                        status: update.title,
+                    };
+                }
+            }
+            Message::Installed(result) => match result {
+                Ok(_) => {
+                    if let Some(DialogPage::Install(_)) = self.dialog {
+                        self.dialog = None;
+                    }
+                    // Picks up the bottle and the program added by the recipe.
+                    tasks.push(self.load());
+                }
+                Err(why) => {
+                    match &mut self.dialog {
+                        Some(DialogPage::Install(wizard)) => {
+                            wizard.step = InstallStep::Failed(why.to_string());
+                        }
+                        _ => self.report("Couldn't install the program", why),
+                    }
+                    // The installation is now unfinished.
+                    tasks.push(self.load_recipes());
+                }
+            },
+            Message::ScanBottle(bottle) => tasks.push(self.scan_bottle(&bottle)),
+            Message::CancelScan => {
+                self.cancel_scan();
+                tasks.push(to_details(details::Message::SetScan(None)));
+            }
+            Message::Scanned(id, result) => {
+                // Results of a scan that was replaced or cancelled are stale.
+                if self
+                    .scan
+                    .as_ref()
+                    .is_some_and(|(scanning, _)| *scanning == id)
+                {
+                    self.scan = None;
+                    let scan = match result {
+                        Ok(found) => Some(Scan::Found(found)),
+                        Err(Error::Cancelled) => None,
+                        Err(why) => Some(Scan::Failed(why.to_string())),
+                    };
+                    tasks.push(to_details(details::Message::SetScan(scan)));
+                }
+            }
+            Message::AddPrograms(bottle, found) => {
+                let store = self.store.clone();
+                tasks.push(run(move |output| async move {
+                    for discovered in found {
+                        let mut program =
+                            Program::new(&bottle, discovered.title, discovered.executable);
+                        program.arguments = discovered.arguments;
+                        program.working_dir = discovered
+                            .working_dir
+                            .or_else(|| program.executable.parent().map(Path::to_path_buf));
+                        let result = store.save_program(program).await;
+                        let _ = output.send(Message::ProgramSaved(result));
+                    }
+                }));
+            }
+            Message::SnapshotProgress(id, progress, status) => {
+                if self.is_selected_bottle(&id) {
+                    tasks.push(to_details(details::Message::SetSnapshotJob(Some(
+                        SnapshotJob::Running { progress, status },
+                    ))));
+                }
+            }
+            Message::SnapshotDone(bottle, replaced, result) => {
+                self.snapshot_jobs.remove(&bottle.id);
+                if self.is_selected_bottle(&bottle.id) {
+                    let job = result.err().map(|why| SnapshotJob::Failed(why.to_string()));
+                    tasks.push(to_details(details::Message::SetSnapshotJob(job)));
+                    tasks.push(self.load_snapshots(&bottle));
+                    tasks.push(self.load_disk_usage(&bottle));
+                    if replaced {
+                        tasks.push(self.scan_bottle(&bottle));
+                    }
+                } else if let Err(why) = result {
+                    self.report(
+                        &format!("Couldn't update the snapshots of {}", bottle.name),
+                        why,
+                    );
+                }
+            }
+            Message::Process(event) => tasks.push(match event {
+                supervisor::Event::Started { program, pid } => {
+                    self.set_status(&program, Status::Running { pid })
+                }
+                supervisor::Event::Exited { program, code } => {
+                    self.set_status(&program, Status::Exited { code })
+                }
+                supervisor::Event::Crashed { program, signal } => {
+                    self.set_status(&program, Status::Crashed { signal })
+                }
+                supervisor::Event::Stopped { program } => self.set_status(&program, Status::Idle),
+                supervisor::Event::Activity { program, activity } => {
+                    self.update_program(&program, |program| program.activity = activity)
+                }
+            }),
+            Message::EditProgram(program) => {
+                self.dialog = Some(DialogPage::Program(ProgramForm::edit(
+                    &program,
+                    &self.bottles,
+                )));
+            }
+            Message::OpenDialog(page) | Message::UpdateDialog(page) => {
+                self.dialog = Some(page);
+            }
+            Message::CloseDialog => {
+                self.dialog = None;
+            }
+            Message::DismissError => {
+                self.error = None;
+            }
             Message::QueryInput(query) => {
                 self.query = query;
             }
+            Message::FocusSearch => {
+                if self.selected.is_some() {
+                    tasks.push(self.update(Message::Select(None)));
+                }
+                tasks.push(widget::text_input::focus(self.search_id.clone()));
+            }
+            Message::ClearSearch => {
+                // Escape belongs to the dialog while one is shown.
+                if self.dialog.is_none() {
+                    self.query.clear();
+                }
+            }
             Message::ClassicTabActivated(entity) => {
                 self.bottle_tabs_model = None;
                 self.program_tabs_model = None;
                 self.selected = None;
-                tasks.push(cosmic::task::message(app::Message::Details(
-                    crate::pages::details::Message::SetSelected(None),
-                )));
+                self.cancel_scan();
+                tasks.push(to_details(details::Message::SetSelected(None)));
                 self.classic_tabs_model.activate(entity)
             }
             Message::ProgramTabActivated(entity) => {
                 if let Some(ref mut program_tabs_model) = self.program_tabs_model {
-                    program_tabs_model.activate(entity)
+                    program_tabs_model.activate(entity);
+                    if let Some(tab) = program_tabs_model.active_data::<ProgramTab>() {
+                        tasks.push(to_details(details::Message::SetProgramTab(*tab)));
+                    }
                 }
             }
             Message::BottleTabActivated(entity) => {
                 if let Some(ref mut bottle_tabs_model) = self.bottle_tabs_model {
-                    bottle_tabs_model.activate(entity)
+                    bottle_tabs_model.activate(entity);
+                    if let Some(tab) = bottle_tabs_model.active_data::<BottleTab>() {
+                        tasks.push(to_details(details::Message::SetBottleTab(*tab)));
+                    }
                 }
             }
             Message::Select(selected) => {
-                self.selected = selected.clone();
-                tasks.push(cosmic::task::message(app::Message::Details(
-                    crate::pages::details::Message::SetSelected(selected),
-                )));
+                self.selected = selected;
+                tasks.push(self.refresh_details());
+                match self.selected.clone() {
+                    Some(Selected::Bottle(bottle)) => tasks.push(self.scan_bottle(&bottle)),
+                    _ => self.cancel_scan(),
+                }
                 match &self.selected {
-                    Some(Selected::Bottle(_bottle)) => {
+                    Some(Selected::Bottle(bottle)) => {
+                        tasks.push(self.load_snapshots(bottle));
+                        tasks.push(self.load_ledger(bottle));
+                        tasks.push(self.load_disk_usage(bottle));
+                        tasks.push(to_details(details::Message::SetBottleTab(
+                            BottleTab::Programs,
+                        )));
                         self.bottle_tabs_model = Some(
                             Model::builder()
                                 .insert(move |b| {
                                     b.text("Programs").data(BottleTab::Programs).activate()
                                 })
                                 .insert(move |b| b.text("Settings").data(BottleTab::Settings))
+                                .insert(move |b| {
+                                    b.text("Dependencies").data(BottleTab::Dependencies)
+                                })
                                 .insert(move |b| b.text("Snapshots").data(BottleTab::Snapshots))
                                 .build(),
                         )
                     }
                     Some(Selected::Program(program)) => {
+                        tasks.push(to_details(details::Message::SetProgramTab(
+                            ProgramTab::Program,
+                        )));
                         self.program_tabs_model = Some(
                             Model::builder()
                                 .insert(move |b| {
@@ -221,16 +1588,38 @@ impl Home {
     }
 
     pub fn next(&self) -> Element<Message> {
-        self.library_grid()
+        self.with_error(self.library_grid())
     }
 
     pub fn classic(&self) -> Element<Message> {
         let active = self.classic_tabs_model.active_data::<ClassicTab>();
-        if let Some(ClassicTab::Bottles) = active {
+        self.with_error(if let Some(ClassicTab::Bottles) = active {
             self.bottles_grid()
         } else {
             self.library_grid()
-        }
+        })
+    }
+
+    /// Shows the last failure above `content`.
+    fn with_error<'a>(&'a self, content: Element<'a, Message>) -> Element<'a, Message> {
+        let Some(error) = &self.error else {
+            return content;
+        };
+        widget::column()
+            .push(
+                widget::row()
+                    .push(icons::get_icon("issue-symbolic", 18))
+                    .push(widget::text(error.as_str()).width(Length::Fill))
+                    .push(
+                        widget::button::icon(icons::get_handle("window-close-symbolic", 18))
+                            .on_press(Message::DismissError),
+                    )
+                    .align_y(Vertical::Center)
+                    .spacing(10.)
+                    .padding(8.),
+            )
+            .push(content)
+            .into()
     }
 
     fn bottles_grid(&self) -> Element<Message> {
@@ -242,17 +1631,27 @@ impl Home {
             column_spacing,
         } = GridMetrics::new(width, 260 + 2 * spacing.space_s as usize, spacing.space_s);
 
+        let mut bottles: Vec<(u32, &Bottle)> = self
+            .bottles
+            .iter()
+            .filter_map(|bottle| Some((self.bottle_score(bottle)?, bottle)))
+            .collect();
+        if bottles.is_empty() && !self.query.is_empty() {
+            return self.no_results();
+        }
+        bottles.sort_by(|(a, _), (b, _)| b.cmp(a));
+
         let mut grid = widget::grid();
         let mut col = 0;
-        for bottle in self.bottles.iter() {
+        for (_, bottle) in bottles {
             if col >= cols {
                 grid = grid.insert_row();
                 col = 0;
             }
             grid = grid.push(crate::components::button::button(
-                &bottle.title,
-                &bottle.caption,
-                Some(bottle.icon.clone()),
+                &bottle.name,
+                bottle.environment.title(),
+                Some(icons::get_handle(bottle.icon(), 18)),
                 Message::Select(Some(Selected::Bottle(bottle.clone()))),
                 item_width as f32,
             ));
@@ -280,20 +1679,73 @@ impl Home {
             column_spacing,
         } = GridMetrics::new(width, 260 + 2 * spacing.space_s as usize, spacing.space_s);
 
+        let mut library: Vec<(u32, &Program)> = self
+            .library
+            .iter()
+            .filter_map(|program| Some((self.program_score(program)?, program)))
+            .collect();
+        if library.is_empty() && !self.query.is_empty() {
+            return self.no_results();
+        }
+        // Best matches first, then the most recently played. Programs never played
+        // keep their order at the end.
+        library.sort_by(|(score_a, a), (score_b, b)| {
+            score_b
+                .cmp(score_a)
+                .then_with(|| b.activity.last_started.cmp(&a.activity.last_started))
+        });
+
         let mut grid = widget::grid();
         let mut col = 0;
-        for program in self.library.iter() {
+        for (_, program) in library {
             if col >= cols {
                 grid = grid.insert_row();
                 col = 0;
             }
+            let caption = program.caption();
             grid = grid.push(
-                crate::components::card::card(program.into(), item_width)
-                    .on_press(Message::Select(Some(Selected::Program(program.clone())))),
+                crate::components::card::card(
+                    Card::new(&program.title, caption, program.image())
+                        .running(program.status.is_running()),
+                    item_width,
+                    Some(if program.status.is_running() {
+                        Message::Stop(program.clone())
+                    } else {
+                        Message::Launch(program.clone())
+                    }),
+                )
+                .on_press(Message::Select(Some(Selected::Program(program.clone())))),
             );
             col += 1;
         }
 
+        if self.query.is_empty() {
+            if col >= cols {
+                grid = grid.insert_row();
+            }
+            // Programs live in bottles, so offer to create one first.
+            grid = grid.push(if self.bottles.is_empty() {
+                crate::components::button::button(
+                    "New bottle",
+                    "Create an environment for your programs",
+                    Some(icons::get_handle("plus-large-symbolic", 18)),
+                    Message::OpenDialog(DialogPage::NewBottle(NewBottle::default())),
+                    item_width as f32,
+                )
+            } else {
+                crate::components::button::button(
+                    "Add program",
+                    "Register a Windows program",
+                    Some(icons::get_handle("plus-large-symbolic", 18)),
+                    Message::OpenDialog(DialogPage::Program(ProgramForm {
+                        bottle: Some(0),
+                        ..Default::default()
+                    })),
+                    item_width as f32,
+                )
+            });
+        }
+
         widget::container(widget::scrollable(
             grid.column_spacing(column_spacing)
                 .row_spacing(column_spacing),
@@ -306,6 +1758,24 @@ impl Home {
         .into()
     }
 
+    fn no_results(&self) -> Element<Message> {
+        widget::column()
+            .push(
+                widget::text(format!("No results for \u{201c}{}\u{201d}", self.query))
+                    .size(18.)
+                    .font(cosmic::font::bold()),
+            )
+            .push(widget::text::caption("Try searching for something else."))
+            .align_x(Horizontal::Center)
+            .spacing(10.)
+            .apply(widget::container)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_x(Horizontal::Center)
+            .align_y(Vertical::Center)
+            .into()
+    }
+
     pub fn classic_tabs(&self) -> Element<Message> {
         widget::tab_bar::horizontal(&self.classic_tabs_model)
             .width(Length::Shrink)
@@ -318,6 +1788,7 @@ impl Home {
         let icon = icons::get_icon("loupe-large-symbolic", 18).into();
         if self.selected.is_none() {
             widget::text_input("Search for software and games...", &self.query)
+                .id(self.search_id.clone())
                 .width(Length::Shrink)
                 .leading_icon(icon)
                 .on_input(Message::QueryInput)
@@ -337,6 +1808,7 @@ impl Home {
                     .padding(5.)
                     .width(100.),
             )
+            .on_press(Message::FocusSearch)
             .into()
         }
     }
@@ -348,27 +1820,73 @@ impl Home {
     }
 
     pub fn options_button(&self) -> Element<Message> {
-        widget::button::icon(icons::get_handle("view-more-symbolic", 18)).into()
+        widget::button::icon(icons::get_handle("view-more-symbolic", 18))
+            .on_press(Message::OpenDialog(DialogPage::Install(Install::default())))
+            .into()
+    }
+
+    pub fn runners_button(&self) -> Element<Message> {
+        widget::button::icon(icons::get_handle("build-alt-symbolic", 18))
+            .on_press(Message::ManageRunners)
+            .into()
     }
 
     pub fn new_button(&self) -> Element<Message> {
-        widget::button::icon(icons::get_handle("plus-large-symbolic", 18)).into()
+        widget::button::icon(icons::get_handle("plus-large-symbolic", 18))
+            .on_press(Message::OpenDialog(DialogPage::NewBottle(
+                NewBottle::default(),
+            )))
+            .into()
     }
 
     pub fn program_options_button(&self) -> Element<Message> {
-        widget::button::icon(icons::get_handle("view-more-symbolic", 18)).into()
+        let on_press = match &self.selected {
+            Some(Selected::Program(program)) => Some(Message::OpenDialog(DialogPage::Program(
+                ProgramForm::edit(program, &self.bottles),
+            ))),
+            _ => None,
+        };
+        widget::button::icon(icons::get_handle("view-more-symbolic", 18))
+            .on_press_maybe(on_press)
+            .into()
     }
 
     pub fn bottle_options_button(&self) -> Element<Message> {
-        widget::button::icon(icons::get_handle("view-more-symbolic", 18)).into()
+        let on_press = match &self.selected {
+            Some(Selected::Bottle(bottle)) => {
+                Some(Message::OpenDialog(DialogPage::BottleOptions {
+                    id: bottle.id.clone(),
+                    name: bottle.name.clone(),
+                }))
+            }
+            _ => None,
+        };
+        widget::button::icon(icons::get_handle("view-more-symbolic", 18))
+            .on_press_maybe(on_press)
+            .into()
     }
 
     pub fn program_power_button(&self) -> Element<Message> {
-        widget::button::icon(icons::get_handle("power-symbolic", 18)).into()
+        let on_press = match &self.selected {
+            Some(Selected::Program(program)) => self
+                .program(&program.id)
+                .filter(|program| program.status.is_running())
+                .map(|program| Message::Stop(program.clone())),
+            _ => None,
+        };
+        widget::button::icon(icons::get_handle("power-symbolic", 18))
+            .on_press_maybe(on_press)
+            .into()
     }
 
     pub fn bottle_power_button(&self) -> Element<Message> {
-        widget::button::icon(icons::get_handle("power-symbolic", 18)).into()
+        let on_press = match &self.selected {
+            Some(Selected::Bottle(bottle)) => Some(Message::StopBottle(bottle.clone())),
+            _ => None,
+        };
+        widget::button::icon(icons::get_handle("power-symbolic", 18))
+            .on_press_maybe(on_press)
+            .into()
     }
 
     pub fn program_tabs(&self) -> Option<Element<Message>> {
@@ -392,6 +1910,49 @@ impl Home {
     }
 }
 
+/// Reads a program and its bottle from `store`.
+async fn find_program(
+    store: &BottleStore,
+    bottle: &str,
+    program: &str,
+) -> Result<(Bottle, Program)> {
+    let bottle = store.get(bottle).await?;
+    let program = store
+        .programs(&bottle.id)
+        .await?
+        .into_iter()
+        .find(|p| p.id == program)
+        .ok_or_else(|| Error::ProgramNotFound(program.to_string()))?;
+    Ok((bottle, program))
+}
+
+/// Runs `future` in the background and routes its result back to the home page.
+fn perform(future: impl Future<Output = Message> + Send + 'static) -> Task<app::Message> {
+    cosmic::task::future(async move { app::Message::Home(future.await) })
+}
+
+/// Routes `message` to the details pane.
+fn to_details(message: details::Message) -> Task<app::Message> {
+    cosmic::task::message(app::Message::Details(message))
+}
+
+/// Runs `job` in the background, routing every message it sends back to the home
+/// page as soon as it is sent.
+fn run<F>(job: impl FnOnce(mpsc::UnboundedSender<Message>) -> F) -> Task<app::Message>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (output, messages) = mpsc::unbounded_channel();
+    let job = futures_util::stream::once(job(output)).filter_map(|()| async { None::<Message> });
+    let messages = futures_util::stream::unfold(messages, |mut messages| async move {
+        messages.recv().await.map(|message| (message, messages))
+    });
+
+    Task::run(futures_util::stream::select(job, messages), |message| {
+        cosmic::app::Message::App(app::Message::Home(message))
+    })
+}
+
 pub struct GridMetrics {
     pub cols: usize,
     pub item_width: usize,
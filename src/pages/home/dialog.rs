@@ -0,0 +1,665 @@
+use bottles_core::{
+    bottle::{self, Bottle, Environment},
+    program::{self, Program},
+    runner::repository::Release,
+    snapshot::Snapshot,
+};
+use cosmic::{
+    iced::{Alignment, Length},
+    widget::{self, dialog::Dialog},
+    Apply, Element,
+};
+
+use super::{Home, Message};
+use crate::{i18n, icons};
+
+#[derive(Clone, Debug)]
+pub enum DialogPage {
+    BottleOptions { id: String, name: String },
+    DeleteBottle { id: String, name: String },
+    Program(ProgramForm),
+    NewBottle(NewBottle),
+    NewSnapshot { bottle: Bottle, description: String },
+    RestoreSnapshot { bottle: Bottle, snapshot: Snapshot },
+    DeleteSnapshot { bottle: Bottle, snapshot: Snapshot },
+    Install(Install),
+    Runners(ManageRunners),
+}
+
+/// Fields of the dialog used to add or edit a program.
+#[derive(Clone, Debug, Default)]
+pub struct ProgramForm {
+    /// The program being edited, or `None` when adding a new one.
+    pub program: Option<Program>,
+    pub bottle: Option<usize>,
+    pub title: String,
+    pub executable: String,
+    pub arguments: String,
+    pub working_dir: String,
+    pub cover: String,
+}
+
+impl ProgramForm {
+    pub(super) fn edit(program: &Program, bottles: &[Bottle]) -> Self {
+        Self {
+            program: Some(program.clone()),
+            bottle: bottles.iter().position(|b| b.id == program.bottle),
+            title: program.title.clone(),
+            executable: program.executable.display().to_string(),
+            arguments: program::join_arguments(&program.arguments),
+            working_dir: path_to_string(program.working_dir.as_ref()),
+            cover: path_to_string(program.cover.as_ref()),
+        }
+    }
+
+    /// Builds the program described by the form, if the form is complete.
+    fn program(&self, bottles: &[Bottle]) -> Option<Program> {
+        let title = self.title.trim();
+        let executable = self.executable.trim();
+        if title.is_empty() || executable.is_empty() {
+            return None;
+        }
+
+        let bottle = bottles.get(self.bottle?)?;
+        let mut program = match &self.program {
+            Some(program) => program.clone(),
+            None => Program::new(&bottle.id, title, executable.into()),
+        };
+        program.title = title.to_string();
+        program.executable = executable.into();
+        program.arguments = program::split_arguments(&self.arguments);
+        program.working_dir = string_to_path(&self.working_dir);
+        program.cover = string_to_path(&self.cover);
+        Some(program)
+    }
+}
+
+/// State of the create-bottle wizard.
+#[derive(Clone, Debug, Default)]
+pub struct NewBottle {
+    pub step: NewBottleStep,
+    pub name: String,
+    pub environment: Environment,
+    /// Index of the chosen runner in [`Runners`](bottles_core::runner::Runners).
+    pub runner: Option<usize>,
+    pub icon: Option<&'static str>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum NewBottleStep {
+    #[default]
+    Name,
+    Environment,
+    Runner,
+    Icon,
+    Creating {
+        progress: f32,
+        status: String,
+    },
+    Failed(String),
+}
+
+impl NewBottle {
+    fn at(&self, step: NewBottleStep) -> Self {
+        Self {
+            step,
+            ..self.clone()
+        }
+    }
+}
+
+/// State of the wizard installing a recipe.
+#[derive(Clone, Debug, Default)]
+pub struct Install {
+    pub step: InstallStep,
+    /// Index of the chosen recipe in the recipes of the home page.
+    pub recipe: Option<usize>,
+    /// Index of the chosen bottle, 0 being a new bottle and the others the
+    /// bottles in the same order as the bottles of the home page.
+    pub bottle: usize,
+    /// Index of the runner of a new bottle in [`Runners`](bottles_core::runner::Runners).
+    pub runner: Option<usize>,
+    /// Name of the bottle of an unfinished installation resumed instead of the
+    /// bottle that was chosen.
+    pub resumed: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum InstallStep {
+    #[default]
+    Recipe,
+    Bottle,
+    Installing {
+        progress: f32,
+        status: String,
+    },
+    Failed(String),
+}
+
+impl Install {
+    fn at(&self, step: InstallStep) -> Self {
+        Self {
+            step,
+            ..self.clone()
+        }
+    }
+}
+
+/// State of the dialog downloading and removing runners.
+#[derive(Clone, Debug, Default)]
+pub struct ManageRunners {
+    /// Releases listed by the runner index, `None` until it is fetched.
+    pub releases: Option<Vec<Release>>,
+    /// Ids of the runners in the runners folder.
+    pub installed: Vec<String>,
+    pub job: Option<RunnerJob>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunnerJob {
+    Running { progress: f32, status: String },
+    Failed(String),
+}
+
+fn path_to_string(path: Option<&std::path::PathBuf>) -> String {
+    path.map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
+fn string_to_path(value: &str) -> Option<std::path::PathBuf> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.into())
+}
+
+impl Home {
+    pub fn dialog(&self) -> Option<Element<Message>> {
+        let page = self.dialog.as_ref()?;
+        let dialog = match page {
+            DialogPage::BottleOptions { id, name } => {
+                let rename = (!name.trim().is_empty())
+                    .then(|| Message::RenameBottle(id.clone(), name.trim().to_string()));
+                widget::dialog()
+                    .title("Bottle options")
+                    .control(
+                        widget::text_input("Name", name.as_str()).on_input(move |name| {
+                            Message::UpdateDialog(DialogPage::BottleOptions {
+                                id: id.clone(),
+                                name,
+                            })
+                        }),
+                    )
+                    .primary_action(widget::button::suggested("Save").on_press_maybe(rename))
+                    .secondary_action(
+                        widget::button::standard("Cancel").on_press(Message::CloseDialog),
+                    )
+                    .tertiary_action(widget::button::destructive("Delete").on_press(
+                        Message::OpenDialog(DialogPage::DeleteBottle {
+                            id: id.clone(),
+                            name: name.clone(),
+                        }),
+                    ))
+            }
+            DialogPage::DeleteBottle { id, name } => widget::dialog()
+                .title(format!("Delete {name}?"))
+                .body("The bottle and everything installed in it will be removed permanently.")
+                .primary_action(
+                    widget::button::destructive("Delete")
+                        .on_press(Message::DeleteBottle(id.clone())),
+                )
+                .secondary_action(
+                    widget::button::standard("Cancel").on_press(Message::CloseDialog),
+                ),
+            DialogPage::Program(form) => {
+                let update = |form: ProgramForm| Message::UpdateDialog(DialogPage::Program(form));
+                let mut fields = widget::column().spacing(10.);
+
+                if form.program.is_none() {
+                    fields = fields.push(widget::dropdown(
+                        &self.bottle_names,
+                        form.bottle,
+                        move |bottle| {
+                            update(ProgramForm {
+                                bottle: Some(bottle),
+                                ..form.clone()
+                            })
+                        },
+                    ));
+                }
+
+                let fields = fields
+                    .push(
+                        widget::text_input("Title", form.title.as_str()).on_input(move |title| {
+                            update(ProgramForm {
+                                title,
+                                ..form.clone()
+                            })
+                        }),
+                    )
+                    .push(
+                        widget::text_input("Executable", form.executable.as_str()).on_input(
+                            move |executable| {
+                                update(ProgramForm {
+                                    executable,
+                                    ..form.clone()
+                                })
+                            },
+                        ),
+                    )
+                    .push(
+                        widget::text_input("Arguments", form.arguments.as_str()).on_input(
+                            move |arguments| {
+                                update(ProgramForm {
+                                    arguments,
+                                    ..form.clone()
+                                })
+                            },
+                        ),
+                    )
+                    .push(
+                        widget::text_input("Working directory", form.working_dir.as_str())
+                            .on_input(move |working_dir| {
+                                update(ProgramForm {
+                                    working_dir,
+                                    ..form.clone()
+                                })
+                            }),
+                    )
+                    .push(
+                        widget::text_input("Cover image", form.cover.as_str()).on_input(
+                            move |cover| {
+                                update(ProgramForm {
+                                    cover,
+                                    ..form.clone()
+                                })
+                            },
+                        ),
+                    );
+
+                let save = form.program(&self.bottles).map(Message::SaveProgram);
+                let mut dialog = widget::dialog()
+                    .title(if form.program.is_some() {
+                        "Edit program"
+                    } else {
+                        "Add program"
+                    })
+                    .control(fields)
+                    .primary_action(widget::button::suggested("Save").on_press_maybe(save))
+                    .secondary_action(
+                        widget::button::standard("Cancel").on_press(Message::CloseDialog),
+                    );
+                if let Some(program) = &form.program {
+                    dialog = dialog.tertiary_action(
+                        widget::button::destructive("Remove")
+                            .on_press(Message::RemoveProgram(program.clone())),
+                    );
+                }
+                dialog
+            }
+            DialogPage::NewBottle(wizard) => self.new_bottle_dialog(wizard),
+            DialogPage::NewSnapshot {
+                bottle,
+                description,
+            } => widget::dialog()
+                .title("New snapshot")
+                .body("Describe the state of the bottle to find the snapshot later.")
+                .control(
+                    widget::text_input("Description", description.as_str()).on_input(
+                        move |description| {
+                            Message::UpdateDialog(DialogPage::NewSnapshot {
+                                bottle: bottle.clone(),
+                                description,
+                            })
+                        },
+                    ),
+                )
+                .primary_action(
+                    widget::button::suggested("Create")
+                        .on_press(Message::CreateSnapshot(bottle.clone(), description.clone())),
+                )
+                .secondary_action(
+                    widget::button::standard("Cancel").on_press(Message::CloseDialog),
+                ),
+            DialogPage::RestoreSnapshot { bottle, snapshot } => widget::dialog()
+                .title(format!("Restore {}?", bottle.name))
+                .body(
+                    "Programs running in the bottle will be stopped and every change made \
+                     since the snapshot will be lost.",
+                )
+                .primary_action(
+                    widget::button::destructive("Restore")
+                        .on_press(Message::RestoreSnapshot(bottle.clone(), snapshot.clone())),
+                )
+                .secondary_action(
+                    widget::button::standard("Cancel").on_press(Message::CloseDialog),
+                ),
+            DialogPage::DeleteSnapshot { bottle, snapshot } => widget::dialog()
+                .title("Delete snapshot?")
+                .body("The bottle won't be able to go back to this snapshot anymore.")
+                .primary_action(
+                    widget::button::destructive("Delete")
+                        .on_press(Message::DeleteSnapshot(bottle.clone(), snapshot.clone())),
+                )
+                .secondary_action(
+                    widget::button::standard("Cancel").on_press(Message::CloseDialog),
+                ),
+            DialogPage::Install(wizard) => self.install_dialog(wizard),
+            DialogPage::Runners(manager) => self.runners_dialog(manager),
+        };
+        Some(dialog.into())
+    }
+
+    fn new_bottle_dialog<'a>(&'a self, wizard: &'a NewBottle) -> Dialog<'a, Message> {
+        let update = |wizard: NewBottle| Message::UpdateDialog(DialogPage::NewBottle(wizard));
+        let back = |step: NewBottleStep| {
+            widget::button::standard("Back").on_press(update(wizard.at(step)))
+        };
+        let cancel = widget::button::standard("Cancel").on_press(Message::CloseDialog);
+        let dialog = widget::dialog().title("New bottle");
+
+        match &wizard.step {
+            NewBottleStep::Name => {
+                let next = (!wizard.name.trim().is_empty())
+                    .then(|| update(wizard.at(NewBottleStep::Environment)));
+                dialog
+                    .body("Choose a name for the bottle.")
+                    .control(widget::text_input("Name", wizard.name.as_str()).on_input(
+                        move |name| {
+                            update(NewBottle {
+                                name,
+                                ..wizard.clone()
+                            })
+                        },
+                    ))
+                    .primary_action(widget::button::suggested("Next").on_press_maybe(next))
+                    .secondary_action(cancel)
+            }
+            NewBottleStep::Environment => {
+                let environments = Environment::ALL.map(|environment| {
+                    crate::components::button::button(
+                        environment.title(),
+                        environment.description(),
+                        Some(icons::get_handle(environment.icon(), 18)),
+                        update(NewBottle {
+                            environment,
+                            step: NewBottleStep::Runner,
+                            ..wizard.clone()
+                        }),
+                        Length::Fill,
+                    )
+                    .into()
+                });
+                dialog
+                    .body("Pick the environment that suits what you are going to run.")
+                    .control(widget::column().extend(environments).spacing(10.))
+                    .primary_action(back(NewBottleStep::Name))
+                    .secondary_action(cancel)
+            }
+            NewBottleStep::Runner => {
+                let control: Element<Message> = if self.runner_names.is_empty() {
+                    widget::text(
+                        "No runners were found. Install Wine or add a runner to create a bottle.",
+                    )
+                    .into()
+                } else {
+                    widget::dropdown(&self.runner_names, wizard.runner, move |runner| {
+                        update(NewBottle {
+                            runner: Some(runner),
+                            ..wizard.clone()
+                        })
+                    })
+                    .into()
+                };
+                let next = wizard
+                    .runner
+                    .map(|_| update(wizard.at(NewBottleStep::Icon)));
+                dialog
+                    .body("Choose the runner used to start programs in this bottle.")
+                    .control(control)
+                    .primary_action(widget::button::suggested("Next").on_press_maybe(next))
+                    .secondary_action(back(NewBottleStep::Environment))
+                    .tertiary_action(cancel)
+            }
+            NewBottleStep::Icon => {
+                let selected = wizard.icon.unwrap_or(wizard.environment.icon());
+                let icons = bottle::ICONS.map(|icon| {
+                    widget::button::icon(icons::get_handle(icon, 18))
+                        .class(if icon == selected {
+                            cosmic::style::Button::Suggested
+                        } else {
+                            cosmic::style::Button::Icon
+                        })
+                        .on_press(update(NewBottle {
+                            icon: Some(icon),
+                            ..wizard.clone()
+                        }))
+                        .into()
+                });
+                dialog
+                    .body("Choose an icon for the bottle.")
+                    .control(widget::row().extend(icons).spacing(10.))
+                    .primary_action(
+                        widget::button::suggested("Create")
+                            .on_press(Message::CreateBottle(wizard.clone())),
+                    )
+                    .secondary_action(back(NewBottleStep::Runner))
+                    .tertiary_action(cancel)
+            }
+            NewBottleStep::Creating { progress, status } => dialog
+                .body(status.as_str())
+                .control(widget::progress_bar(0.0..=1.0, *progress)),
+            NewBottleStep::Failed(why) => dialog
+                .body(format!("The bottle couldn't be created: {why}"))
+                .primary_action(
+                    widget::button::suggested("Try again")
+                        .on_press(Message::CreateBottle(wizard.clone())),
+                )
+                .secondary_action(back(NewBottleStep::Name))
+                .tertiary_action(cancel),
+        }
+    }
+
+    fn install_dialog<'a>(&'a self, wizard: &'a Install) -> Dialog<'a, Message> {
+        let update = |wizard: Install| Message::UpdateDialog(DialogPage::Install(wizard));
+        let cancel = widget::button::standard("Cancel").on_press(Message::CloseDialog);
+        let recipe = wizard.recipe.and_then(|index| self.recipes.get(index));
+        let dialog = widget::dialog().title(match recipe {
+            Some(recipe) => format!("Install {}", recipe.name),
+            None => "Install".to_string(),
+        });
+
+        match &wizard.step {
+            InstallStep::Recipe => {
+                let control: Element<Message> = if self.recipes.is_empty() {
+                    widget::text("No recipes were found.").into()
+                } else {
+                    let recipes = self.recipes.iter().enumerate().map(|(index, recipe)| {
+                        let chosen = Install {
+                            recipe: Some(index),
+                            ..wizard.clone()
+                        };
+                        // Unfinished installations resume in the bottle they started in.
+                        let (caption, message) = if self.unfinished.contains_key(&recipe.id) {
+                            (
+                                "Resume the unfinished installation",
+                                Message::InstallRecipe(chosen),
+                            )
+                        } else {
+                            (
+                                recipe.description.as_str(),
+                                update(chosen.at(InstallStep::Bottle)),
+                            )
+                        };
+                        crate::components::button::button(
+                            &recipe.name,
+                            caption,
+                            Some(icons::get_handle("magic-wand-symbolic", 18)),
+                            message,
+                            Length::Fill,
+                        )
+                        .into()
+                    });
+                    widget::column().extend(recipes).spacing(10.).into()
+                };
+                dialog
+                    .body("Choose what to install. Everything it needs is set up along with it.")
+                    .control(control)
+                    .primary_action(cancel)
+            }
+            InstallStep::Bottle => {
+                let mut fields = widget::column().spacing(10.).push(widget::dropdown(
+                    &self.target_names,
+                    Some(wizard.bottle),
+                    move |bottle| {
+                        update(Install {
+                            bottle,
+                            ..wizard.clone()
+                        })
+                    },
+                ));
+                if wizard.bottle == 0 {
+                    if self.runner_names.is_empty() {
+                        fields = fields.push(widget::text(
+                            "No runners were found. Install Wine or add a runner to create a bottle.",
+                        ));
+                    } else {
+                        fields = fields.push(widget::dropdown(
+                            &self.runner_names,
+                            wizard.runner,
+                            move |runner| {
+                                update(Install {
+                                    runner: Some(runner),
+                                    ..wizard.clone()
+                                })
+                            },
+                        ));
+                    }
+                }
+                let ready = wizard.bottle > 0 || wizard.runner.is_some();
+                let install = ready.then(|| Message::InstallRecipe(wizard.clone()));
+                dialog
+                    .body("Choose the bottle to install in, or the runner of a new one.")
+                    .control(fields)
+                    .primary_action(widget::button::suggested("Install").on_press_maybe(install))
+                    .secondary_action(
+                        widget::button::standard("Back")
+                            .on_press(update(wizard.at(InstallStep::Recipe))),
+                    )
+                    .tertiary_action(cancel)
+            }
+            InstallStep::Installing { progress, status } => dialog
+                .body(match &wizard.resumed {
+                    Some(bottle) => format!(
+                        "{status}\n\nAn unfinished installation is resumed in {bottle} instead \
+                         of the chosen bottle."
+                    ),
+                    None => status.clone(),
+                })
+                .control(widget::progress_bar(0.0..=1.0, *progress)),
+            InstallStep::Failed(why) => dialog
+                .body(format!(
+                    "The installation failed: {why}\n\nTrying again picks up where it stopped."
+                ))
+                .primary_action(
+                    widget::button::suggested("Try again")
+                        .on_press(Message::InstallRecipe(wizard.clone())),
+                )
+                .secondary_action(cancel),
+        }
+    }
+
+    fn runners_dialog<'a>(&'a self, manager: &'a ManageRunners) -> Dialog<'a, Message> {
+        let spacing = cosmic::theme::active().cosmic().spacing;
+        let running = matches!(manager.job, Some(RunnerJob::Running { .. }));
+        let row = |title: String, caption: String, action: Element<'a, Message>| {
+            widget::row()
+                .push(
+                    widget::column()
+                        .push(widget::text(title))
+                        .push(widget::text::caption(caption)),
+                )
+                .push(widget::horizontal_space())
+                .push(action)
+                .align_y(Alignment::Center)
+                .spacing(spacing.space_xs)
+                .padding(spacing.space_xs)
+                .apply(widget::container)
+                .class(cosmic::style::Container::Card)
+        };
+
+        let mut available = widget::column()
+            .push(widget::text("Available").size(18.))
+            .spacing(spacing.space_xs);
+        match &manager.releases {
+            None => {
+                available = available.push(widget::text::caption("Loading the runner index..."))
+            }
+            Some(releases) if releases.is_empty() => {
+                available = available.push(widget::text::caption("No runners are available."))
+            }
+            Some(releases) => {
+                for release in releases {
+                    let caption = match release.size {
+                        Some(size) => {
+                            format!("{} \u{b7} {}", release.version, i18n::file_size(size))
+                        }
+                        None => release.version.clone(),
+                    };
+                    let action: Element<Message> = if manager.installed.contains(&release.id) {
+                        widget::text::caption("Installed").into()
+                    } else {
+                        widget::button::suggested("Install")
+                            .on_press_maybe(
+                                (!running).then(|| Message::InstallRunner(release.clone())),
+                            )
+                            .into()
+                    };
+                    available = available.push(row(release.name.clone(), caption, action));
+                }
+            }
+        }
+
+        let mut installed = widget::column()
+            .push(widget::text("Installed").size(18.))
+            .spacing(spacing.space_xs);
+        if manager.installed.is_empty() {
+            installed = installed.push(widget::text::caption("No runners were downloaded yet."));
+        }
+        for id in &manager.installed {
+            let version = self
+                .runners
+                .get(id)
+                .and_then(|runner| runner.version().map(str::to_string))
+                .unwrap_or_default();
+            let remove = widget::button::icon(icons::get_handle("user-trash-symbolic", 18))
+                .on_press_maybe((!running).then(|| Message::RemoveRunner(id.clone())));
+            installed = installed.push(row(id.clone(), version, remove.into()));
+        }
+
+        let job: Option<Element<Message>> = manager.job.as_ref().map(|job| match job {
+            RunnerJob::Running { progress, status } => widget::column()
+                .push(widget::text::caption(status.as_str()))
+                .push(widget::progress_bar(0.0..=1.0, *progress))
+                .spacing(5.)
+                .into(),
+            RunnerJob::Failed(why) => widget::text::caption(why.as_str()).into(),
+        });
+
+        widget::dialog()
+            .title("Runners")
+            .body("Runners start Windows programs. Bottles use the runner chosen when they are created.")
+            .control(
+                widget::column()
+                    .push_maybe(job)
+                    .push(available)
+                    .push(installed)
+                    .spacing(spacing.space_s)
+                    .apply(widget::scrollable)
+                    .height(Length::Fixed(400.)),
+            )
+            .primary_action(widget::button::standard("Close").on_press(Message::CloseDialog))
+            .secondary_action(
+                widget::button::destructive("Remove unused")
+                    .on_press_maybe((!running).then_some(Message::PruneRunners)),
+            )
+    }
+}
@@ -0,0 +1,31 @@
+use bottles_core::program::Program;
+use cosmic::widget::image;
+
+use crate::{fl, i18n};
+
+/// How a program is shown in the library and the details pane.
+pub trait ProgramExt {
+    /// The status of the program, or when it was last played.
+    fn caption(&self) -> String;
+
+    /// The thumbnail of the cover, or the icon of the program while there is none.
+    fn image(&self) -> image::Handle;
+}
+
+impl ProgramExt for Program {
+    fn caption(&self) -> String {
+        self.status.caption().unwrap_or_else(|| {
+            self.activity
+                .last_started
+                .map(i18n::relative_time)
+                .unwrap_or_else(|| fl!("never-played"))
+        })
+    }
+
+    fn image(&self) -> image::Handle {
+        match self.thumbnail.as_ref().or(self.icon.as_ref()) {
+            Some(cover) => image::Handle::from_path(cover),
+            None => image::Handle::from_bytes(vec![]),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Fuzzy matching used by the search bar.
+
+/// Scores how well `query` matches `text`, ignoring case and whitespace in the query.
+///
+/// Every character of the query must appear in `text` in the same order. Matches at
+/// the start of words, runs of consecutive characters and plain substrings rank
+/// higher. Returns `None` when `text` doesn't match, and `Some(0)` for an empty query.
+pub fn score(query: &str, text: &str) -> Option<u32> {
+    let needle: Vec<char> = query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    let haystack: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut matched = 0;
+    let mut previous: Option<usize> = None;
+
+    for (i, c) in haystack.iter().enumerate() {
+        if matched == needle.len() {
+            break;
+        }
+        if *c != needle[matched] {
+            continue;
+        }
+
+        score += 1;
+        if i == 0 || !haystack[i - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if previous.is_some_and(|previous| previous + 1 == i) {
+            score += 5;
+        }
+        previous = Some(i);
+        matched += 1;
+    }
+
+    if matched < needle.len() {
+        return None;
+    }
+
+    let query = query.trim().to_lowercase();
+    let text = text.to_lowercase();
+    if text.starts_with(&query) {
+        score += 30;
+    } else if text.contains(&query) {
+        score += 20;
+    }
+    Some(score)
+}
+
+/// The best score of `query` against any of `texts`.
+pub fn best<'a>(query: &str, texts: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+    texts
+        .into_iter()
+        .filter_map(|text| score(query, text))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The title, caption and bottle name of a program, as the library searches them.
+    const PROGRAM: [&str; 3] = ["Overwatch 2", "Crashed (signal 11)", "Battle.net"];
+
+    #[test]
+    fn programs_match_on_any_field() {
+        for query in ["overwatch", "crashed", "battle", "OW 2"] {
+            assert!(best(query, PROGRAM).is_some(), "{query}");
+        }
+        assert_eq!(best("steam", PROGRAM), None);
+        assert_eq!(best("", PROGRAM), Some(0));
+    }
+
+    #[test]
+    fn characters_must_match_in_order() {
+        assert!(score("nfs", "Need for Speed").is_some());
+        assert_eq!(score("sfn", "Need for Speed"), None);
+        assert_eq!(score("need speed heat", "Need for Speed"), None);
+    }
+
+    #[test]
+    fn prefixes_and_contiguous_matches_rank_first() {
+        let prefix = score("war", "Warframe").unwrap();
+        let substring = score("war", "Star Wars").unwrap();
+        let scattered = score("war", "Wild Arcade Racer").unwrap();
+        assert!(prefix > substring, "{prefix} > {substring}");
+        assert!(substring > scattered, "{substring} > {scattered}");
+
+        let contiguous = score("frame", "Warframe").unwrap();
+        let scattered = score("frame", "Far Remote Game").unwrap();
+        assert!(contiguous > scattered, "{contiguous} > {scattered}");
+    }
+}
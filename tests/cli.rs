@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use bottles_core::program::Program;
+use bottles_core::runner::{Fake, FAKE_RUNNER_ENV};
+use bottles_core::store::BottleStore;
+use serde_json::Value;
+
+/// Runs the command line with its data in `home`, the fake runner running
+/// `script` for programs and succeeding right away for the tools of Wine.
+fn bottles(home: &Path, script: &str, args: &[&str]) -> Output {
+    command(home, script, args).output().unwrap()
+}
+
+fn command(home: &Path, script: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_bottles-next"));
+    command
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env(
+            FAKE_RUNNER_ENV,
+            format!(r#"case "$FAKE_EXECUTABLE" in winecfg|wineboot|reg) exit 0;; esac; {script}"#),
+        );
+    command
+}
+
+fn json(bytes: &[u8]) -> Value {
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|why| panic!("{why}: {}", String::from_utf8_lossy(bytes)))
+}
+
+fn keys(value: &Value) -> Vec<&str> {
+    let mut keys: Vec<&str> = value
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// Creates a bottle named `name` with a single program, returning their ids.
+async fn setup(home: &Path, name: &str) -> (String, String) {
+    let output = bottles(
+        home,
+        "exit 0",
+        &["new", name, "--runner", Fake::ID, "--json"],
+    );
+    assert!(output.status.success(), "{output:?}");
+    let bottle = json(&output.stdout)["id"].as_str().unwrap().to_string();
+
+    let store = BottleStore::new(home.join("data/bottles-next/bottles"));
+    let program = Program::new(&bottle, "Game", PathBuf::from("C:\\game.exe"));
+    let program = store.save_program(program).await.unwrap();
+    (bottle, program.id)
+}
+
+#[tokio::test]
+async fn bottles_and_programs_are_listed() {
+    let home = tempfile::tempdir().unwrap();
+    let (bottle, program) = setup(home.path(), "Games").await;
+
+    let output = bottles(home.path(), "exit 0", &["list", "--json"]);
+    assert!(output.status.success(), "{output:?}");
+    let list = json(&output.stdout);
+    let [listed] = list.as_array().unwrap().as_slice() else {
+        panic!("expected a single bottle: {list}");
+    };
+    assert_eq!(
+        keys(listed),
+        ["created", "environment", "id", "name", "programs", "runner"]
+    );
+    assert_eq!(listed["id"], bottle.as_str());
+    assert_eq!(listed["name"], "Games");
+    assert_eq!(listed["environment"], "Gaming");
+    assert_eq!(listed["runner"], Fake::ID);
+    assert!(listed["created"].is_string());
+
+    let [listed] = listed["programs"].as_array().unwrap().as_slice() else {
+        panic!("expected a single program: {list}");
+    };
+    assert_eq!(
+        keys(listed),
+        ["executable", "id", "last_played", "play_time", "title"]
+    );
+    assert_eq!(listed["id"], program.as_str());
+    assert_eq!(listed["title"], "Game");
+    assert_eq!(listed["executable"], "C:\\game.exe");
+    assert_eq!(listed["play_time"], 0);
+    assert!(listed["last_played"].is_null());
+}
+
+#[tokio::test]
+async fn programs_exit_with_their_code() {
+    let home = tempfile::tempdir().unwrap();
+    let (_, program) = setup(home.path(), "Games").await;
+
+    let output = bottles(home.path(), "exit 3", &["run", "Games", "Game", "--json"]);
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    let result = json(&output.stdout);
+    assert_eq!(keys(&result), ["code", "pid", "program"]);
+    assert_eq!(result["program"], program.as_str());
+    assert_eq!(result["code"], 3);
+    assert!(result["pid"].as_u64().is_some_and(|pid| pid > 0));
+
+    // The play time was recorded before the command returned.
+    let output = bottles(home.path(), "exit 0", &["list", "--json"]);
+    let list = json(&output.stdout);
+    assert!(list[0]["programs"][0]["last_played"].is_string());
+}
+
+#[tokio::test]
+async fn failures_are_reported_as_json() {
+    let home = tempfile::tempdir().unwrap();
+    setup(home.path(), "Games").await;
+
+    for args in [
+        &["run", "Games", "Nothing", "--json"][..],
+        &["run", "Nothing", "Game", "--json"],
+        &["kill", "Nothing", "--json"],
+        &["new", "Other", "--runner", "missing", "--json"],
+    ] {
+        let output = bottles(home.path(), "exit 0", args);
+        assert_eq!(output.status.code(), Some(1), "{args:?}: {output:?}");
+        assert!(output.stdout.is_empty(), "{args:?}: {output:?}");
+        let error = json(&output.stderr);
+        assert_eq!(keys(&error), ["error"], "{args:?}");
+        assert!(error["error"].as_str().is_some_and(|why| !why.is_empty()));
+    }
+
+    // Without --json, errors are for people to read.
+    let output = bottles(home.path(), "exit 0", &["kill", "Nothing"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+}
+
+#[tokio::test]
+async fn new_bottles_use_the_default_runner() {
+    let home = tempfile::tempdir().unwrap();
+    // Only the fake runner can be found without Wine on the path.
+    let path = home.path().join("bin");
+    std::fs::create_dir_all(&path).unwrap();
+    std::os::unix::fs::symlink("/bin/sh", path.join("sh")).unwrap();
+
+    let output = command(home.path(), "exit 0", &["new", "Games", "--json"])
+        .env("PATH", &path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(json(&output.stdout)["runner"], Fake::ID);
+}
+
+#[tokio::test]
+async fn ambiguous_names_are_refused() {
+    let home = tempfile::tempdir().unwrap();
+    let (first, _) = setup(home.path(), "Games").await;
+    let (second, program) = setup(home.path(), "games").await;
+    let store = BottleStore::new(home.path().join("data/bottles-next/bottles"));
+    let copy = Program::new(&second, "GAME", PathBuf::from("C:\\copy.exe"));
+    let copy = store.save_program(copy).await.unwrap();
+
+    for (args, ids) in [
+        (["run", "Games", "Game"], [&first, &second]),
+        (["run", &second, "game"], [&program, &copy.id]),
+    ] {
+        let output = bottles(home.path(), "exit 0", &[&args[..], &["--json"]].concat());
+        assert_eq!(output.status.code(), Some(1), "{args:?}: {output:?}");
+        let error = json(&output.stderr)["error"].as_str().unwrap().to_string();
+        for id in ids {
+            assert!(error.contains(id.as_str()), "{args:?}: {error}");
+        }
+    }
+
+    // Ids are never ambiguous.
+    let output = bottles(home.path(), "exit 0", &["run", &second, &copy.id, "--json"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(json(&output.stdout)["program"], copy.id.as_str());
+}